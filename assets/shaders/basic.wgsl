@@ -1,3 +1,5 @@
+#include "view.wgsl"
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     // @location(1) color_index: u32,
@@ -8,15 +10,21 @@ struct VertexOutput {
     @location(0) color_index: u32,
 }
 
-struct PushConstans {
-    camera_mvp: mat4x4<f32>,
-}
-
 struct Color {
     inner: vec3<f32>,
 }
 
-var<push_constant> constants: PushConstans;
+struct Object {
+    model: mat4x4<f32>,
+    base_color: vec4<f32>,
+    emissive: vec3<f32>,
+    // zero is left for the background of the picking target
+    id: u32,
+    alpha_cutoff: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> object: Object;
 // @group(0) @binding(0)
 // var<storage, read> colors: array<Color>;
 
@@ -26,7 +34,7 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     // out.clip_position = camera * vec4<f32>(in.position, 1.0);
-    out.clip_position = constants.camera_mvp * vec4<f32>(in.position, 1.0);
+    out.clip_position = view.camera_mvp * object.model * vec4<f32>(in.position, 1.0);
     // out.color_index = in.color_index;
     return out;
 }
@@ -34,5 +42,26 @@ fn vs_main(
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     // return vec4<f32>(sqrt(colors[in.color_index].inner), 1.0);
-    return vec4<f32>(0.5, 0.8, 0.3, 1.0);
+    // return vec4<f32>(0.5, 0.8, 0.3, 1.0);
+    var color = object.base_color;
+#ifdef ALPHA_MASK
+    if color.a < object.alpha_cutoff {
+        discard;
+    }
+    color.a = 1.0;
+#endif
+    // premultiplied for blending, opaque colors have an alpha of one,
+    // emitted light is not covered by the alpha
+    return vec4<f32>(color.rgb * color.a + object.emissive, color.a);
+}
+
+@fragment
+fn fs_pick(in: VertexOutput) -> @location(0) u32 {
+#ifdef ALPHA_MASK
+    // the holes of masked materials are not picked
+    if object.base_color.a < object.alpha_cutoff {
+        discard;
+    }
+#endif
+    return object.id;
 }
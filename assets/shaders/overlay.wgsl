@@ -0,0 +1,40 @@
+struct VertexInput {
+    // window pixels, origin at the top left
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct PushConstants {
+    screen_size: vec2<f32>,
+}
+
+var<push_constant> constants: PushConstants;
+@group(0) @binding(0)
+var font_atlas: texture_2d<f32>;
+@group(0) @binding(1)
+var font_sampler: sampler;
+
+@vertex
+fn vs_main(
+    in: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let ndc = in.position / constants.screen_size * 2.0 - 1.0;
+    out.clip_position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+    out.uv = in.uv;
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = textureSample(font_atlas, font_sampler, in.uv).r;
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
@@ -0,0 +1,7 @@
+// The camera of the frame, shared by the scene shaders.
+
+struct View {
+    camera_mvp: mat4x4<f32>,
+}
+
+var<push_constant> view: View;
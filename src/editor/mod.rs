@@ -1,3 +1,5 @@
+use crate::runtime::core::config::EngineConfig;
+
 pub struct Editor {}
 
 impl Editor {
@@ -5,8 +7,8 @@ impl Editor {
         Editor {}
     }
 
-    pub fn run(&self) {
+    pub fn run(&self, config: EngineConfig) {
         log::info!("Engine editor started.");
-        crate::runtime::run();
+        crate::runtime::run(config);
     }
 }
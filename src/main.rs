@@ -1,21 +1,52 @@
+use runtime::core::config::{CliArgs, EngineConfig, LogLevel, USAGE};
+
 mod editor;
 pub mod runtime;
 
 fn main() {
-    prepare();
+    let args = match CliArgs::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return;
+    }
+    let config_path = args.config_path();
+    let loaded = EngineConfig::load(&config_path);
+    let mut config = loaded.as_ref().ok().cloned().unwrap_or_default();
+    args.apply(&mut config);
+    if let Err(err) = config.validate() {
+        eprintln!("{}\n\n{}", err, USAGE);
+        std::process::exit(2);
+    }
+
+    prepare(config.log_level);
+    if let Err(err) = loaded {
+        log::warn!(
+            "Unable to load engine config {:?}, using defaults: {}",
+            config_path,
+            err
+        );
+    }
 
     profiling::register_thread!("Main Thread");
     log::info!("Ruccolo Engine started.");
     let editor = editor::Editor::new();
-    editor.run();
+    editor.run(config);
 }
 
-fn prepare() {
+fn prepare(log_level: Option<LogLevel>) {
     #[cfg(feature = "profile-with-tracy")]
     profiling::tracy_client::Client::start();
 
     let mut logger = env_logger::Builder::from_default_env();
     logger.target(env_logger::Target::Stdout);
-    // logger.filter_level(log::LevelFilter::Info);
+    if let Some(level) = log_level {
+        logger.filter_level(level.filter());
+    }
     logger.init();
 }
@@ -0,0 +1,325 @@
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+};
+
+use crate::runtime::functions::scene_system::scene_file::DEFAULT_SCENE_PATH;
+
+pub const DEFAULT_CONFIG_PATH: &str = "assets/config/engine.json";
+
+pub const USAGE: &str = "\
+Usage: ruccolo [OPTIONS] [SCENE]
+
+Arguments:
+  [SCENE]                  Scene file (.json) or model (.gltf, .glb, .obj) to open
+
+Options:
+  -c, --config <PATH>      Engine config file [default: assets/config/engine.json]
+  -s, --scene <PATH>       Same as [SCENE]
+      --size <WxH>         Window size, e.g. 1920x1080
+      --backend <NAME>     auto, vulkan, dx12, metal or gl
+      --vsync              Wait for the vertical blank
+      --no-vsync           Present as fast as possible
+      --hot-reload         Reload the scene and its models when their files change
+      --no-hot-reload      Leave changed files alone
+      --log <LEVEL>        off, error, warn, info, debug or trace, overrides RUST_LOG
+      --screenshot <PATH>  Render with a hidden window, save a .ppm screenshot and exit
+  -h, --help               Print this help
+
+Options given on the command line override the config file.";
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Whatever the platform supports best.
+    Auto,
+    Vulkan,
+    Dx12,
+    Metal,
+    Gl,
+}
+
+impl Backend {
+    pub fn backends(self) -> wgpu::Backends {
+        match self {
+            Backend::Auto => wgpu::Backends::PRIMARY,
+            Backend::Vulkan => wgpu::Backends::VULKAN,
+            Backend::Dx12 => wgpu::Backends::DX12,
+            Backend::Metal => wgpu::Backends::METAL,
+            Backend::Gl => wgpu::Backends::GL,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn filter(self) -> log::LevelFilter {
+        match self {
+            LogLevel::Off => log::LevelFilter::Off,
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+/// How the engine is launched, read from `assets/config/engine.json` and
+/// overridden by the command line, see `CliArgs`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct EngineConfig {
+    /// Scene file, or a model to open on its own.
+    pub scene: PathBuf,
+    pub window_size: [u32; 2],
+    pub backend: Backend,
+    pub vsync: bool,
+    /// Reload the scene file and its models when they change on disk.
+    pub hot_reload: bool,
+    /// `RUST_LOG` decides when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<LogLevel>,
+    /// Render with a hidden window, save a screenshot here and exit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screenshot: Option<PathBuf>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            scene: PathBuf::from(DEFAULT_SCENE_PATH),
+            window_size: [1280, 720],
+            backend: Backend::Dx12,
+            vsync: false,
+            hot_reload: true,
+            log_level: None,
+            screenshot: None,
+        }
+    }
+}
+
+impl EngineConfig {
+    pub fn load<P: AsRef<Path> + Debug>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = std::fs::File::open(path.as_ref())?;
+        let reader = std::io::BufReader::new(file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Checks what neither the config file nor the command line may set,
+    /// once both are merged.
+    pub fn validate(&self) -> Result<(), String> {
+        let [width, height] = self.window_size;
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid window size {}x{}", width, height));
+        }
+        if let Some(screenshot) = self.screenshot.as_ref() {
+            // screenshots are only written as PPM
+            if screenshot.extension().and_then(|ext| ext.to_str()) != Some("ppm") {
+                return Err(format!("Screenshot {:?} must be a .ppm file", screenshot));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Options of the `ruccolo` binary, unset ones leave the config file's value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CliArgs {
+    pub help: bool,
+    pub config: Option<PathBuf>,
+    pub scene: Option<PathBuf>,
+    pub window_size: Option<[u32; 2]>,
+    pub backend: Option<Backend>,
+    pub vsync: Option<bool>,
+    pub hot_reload: Option<bool>,
+    pub log_level: Option<LogLevel>,
+    pub screenshot: Option<PathBuf>,
+}
+
+impl CliArgs {
+    /// Parses the arguments without the program name, see `USAGE`.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut cli = CliArgs::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // `--name=value` is the same as `--name value`
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_owned())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("Missing value for {}", name))
+            };
+            let flag = |set: bool| match inline {
+                Some(_) => Err(format!("Unexpected value for {}", name)),
+                None => Ok(set),
+            };
+            match name {
+                "-h" | "--help" => cli.help = flag(true)?,
+                "-c" | "--config" => cli.config = Some(value()?.into()),
+                "-s" | "--scene" => cli.scene = Some(value()?.into()),
+                "--size" => cli.window_size = Some(parse_size(&value()?)?),
+                "--backend" => cli.backend = Some(parse_name(&value()?, "backend")?),
+                "--vsync" => cli.vsync = Some(flag(true)?),
+                "--no-vsync" => cli.vsync = Some(flag(false)?),
+                "--hot-reload" => cli.hot_reload = Some(flag(true)?),
+                "--no-hot-reload" => cli.hot_reload = Some(flag(false)?),
+                "--log" => cli.log_level = Some(parse_name(&value()?, "log level")?),
+                "--screenshot" => cli.screenshot = Some(value()?.into()),
+                _ if !name.starts_with('-') && cli.scene.is_none() => cli.scene = Some(arg.into()),
+                _ => return Err(format!("Unexpected argument {}", arg)),
+            }
+        }
+        Ok(cli)
+    }
+
+    /// The config file to read, the default one unless `--config` was given.
+    pub fn config_path(&self) -> PathBuf {
+        self.config
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH))
+    }
+
+    pub fn apply(&self, config: &mut EngineConfig) {
+        if let Some(scene) = self.scene.as_ref() {
+            config.scene = scene.clone();
+        }
+        if let Some(window_size) = self.window_size {
+            config.window_size = window_size;
+        }
+        if let Some(backend) = self.backend {
+            config.backend = backend;
+        }
+        if let Some(vsync) = self.vsync {
+            config.vsync = vsync;
+        }
+        if let Some(hot_reload) = self.hot_reload {
+            config.hot_reload = hot_reload;
+        }
+        if self.log_level.is_some() {
+            config.log_level = self.log_level;
+        }
+        if let Some(screenshot) = self.screenshot.as_ref() {
+            config.screenshot = Some(screenshot.clone());
+        }
+    }
+}
+
+fn parse_size(value: &str) -> Result<[u32; 2], String> {
+    let invalid = || format!("Invalid size {}, expected e.g. 1280x720", value);
+    let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+    let size = [width.parse(), height.parse()];
+    match size {
+        [Ok(width), Ok(height)] if width > 0 && height > 0 => Ok([width, height]),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses the lowercase names of a config enum like the config file does.
+fn parse_name<T: serde::de::DeserializeOwned>(value: &str, what: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_owned()))
+        .map_err(|_| format!("Unknown {} {}", what, value))
+}
+
+#[allow(unused_imports)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{Backend, CliArgs, EngineConfig, LogLevel, DEFAULT_CONFIG_PATH};
+
+    #[test]
+    fn cli_overrides_config() {
+        let parse = |args: &[&str]| CliArgs::parse(args.iter().map(|arg| arg.to_string()));
+
+        let cli = parse(&[
+            "scenes/level.json",
+            "--size",
+            "800x600",
+            "--backend=vulkan",
+            "--no-vsync",
+            "--no-hot-reload",
+            "--log",
+            "debug",
+            "--screenshot",
+            "out.ppm",
+        ])
+        .unwrap();
+        assert_eq!(cli.config_path(), PathBuf::from(DEFAULT_CONFIG_PATH));
+        let mut config = EngineConfig {
+            vsync: true,
+            ..Default::default()
+        };
+        cli.apply(&mut config);
+        assert_eq!(
+            config,
+            EngineConfig {
+                scene: "scenes/level.json".into(),
+                window_size: [800, 600],
+                backend: Backend::Vulkan,
+                vsync: false,
+                hot_reload: false,
+                log_level: Some(LogLevel::Debug),
+                screenshot: Some("out.ppm".into()),
+            }
+        );
+
+        assert!(config.validate().is_ok());
+
+        // nothing given keeps the config file's values
+        let mut unchanged = config.clone();
+        parse(&[]).unwrap().apply(&mut unchanged);
+        assert_eq!(unchanged, config);
+
+        // a config file is checked like the command line
+        let zero_size = EngineConfig {
+            window_size: [0, 720],
+            ..Default::default()
+        };
+        assert!(zero_size.validate().is_err());
+        for screenshot in ["out.png", "out"] {
+            let mut config = EngineConfig::default();
+            parse(&["--screenshot", screenshot])
+                .unwrap()
+                .apply(&mut config);
+            assert!(config.validate().is_err());
+        }
+
+        assert!(parse(&["-h"]).unwrap().help);
+        assert_eq!(
+            parse(&["-c", "my.json"]).unwrap().config_path(),
+            PathBuf::from("my.json")
+        );
+        assert!(parse(&["--size", "800"]).is_err());
+        assert!(parse(&["--size", "0x600"]).is_err());
+        assert!(parse(&["--backend", "glide"]).is_err());
+        assert!(parse(&["--log"]).is_err());
+        assert!(parse(&["a.json", "b.json"]).is_err());
+        assert!(parse(&["--fullscreen"]).is_err());
+        // flags take no value
+        assert_eq!(
+            parse(&["--vsync=false"]).unwrap_err(),
+            "Unexpected value for --vsync"
+        );
+        assert!(parse(&["--no-vsync=true"]).is_err());
+        assert!(parse(&["--hot-reload=false"]).is_err());
+        assert!(parse(&["--help=yes"]).is_err());
+
+        // the shipped config file is the defaults
+        let shipped = EngineConfig::load(DEFAULT_CONFIG_PATH).unwrap();
+        assert_eq!(shipped, EngineConfig::default());
+    }
+}
@@ -0,0 +1,63 @@
+/// An id of a runtime object, components are attached to it in the `World`.
+///
+/// Ids of despawned entities are reused with a new generation, a stale
+/// `Entity` never reaches the components of the one that replaced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    /// The slot of the entity, the same for all generations.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+}
+
+/// Allocates entity ids and tracks which are alive.
+#[derive(Debug, Default)]
+pub(super) struct Entities {
+    // current generation per slot, odd while the slot is alive
+    generations: Vec<u32>,
+    free: Vec<u32>,
+}
+
+impl Entities {
+    pub fn alloc(&mut self) -> Entity {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.generations.push(0);
+                self.generations.len() as u32 - 1
+            }
+        };
+        let generation = &mut self.generations[index as usize];
+        *generation += 1;
+        Entity {
+            index,
+            generation: *generation,
+        }
+    }
+
+    /// Returns whether the entity was alive.
+    pub fn free(&mut self, entity: Entity) -> bool {
+        if !self.contains(entity) {
+            return false;
+        }
+        self.generations[entity.index as usize] += 1;
+        self.free.push(entity.index);
+        true
+    }
+
+    #[inline]
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.generations.get(entity.index as usize) == Some(&entity.generation)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.generations.len() - self.free.len()
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    any::{Any, TypeId},
+    cell::{Ref, RefCell, RefMut},
+    collections::HashMap,
+};
+
+use self::{
+    entity::{Entities, Entity},
+    query::{Query, QueryBorrow},
+    storage::{ComponentStorage, SparseSet},
+};
+
+pub mod entity;
+pub mod query;
+pub mod schedule;
+pub mod storage;
+
+/// Entities with typed components, plus resources that exist once per world.
+///
+/// Any `'static` type is a component, each type lives in its own sparse set.
+/// Component storages are borrow checked at runtime so queries and lookups only
+/// need a shared `World`, resources follow the usual borrow rules.
+#[derive(Default)]
+pub struct World {
+    entities: Entities,
+    storages: HashMap<TypeId, RefCell<Box<dyn ComponentStorage>>>,
+    resources: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl World {
+    pub fn spawn(&mut self) -> Entity {
+        self.entities.alloc()
+    }
+
+    /// Removes the entity with all its components, returns whether it was alive.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.free(entity) {
+            return false;
+        }
+        for storage in self.storages.values_mut() {
+            storage.get_mut().remove_entity(entity);
+        }
+        true
+    }
+
+    #[inline]
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(entity)
+    }
+
+    #[inline]
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Attaches a component, replacing and returning one of the same type.
+    /// Despawned entities are left alone.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+        if !self.contains(entity) {
+            return None;
+        }
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| RefCell::new(Box::<SparseSet<T>>::default()))
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()?
+            .insert(entity, component)
+    }
+
+    /// Borrows the storages of the query's components, e.g.
+    /// `world.query::<(&mut Transform, &Velocity)>().for_each(|entity, (transform, velocity)| ..)`.
+    pub fn query<Q: Query>(&self) -> QueryBorrow<'_, Q> {
+        QueryBorrow::new(self)
+    }
+
+    fn storage<T: 'static>(&self) -> Option<Ref<'_, SparseSet<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?.borrow();
+        Ref::filter_map(storage, |storage| storage.as_any().downcast_ref()).ok()
+    }
+
+    fn storage_mut<T: 'static>(&self) -> Option<RefMut<'_, SparseSet<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?.borrow_mut();
+        RefMut::filter_map(storage, |storage| storage.as_any_mut().downcast_mut()).ok()
+    }
+
+    /// Returns the resource of the same type that was replaced.
+    pub fn insert_resource<R: 'static>(&mut self, resource: R) -> Option<R> {
+        self.resources
+            .insert(TypeId::of::<R>(), Box::new(resource))
+            .and_then(|old| old.downcast().ok())
+            .map(|old| *old)
+    }
+
+    pub fn remove_resource<R: 'static>(&mut self) -> Option<R> {
+        self.resources
+            .remove(&TypeId::of::<R>())
+            .and_then(|resource| resource.downcast().ok())
+            .map(|resource| *resource)
+    }
+
+    pub fn get_resource<R: 'static>(&self) -> Option<&R> {
+        self.resources.get(&TypeId::of::<R>())?.downcast_ref()
+    }
+
+    pub fn get_resource_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.resources.get_mut(&TypeId::of::<R>())?.downcast_mut()
+    }
+
+    /// A resource the world is set up with, panics if it is missing.
+    pub fn resource<R: 'static>(&self) -> &R {
+        self.get_resource()
+            .unwrap_or_else(|| panic!("Missing resource {}", std::any::type_name::<R>()))
+    }
+
+    pub fn resource_mut<R: 'static>(&mut self) -> &mut R {
+        self.get_resource_mut()
+            .unwrap_or_else(|| panic!("Missing resource {}", std::any::type_name::<R>()))
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{Entity, World};
+
+    #[test]
+    fn entities_and_queries() {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Position(f32);
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Velocity(f32);
+
+        fn position(world: &World, entity: Entity) -> Option<Position> {
+            let mut found = None;
+            world.query::<&Position>().for_each(|matched, position| {
+                found = found.or((matched == entity).then_some(*position))
+            });
+            found
+        }
+
+        let mut world = World::default();
+        let moving = world.spawn();
+        let still = world.spawn();
+        world.insert(moving, Position(1.0));
+        world.insert(moving, Velocity(2.0));
+        world.insert(still, Position(5.0));
+        assert_eq!(world.insert(still, Position(3.0)), Some(Position(5.0)));
+
+        world
+            .query::<(&mut Position, &Velocity)>()
+            .for_each(|_, (position, velocity)| position.0 += velocity.0);
+        assert_eq!(position(&world, moving), Some(Position(3.0)));
+        assert_eq!(position(&world, still), Some(Position(3.0)));
+        assert_eq!(world.query::<&Position>().entities().len(), 2);
+        assert_eq!(world.query::<&Velocity>().entities(), [moving]);
+        // never inserted, matches nothing
+        assert!(world.query::<&u32>().entities().is_empty());
+
+        // despawned ids come back with a new generation
+        assert!(world.despawn(moving));
+        assert!(!world.despawn(moving));
+        let reused = world.spawn();
+        assert_eq!(reused.index(), moving.index());
+        assert!(position(&world, moving).is_none());
+        assert!(position(&world, reused).is_none());
+        assert_eq!(world.insert(moving, Velocity(1.0)), None);
+        assert!(world.query::<&Velocity>().entities().is_empty());
+        assert_eq!(world.entity_count(), 2);
+
+        // the swap on removal keeps the other entities reachable
+        world.insert(reused, Position(7.0));
+        assert!(world.despawn(still));
+        assert_eq!(position(&world, reused), Some(Position(7.0)));
+
+        assert_eq!(world.insert_resource(1.5_f32), None);
+        *world.resource_mut::<f32>() *= 2.0;
+        assert_eq!(world.insert_resource(0.0_f32), Some(3.0));
+        assert_eq!(world.remove_resource::<f32>(), Some(0.0));
+        assert!(world.get_resource::<f32>().is_none());
+    }
+}
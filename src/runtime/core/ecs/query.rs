@@ -0,0 +1,117 @@
+use std::cell::{Ref, RefMut};
+
+use super::{entity::Entity, storage::SparseSet, World};
+
+/// Component access of a query, `&T`, `&mut T` or a tuple of them.
+///
+/// Storages are borrowed for as long as the query lives, asking for the same
+/// component type twice with one of them mutable panics.
+pub trait Query {
+    type Fetch<'w>;
+    type Item<'f>;
+
+    /// `None` when a component type was never inserted, nothing matches then.
+    fn fetch(world: &World) -> Option<Self::Fetch<'_>>;
+    /// Entities that may match, the shortest storage of the query.
+    fn candidates<'a>(fetch: &'a Self::Fetch<'_>) -> &'a [Entity];
+    fn get<'f>(fetch: &'f mut Self::Fetch<'_>, entity: Entity) -> Option<Self::Item<'f>>;
+}
+
+impl<T: 'static> Query for &T {
+    type Fetch<'w> = Ref<'w, SparseSet<T>>;
+    type Item<'f> = &'f T;
+
+    fn fetch(world: &World) -> Option<Self::Fetch<'_>> {
+        world.storage::<T>()
+    }
+
+    fn candidates<'a>(fetch: &'a Self::Fetch<'_>) -> &'a [Entity] {
+        fetch.entities()
+    }
+
+    fn get<'f>(fetch: &'f mut Self::Fetch<'_>, entity: Entity) -> Option<Self::Item<'f>> {
+        fetch.get(entity)
+    }
+}
+
+impl<T: 'static> Query for &mut T {
+    type Fetch<'w> = RefMut<'w, SparseSet<T>>;
+    type Item<'f> = &'f mut T;
+
+    fn fetch(world: &World) -> Option<Self::Fetch<'_>> {
+        world.storage_mut::<T>()
+    }
+
+    fn candidates<'a>(fetch: &'a Self::Fetch<'_>) -> &'a [Entity] {
+        fetch.entities()
+    }
+
+    fn get<'f>(fetch: &'f mut Self::Fetch<'_>, entity: Entity) -> Option<Self::Item<'f>> {
+        fetch.get_mut(entity)
+    }
+}
+
+macro_rules! impl_query_tuple {
+    ($($query:ident $fetch:ident),+) => {
+        impl<$($query: Query),+> Query for ($($query,)+) {
+            type Fetch<'w> = ($($query::Fetch<'w>,)+);
+            type Item<'f> = ($($query::Item<'f>,)+);
+
+            fn fetch(world: &World) -> Option<Self::Fetch<'_>> {
+                Some(($($query::fetch(world)?,)+))
+            }
+
+            fn candidates<'a>(fetch: &'a Self::Fetch<'_>) -> &'a [Entity] {
+                let ($($fetch,)+) = fetch;
+                [$($query::candidates($fetch)),+]
+                    .into_iter()
+                    .min_by_key(|entities| entities.len())
+                    .unwrap_or(&[])
+            }
+
+            fn get<'f>(fetch: &'f mut Self::Fetch<'_>, entity: Entity) -> Option<Self::Item<'f>> {
+                let ($($fetch,)+) = fetch;
+                Some(($($query::get($fetch, entity)?,)+))
+            }
+        }
+    };
+}
+
+impl_query_tuple!(A a);
+impl_query_tuple!(A a, B b);
+impl_query_tuple!(A a, B b, C c);
+impl_query_tuple!(A a, B b, C c, D d);
+
+/// The borrowed storages of a query, see `World::query`.
+pub struct QueryBorrow<'w, Q: Query> {
+    fetch: Option<Q::Fetch<'w>>,
+}
+
+impl<'w, Q: Query> QueryBorrow<'w, Q> {
+    pub(super) fn new(world: &'w World) -> Self {
+        QueryBorrow {
+            fetch: Q::fetch(world),
+        }
+    }
+
+    /// Runs `f` for every entity having all the components of the query.
+    pub fn for_each(&mut self, mut f: impl FnMut(Entity, Q::Item<'_>)) {
+        let Some(fetch) = self.fetch.as_mut() else {
+            return;
+        };
+        // copied, the items borrow the storages mutably
+        let candidates = Q::candidates(fetch).to_vec();
+        for entity in candidates {
+            if let Some(item) = Q::get(fetch, entity) {
+                f(entity, item);
+            }
+        }
+    }
+
+    /// The matching entities.
+    pub fn entities(&mut self) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        self.for_each(|entity, _| entities.push(entity));
+        entities
+    }
+}
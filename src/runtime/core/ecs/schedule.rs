@@ -0,0 +1,85 @@
+use super::World;
+
+/// The points of a frame systems run at, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// After the window and device events of the frame were processed.
+    Input,
+    /// Gameplay, after the fixed simulation steps of the frame.
+    Update,
+    /// After all edits of the frame, world transforms are brought up to date.
+    TransformPropagation,
+    /// Right before the renderer takes what it draws out of the scene.
+    RenderExtract,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 4] = [
+        Stage::Input,
+        Stage::Update,
+        Stage::TransformPropagation,
+        Stage::RenderExtract,
+    ];
+}
+
+struct System {
+    name: &'static str,
+    run: Box<dyn FnMut(&mut World)>,
+}
+
+/// Systems grouped by stage, each stage runs its systems in the order they were added.
+#[derive(Default)]
+pub struct Schedule {
+    stages: [Vec<System>; Stage::ALL.len()],
+}
+
+impl Schedule {
+    pub fn add_system(
+        &mut self,
+        stage: Stage,
+        name: &'static str,
+        system: impl FnMut(&mut World) + 'static,
+    ) {
+        self.stages[stage as usize].push(System {
+            name,
+            run: Box::new(system),
+        });
+    }
+
+    pub fn run(&mut self, stage: Stage, world: &mut World) {
+        for system in self.stages[stage as usize].iter_mut() {
+            log::trace!("Running system {} in {:?}", system.name, stage);
+            (system.run)(world);
+        }
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{Schedule, Stage};
+    use crate::runtime::core::ecs::World;
+
+    #[test]
+    fn stage_order() {
+        #[derive(Debug, Default, PartialEq)]
+        struct Log(Vec<&'static str>);
+
+        let mut world = World::default();
+        world.insert_resource(Log::default());
+        let mut schedule = Schedule::default();
+        schedule.add_system(Stage::RenderExtract, "extract", |world| {
+            world.resource_mut::<Log>().0.push("extract")
+        });
+        schedule.add_system(Stage::Update, "first", |world| {
+            world.resource_mut::<Log>().0.push("first")
+        });
+        schedule.add_system(Stage::Update, "second", |world| {
+            world.resource_mut::<Log>().0.push("second")
+        });
+
+        for stage in Stage::ALL {
+            schedule.run(stage, &mut world);
+        }
+        assert_eq!(world.resource::<Log>().0, ["first", "second", "extract"]);
+    }
+}
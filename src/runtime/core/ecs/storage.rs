@@ -0,0 +1,92 @@
+use std::any::Any;
+
+use super::entity::Entity;
+
+/// The components of one type, packed densely with a sparse index by entity.
+///
+/// Iteration walks the dense arrays, lookups and removals are constant time.
+#[derive(Debug)]
+pub struct SparseSet<T> {
+    // dense position per entity index
+    sparse: Vec<Option<u32>>,
+    entities: Vec<Entity>,
+    values: Vec<T>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        SparseSet {
+            sparse: Vec::new(),
+            entities: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+}
+
+impl<T> SparseSet<T> {
+    /// Returns the component the entity had before.
+    pub fn insert(&mut self, entity: Entity, value: T) -> Option<T> {
+        if let Some(dense) = self.dense(entity) {
+            return Some(std::mem::replace(&mut self.values[dense], value));
+        }
+        if self.sparse.len() <= entity.index() {
+            self.sparse.resize(entity.index() + 1, None);
+        }
+        self.sparse[entity.index()] = Some(self.entities.len() as u32);
+        self.entities.push(entity);
+        self.values.push(value);
+        None
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let dense = self.dense(entity)?;
+        self.sparse[entity.index()] = None;
+        self.entities.swap_remove(dense);
+        if let Some(moved) = self.entities.get(dense) {
+            self.sparse[moved.index()] = Some(dense as u32);
+        }
+        Some(self.values.swap_remove(dense))
+    }
+
+    #[inline]
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.dense(entity).map(|dense| &self.values[dense])
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.dense(entity).map(|dense| &mut self.values[dense])
+    }
+
+    /// The entities having the component, in storage order.
+    #[inline]
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    fn dense(&self, entity: Entity) -> Option<usize> {
+        let dense = (*self.sparse.get(entity.index())?)? as usize;
+        (self.entities[dense] == entity).then_some(dense)
+    }
+}
+
+/// A `SparseSet` of any component type, so despawning can reach all of them.
+pub(super) trait ComponentStorage {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> ComponentStorage for SparseSet<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
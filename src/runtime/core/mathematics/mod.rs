@@ -1,5 +1,7 @@
 pub mod array;
 mod matrix;
+pub mod ray;
+pub mod transform;
 
 pub const PI: f32 = 3.141592653;
 pub type Matrix4 = matrix::Matrix<f32, 4>;
@@ -121,7 +123,17 @@ pub fn cross(x: &Array4, y: &Array4) -> Array4 {
     ]);
 }
 
+/// Dot product of the xyz parts.
+pub fn dot(x: &Array4, y: &Array4) -> f32 {
+    x.0[0] * y.0[0] + x.0[1] * y.0[1] + x.0[2] * y.0[2]
+}
+
 impl Array4 {
+    /// Length of the xyz part.
+    pub fn length(&self) -> f32 {
+        dot(self, self).sqrt()
+    }
+
     #[allow(dead_code)]
     pub fn almost_eq(&self, other: &Self) -> bool {
         for index in 0..4 {
@@ -145,6 +157,15 @@ impl Array4 {
 }
 
 impl Matrix4 {
+    pub fn identity() -> Matrix4 {
+        Matrix4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
     pub fn trans(&self) -> Matrix4 {
         Matrix4::new([
             [
@@ -174,6 +195,50 @@ impl Matrix4 {
         ])
     }
 
+    /// `None` when the matrix is singular.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        // Gauss-Jordan elimination on the rows, `rows[r][c]` is `self.0[c].0[r]`
+        let mut rows = [[0.0f32; 8]; 4];
+        for (r, row) in rows.iter_mut().enumerate() {
+            for (value, column) in row.iter_mut().zip(self.0.iter()) {
+                *value = column.0[r];
+            }
+            row[4 + r] = 1.0;
+        }
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|a, b| rows[*a][column].abs().total_cmp(&rows[*b][column].abs()))
+                .unwrap();
+            if rows[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            rows.swap(column, pivot);
+            let scale = 1.0 / rows[column][column];
+            for value in rows[column].iter_mut() {
+                *value *= scale;
+            }
+            let pivot_row = rows[column];
+            for (r, row) in rows.iter_mut().enumerate() {
+                if r == column {
+                    continue;
+                }
+                let factor = row[column];
+                for (value, pivot_value) in row.iter_mut().zip(pivot_row.iter()) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+        let column = |c: usize| {
+            [
+                rows[0][4 + c],
+                rows[1][4 + c],
+                rows[2][4 + c],
+                rows[3][4 + c],
+            ]
+        };
+        Some(Matrix4::new([column(0), column(1), column(2), column(3)]))
+    }
+
     pub fn almost_eq(&self, other: &Matrix4) -> bool {
         for index in 0..4 {
             if !self.0[index].almost_eq(&other.0[index]) {
@@ -202,7 +267,7 @@ fn f32_eq(x: f32, y: f32) -> bool {
 
 #[allow(unused_imports)]
 mod test {
-    use super::{cross, rotate, rotate_around, rotate_x, rotate_y, rotate_z, Array4, Matrix4};
+    use super::{cross, dot, rotate, rotate_around, rotate_x, rotate_y, rotate_z, Array4, Matrix4};
 
     #[test]
     fn trans_tests() {
@@ -349,4 +414,25 @@ mod test {
         let target = Array4::new([0.0, 0.0, 1.0, 0.0]);
         assert!(res.almost_eq(&target));
     }
+
+    #[test]
+    fn inverse_tests() {
+        let a = rotate_y(30)
+            * Matrix4::new([
+                [2.0, 0.0, 0.0, 0.0],
+                [0.0, 0.5, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [1.0, -2.0, 3.0, 1.0],
+            ]);
+        let inverse = a.inverse().unwrap();
+        assert!((a * inverse).almost_eq(&Matrix4::identity()));
+        assert!((inverse * a).almost_eq(&Matrix4::identity()));
+
+        let singular = Matrix4::new([[1.0, 0.0, 0.0, 0.0]; 4]);
+        assert!(singular.inverse().is_none());
+
+        let x = Array4::new([3.0, 4.0, 0.0, 1.0]);
+        assert_eq!(dot(&x, &Array4::new([1.0, 1.0, 1.0, 0.0])), 7.0);
+        assert_eq!(x.length(), 5.0);
+    }
 }
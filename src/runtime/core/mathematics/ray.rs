@@ -0,0 +1,89 @@
+use super::{cross, dot, Array4};
+
+/// A half line, `direction` is normalized and has a zero w.
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Array4,
+    pub direction: Array4,
+}
+
+impl Ray {
+    pub fn new(origin: Array4, mut direction: Array4) -> Self {
+        direction.0[3] = 0.0;
+        direction.normalize();
+        Ray { origin, direction }
+    }
+
+    #[inline]
+    pub fn at(&self, distance: f32) -> Array4 {
+        self.origin + self.direction * distance
+    }
+
+    /// Distance along the ray to the plane through `point`, `None` when the
+    /// plane is parallel or behind the origin.
+    pub fn intersect_plane(&self, point: Array4, normal: Array4) -> Option<f32> {
+        let denominator = dot(&self.direction, &normal);
+        if denominator.abs() < 1e-6 {
+            return None;
+        }
+        let distance = dot(&(point - self.origin), &normal) / denominator;
+        (distance >= 0.0).then_some(distance)
+    }
+
+    /// Closest points between the ray and the line `point + s * direction`,
+    /// returns the distance along the ray and `s`, `None` when they are parallel.
+    pub fn closest_to_line(&self, point: Array4, direction: Array4) -> Option<(f32, f32)> {
+        // both closest points lie on the plane spanned by the connecting segment
+        let normal = cross(&self.direction, &direction);
+        let denominator = dot(&normal, &normal);
+        if denominator < 1e-10 {
+            return None;
+        }
+        let offset = point - self.origin;
+        let distance = dot(&cross(&offset, &direction), &normal) / denominator;
+        let s = dot(&cross(&offset, &self.direction), &normal) / denominator;
+        Some((distance.max(0.0), s))
+    }
+
+    /// Distance from `point` to the closest point of the ray.
+    pub fn distance_to_point(&self, point: Array4) -> f32 {
+        let along = dot(&(point - self.origin), &self.direction).max(0.0);
+        (point - self.at(along)).length()
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::Ray;
+    use crate::runtime::core::mathematics::Array4;
+
+    #[test]
+    fn ray_queries() {
+        let ray = Ray::new(
+            Array4::new([0.0, 0.0, 0.0, 1.0]),
+            Array4::new([0.0, 0.0, -2.0, 0.0]),
+        );
+        assert!(ray.at(3.0).almost_eq(&Array4::new([0.0, 0.0, -3.0, 1.0])));
+
+        let plane_point = Array4::new([0.0, 0.0, -5.0, 1.0]);
+        let normal = Array4::new([0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(ray.intersect_plane(plane_point, normal), Some(5.0));
+        let behind = Array4::new([0.0, 0.0, 5.0, 1.0]);
+        assert_eq!(ray.intersect_plane(behind, normal), None);
+
+        // the x axis through (0, 1, -4) passes one unit above the ray
+        let (distance, s) = ray
+            .closest_to_line(
+                Array4::new([2.0, 1.0, -4.0, 1.0]),
+                Array4::new([1.0, 0.0, 0.0, 0.0]),
+            )
+            .unwrap();
+        assert!((distance - 4.0).abs() < 1e-5);
+        assert!((s + 2.0).abs() < 1e-5);
+        assert!(ray
+            .closest_to_line(plane_point, Array4::new([0.0, 0.0, 1.0, 0.0]))
+            .is_none());
+
+        assert!((ray.distance_to_point(Array4::new([0.0, 1.0, -4.0, 1.0])) - 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,221 @@
+use super::Matrix4;
+
+/// Unit quaternion in the order (x, y, z, w), as glTF stores rotations.
+pub type Quaternion = [f32; 4];
+
+pub const QUATERNION_IDENTITY: Quaternion = [0.0, 0.0, 0.0, 1.0];
+
+/// Translation, rotation and scale, composed as `T * R * S`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: [f32; 3],
+    pub rotation: Quaternion,
+    pub scale: [f32; 3],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            translation: [0.0; 3],
+            rotation: QUATERNION_IDENTITY,
+            scale: [1.0; 3],
+        }
+    }
+}
+
+impl Transform {
+    pub fn matrix(&self) -> Matrix4 {
+        let [x, y, z, w] = self.rotation;
+        let [sx, sy, sz] = self.scale;
+        let [tx, ty, tz] = self.translation;
+        Matrix4::new([
+            [
+                (1.0 - 2.0 * (y * y + z * z)) * sx,
+                2.0 * (x * y + w * z) * sx,
+                2.0 * (x * z - w * y) * sx,
+                0.0,
+            ],
+            [
+                2.0 * (x * y - w * z) * sy,
+                (1.0 - 2.0 * (x * x + z * z)) * sy,
+                2.0 * (y * z + w * x) * sy,
+                0.0,
+            ],
+            [
+                2.0 * (x * z + w * y) * sz,
+                2.0 * (y * z - w * x) * sz,
+                (1.0 - 2.0 * (x * x + y * y)) * sz,
+                0.0,
+            ],
+            [tx, ty, tz, 1.0],
+        ])
+    }
+
+    /// Splits an affine matrix without shear back into its parts.
+    pub fn from_matrix(matrix: &Matrix4) -> Self {
+        let column = |index: usize| {
+            let [x, y, z, _] = matrix.0[index].0;
+            [x, y, z]
+        };
+        let length = |[x, y, z]: [f32; 3]| (x * x + y * y + z * z).sqrt();
+        let [c0, c1, c2, c3] = [column(0), column(1), column(2), column(3)];
+        let mut scale = [length(c0), length(c1), length(c2)];
+        // a mirrored basis is expressed as a negative x scale
+        let determinant = c0[0] * (c1[1] * c2[2] - c1[2] * c2[1])
+            - c1[0] * (c0[1] * c2[2] - c0[2] * c2[1])
+            + c2[0] * (c0[1] * c1[2] - c0[2] * c1[1]);
+        if determinant < 0.0 {
+            scale[0] = -scale[0];
+        }
+        let divide = |[x, y, z]: [f32; 3], s: f32| {
+            if s != 0.0 {
+                [x / s, y / s, z / s]
+            } else {
+                [x, y, z]
+            }
+        };
+        let rotation = quaternion_from_basis(
+            divide(c0, scale[0]),
+            divide(c1, scale[1]),
+            divide(c2, scale[2]),
+        );
+        Transform {
+            translation: c3,
+            rotation,
+            scale,
+        }
+    }
+
+    /// Rotation as angles around x, then y, then z (fixed axes), in degrees.
+    pub fn euler_degrees(&self) -> [f32; 3] {
+        let [x, y, z, w] = self.rotation;
+        let r00 = 1.0 - 2.0 * (y * y + z * z);
+        let r10 = 2.0 * (x * y + w * z);
+        let r20 = 2.0 * (x * z - w * y);
+        let r21 = 2.0 * (y * z + w * x);
+        let r22 = 1.0 - 2.0 * (x * x + y * y);
+        [
+            r21.atan2(r22).to_degrees(),
+            (-r20).clamp(-1.0, 1.0).asin().to_degrees(),
+            r10.atan2(r00).to_degrees(),
+        ]
+    }
+
+    pub fn set_euler_degrees(&mut self, [x, y, z]: [f32; 3]) {
+        let axis =
+            |axis: [f32; 3], degrees: f32| quaternion_from_axis_angle(axis, degrees.to_radians());
+        self.rotation = quaternion_mul(
+            axis([0.0, 0.0, 1.0], z),
+            quaternion_mul(axis([0.0, 1.0, 0.0], y), axis([1.0, 0.0, 0.0], x)),
+        );
+    }
+}
+
+pub fn quaternion_from_axis_angle(axis: [f32; 3], radians: f32) -> Quaternion {
+    let (sin, cos) = (radians * 0.5).sin_cos();
+    [axis[0] * sin, axis[1] * sin, axis[2] * sin, cos]
+}
+
+/// Rotation by `rhs` followed by `lhs`.
+pub fn quaternion_mul(lhs: Quaternion, rhs: Quaternion) -> Quaternion {
+    let [x1, y1, z1, w1] = lhs;
+    let [x2, y2, z2, w2] = rhs;
+    [
+        w1 * x2 + x1 * w2 + y1 * z2 - z1 * y2,
+        w1 * y2 - x1 * z2 + y1 * w2 + z1 * x2,
+        w1 * z2 + x1 * y2 - y1 * x2 + z1 * w2,
+        w1 * w2 - x1 * x2 - y1 * y2 - z1 * z2,
+    ]
+}
+
+pub fn quaternion_normalize(quaternion: Quaternion) -> Quaternion {
+    let length = quaternion.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if length == 0.0 {
+        return QUATERNION_IDENTITY;
+    }
+    quaternion.map(|v| v / length)
+}
+
+fn quaternion_from_basis(c0: [f32; 3], c1: [f32; 3], c2: [f32; 3]) -> Quaternion {
+    // rows and columns as in the rotation matrix, `c1[0]` is r01
+    let (r00, r11, r22) = (c0[0], c1[1], c2[2]);
+    let trace = r00 + r11 + r22;
+    let quaternion = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [
+            (c1[2] - c2[1]) / s,
+            (c2[0] - c0[2]) / s,
+            (c0[1] - c1[0]) / s,
+            0.25 * s,
+        ]
+    } else if r00 > r11 && r00 > r22 {
+        let s = (1.0 + r00 - r11 - r22).sqrt() * 2.0;
+        [
+            0.25 * s,
+            (c1[0] + c0[1]) / s,
+            (c2[0] + c0[2]) / s,
+            (c1[2] - c2[1]) / s,
+        ]
+    } else if r11 > r22 {
+        let s = (1.0 + r11 - r00 - r22).sqrt() * 2.0;
+        [
+            (c1[0] + c0[1]) / s,
+            0.25 * s,
+            (c2[1] + c1[2]) / s,
+            (c2[0] - c0[2]) / s,
+        ]
+    } else {
+        let s = (1.0 + r22 - r00 - r11).sqrt() * 2.0;
+        [
+            (c2[0] + c0[2]) / s,
+            (c2[1] + c1[2]) / s,
+            0.25 * s,
+            (c0[1] - c1[0]) / s,
+        ]
+    };
+    quaternion_normalize(quaternion)
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{quaternion_from_axis_angle, Transform};
+    use crate::runtime::core::mathematics::rotate_x;
+
+    #[test]
+    fn matrix_round_trip() {
+        let mut transform = Transform {
+            translation: [1.0, -2.0, 3.0],
+            scale: [2.0, 0.5, 1.5],
+            ..Default::default()
+        };
+        transform.set_euler_degrees([30.0, -45.0, 60.0]);
+        let euler = transform.euler_degrees();
+        for (angle, expected) in euler.iter().zip([30.0, -45.0, 60.0]) {
+            assert!((angle - expected).abs() < 1e-3, "{:?}", euler);
+        }
+
+        let decomposed = Transform::from_matrix(&transform.matrix());
+        let (lhs, rhs) = (decomposed.matrix(), transform.matrix());
+        for (a, b) in lhs.0.iter().zip(rhs.0.iter()) {
+            for (a, b) in a.0.iter().zip(b.0) {
+                assert!((a - b).abs() < 1e-5, "{:?} != {:?}", lhs, rhs);
+            }
+        }
+
+        // mirrored bases come back as a negative x scale
+        let mirrored = Transform {
+            scale: [-1.0, 2.0, 1.0],
+            ..Default::default()
+        };
+        let decomposed = Transform::from_matrix(&mirrored.matrix());
+        assert!(decomposed.matrix().almost_eq(&mirrored.matrix()));
+
+        // matches the existing rotation helpers
+        let rotation = Transform {
+            rotation: quaternion_from_axis_angle([1.0, 0.0, 0.0], 90f32.to_radians()),
+            ..Default::default()
+        };
+        assert!(rotation.matrix().almost_eq(&rotate_x(90)));
+    }
+}
@@ -1 +1,4 @@
+pub mod config;
+pub mod ecs;
 pub mod mathematics;
+pub mod timestep;
@@ -0,0 +1,93 @@
+pub const DEFAULT_UPDATE_RATE: f32 = 60.0;
+pub const DEFAULT_MAX_STEPS: u32 = 5;
+
+/// Accumulator for running the simulation at a fixed rate, independent of the frame rate.
+///
+/// Each frame adds its delta time and gets back the number of fixed steps to run,
+/// what is left over is exposed as `alpha` to interpolate between the last two states.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTimestep {
+    step: f32,
+    accumulator: f32,
+    max_steps: u32,
+}
+
+impl FixedTimestep {
+    /// `rate` steps per second, at most `max_steps` per frame.
+    ///
+    /// Panics unless `rate` is positive and finite and `max_steps` at least 1.
+    pub fn new(rate: f32, max_steps: u32) -> Self {
+        assert!(
+            rate > 0.0 && rate.is_finite(),
+            "Invalid update rate {}",
+            rate
+        );
+        assert!(max_steps >= 1, "At least one step per frame is needed");
+        FixedTimestep {
+            step: 1.0 / rate,
+            accumulator: 0.0,
+            max_steps,
+        }
+    }
+
+    #[inline]
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Adds a frame's delta time, returns how many fixed steps are due.
+    ///
+    /// When more than `max_steps` are due (e.g. after a hitch) the backlog is
+    /// dropped instead of spiraling into ever longer frames.
+    pub fn advance(&mut self, delta_t: f32) -> u32 {
+        self.accumulator += delta_t;
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        if self.accumulator >= self.step {
+            log::debug!(
+                "Simulation is behind, dropping {:.3}s",
+                self.accumulator - self.accumulator % self.step
+            );
+            self.accumulator %= self.step;
+        }
+        steps
+    }
+
+    /// How far between the previous and the current step the frame is, in `0.0..1.0`.
+    #[inline]
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        FixedTimestep::new(DEFAULT_UPDATE_RATE, DEFAULT_MAX_STEPS)
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::FixedTimestep;
+
+    #[test]
+    fn accumulates_steps() {
+        let mut timestep = FixedTimestep::new(50.0, 5);
+        assert_eq!(timestep.advance(0.01), 0);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+        assert_eq!(timestep.advance(0.015), 1);
+        assert!((timestep.alpha() - 0.25).abs() < 1e-4);
+        assert_eq!(timestep.advance(0.04), 2);
+    }
+
+    #[test]
+    fn caps_catch_up() {
+        let mut timestep = FixedTimestep::new(100.0, 3);
+        assert_eq!(timestep.advance(1.0), 3);
+        assert!(timestep.alpha() < 1.0);
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::runtime::{
+    core::mathematics::transform::Transform,
+    functions::scene_system::graph::{Material, NodeHandle, SceneGraph},
+    resources::asset_system::{AssetId, AssetServer},
+};
+
+/// Oldest steps are dropped past this.
+pub const MAX_UNDO_STEPS: usize = 256;
+
+/// A reversible edit of the scene graph or the materials it uses.
+///
+/// Value edits keep both states, node edits keep where the node sat among its siblings.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditCommand {
+    Transform {
+        node: NodeHandle,
+        before: Transform,
+        after: Transform,
+    },
+    Material {
+        material: AssetId<Material>,
+        before: Material,
+        after: Material,
+    },
+    /// The node was spawned, undoing detaches it.
+    AddNode { node: NodeHandle, position: usize },
+    /// Detaches the node, it is only removed from the graph once the step is dropped.
+    RemoveNode { node: NodeHandle, position: usize },
+}
+
+impl EditCommand {
+    pub fn apply(&self, graph: &mut SceneGraph, assets: &mut AssetServer) {
+        self.set(graph, assets, false);
+    }
+
+    pub fn revert(&self, graph: &mut SceneGraph, assets: &mut AssetServer) {
+        self.set(graph, assets, true);
+    }
+
+    fn set(&self, graph: &mut SceneGraph, assets: &mut AssetServer, revert: bool) {
+        fn pick<'a, T>(before: &'a T, after: &'a T, revert: bool) -> &'a T {
+            if revert {
+                before
+            } else {
+                after
+            }
+        }
+        match self {
+            EditCommand::Transform {
+                node,
+                before,
+                after,
+            } => graph.set_transform(*node, *pick(before, after, revert)),
+            EditCommand::Material {
+                material,
+                before,
+                after,
+            } => {
+                // nothing to do once the material is freed with its meshes
+                if let Some(material) = assets.get_mut(*material) {
+                    *material = pick(before, after, revert).clone();
+                }
+            }
+            EditCommand::AddNode { node, position }
+            | EditCommand::RemoveNode { node, position } => {
+                let attach = matches!(self, EditCommand::AddNode { .. }) != revert;
+                if attach {
+                    graph.attach(*node, *position);
+                } else {
+                    graph.detach(*node);
+                }
+            }
+        }
+    }
+
+    /// The step can no longer be undone or redone, a node it left detached is removed for good.
+    fn discard(self, graph: &mut SceneGraph) {
+        if let EditCommand::AddNode { node, .. } | EditCommand::RemoveNode { node, .. } = self {
+            if graph.sibling_index(node).is_none() {
+                graph.remove(node);
+            }
+        }
+    }
+
+    /// Folds `next` into `self` when both edit the same value, the first state is kept.
+    fn merge(&mut self, next: &EditCommand) -> bool {
+        match (self, next) {
+            (
+                EditCommand::Transform { node, after, .. },
+                EditCommand::Transform {
+                    node: next_node,
+                    after: next_after,
+                    ..
+                },
+            ) if node == next_node => *after = *next_after,
+            (
+                EditCommand::Material {
+                    material, after, ..
+                },
+                EditCommand::Material {
+                    material: next_material,
+                    after: next_after,
+                    ..
+                },
+            ) if material == next_material => *after = next_after.clone(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Undo and redo history of editor edits.
+///
+/// Edits of the same value merge until `seal` is called, so a drag over many
+/// frames undoes in one step. The editor seals when the pointer is released.
+#[derive(Debug, Default)]
+pub struct CommandStack {
+    undo: Vec<EditCommand>,
+    redo: Vec<EditCommand>,
+    // the last step may still take merges
+    open: bool,
+}
+
+impl CommandStack {
+    /// Applies the edit and records it.
+    pub fn execute(
+        &mut self,
+        command: EditCommand,
+        graph: &mut SceneGraph,
+        assets: &mut AssetServer,
+    ) {
+        command.apply(graph, assets);
+        self.record(command, graph);
+    }
+
+    /// Records an edit that was already applied, like a gizmo drag.
+    pub fn record(&mut self, command: EditCommand, graph: &mut SceneGraph) {
+        for dropped in self.redo.drain(..) {
+            dropped.discard(graph);
+        }
+        if self.open {
+            if let Some(last) = self.undo.last_mut() {
+                if last.merge(&command) {
+                    return;
+                }
+            }
+        }
+        self.undo.push(command);
+        if self.undo.len() > MAX_UNDO_STEPS {
+            self.undo.remove(0).discard(graph);
+        }
+        self.open = true;
+    }
+
+    /// Ends the current step, the next edit starts a new one.
+    #[inline]
+    pub fn seal(&mut self) {
+        self.open = false;
+    }
+
+    /// Returns whether there was anything to undo.
+    pub fn undo(&mut self, graph: &mut SceneGraph, assets: &mut AssetServer) -> bool {
+        self.seal();
+        let Some(command) = self.undo.pop() else {
+            return false;
+        };
+        command.revert(graph, assets);
+        self.redo.push(command);
+        true
+    }
+
+    /// Returns whether there was anything to redo.
+    pub fn redo(&mut self, graph: &mut SceneGraph, assets: &mut AssetServer) -> bool {
+        self.seal();
+        let Some(command) = self.redo.pop() else {
+            return false;
+        };
+        command.apply(graph, assets);
+        self.undo.push(command);
+        true
+    }
+
+    /// Forgets every step, like when the scene they edited is gone.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = false;
+    }
+
+    #[inline]
+    pub fn undo_steps(&self) -> usize {
+        self.undo.len()
+    }
+
+    #[inline]
+    pub fn redo_steps(&self) -> usize {
+        self.redo.len()
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{CommandStack, EditCommand, MAX_UNDO_STEPS};
+    use crate::runtime::{
+        core::mathematics::transform::Transform,
+        functions::scene_system::graph::{Material, SceneGraph},
+        resources::asset_system::AssetServer,
+    };
+
+    #[test]
+    fn undo_redo() {
+        let mut graph = SceneGraph::default();
+        let root = graph.spawn("Root".to_owned(), None, Transform::default());
+        let mut assets = AssetServer::default();
+        let material_handle = assets.add(Material::default());
+        let mut stack = CommandStack::default();
+        let moved = |x: f32| Transform {
+            translation: [x, 0.0, 0.0],
+            ..Default::default()
+        };
+        let transform = |graph: &SceneGraph| *graph.get(root).unwrap().transform();
+
+        // a drag over several frames is one step
+        for x in 1..=3 {
+            let before = transform(&graph);
+            stack.execute(
+                EditCommand::Transform {
+                    node: root,
+                    before,
+                    after: moved(x as f32),
+                },
+                &mut graph,
+                &mut assets,
+            );
+        }
+        stack.seal();
+        let mut material = assets.get(&material_handle).unwrap().clone();
+        material.metallic = 0.5;
+        stack.execute(
+            EditCommand::Material {
+                material: material_handle.id(),
+                before: Material::default(),
+                after: material,
+            },
+            &mut graph,
+            &mut assets,
+        );
+        assert_eq!(transform(&graph), moved(3.0));
+
+        assert!(stack.undo(&mut graph, &mut assets));
+        assert_eq!(assets.get(&material_handle), Some(&Material::default()));
+        assert!(stack.undo(&mut graph, &mut assets));
+        assert_eq!(transform(&graph), Transform::default());
+        assert!(!stack.undo(&mut graph, &mut assets));
+        assert!(stack.redo(&mut graph, &mut assets));
+        assert_eq!(transform(&graph), moved(3.0));
+        assert_eq!(stack.redo_steps(), 1);
+
+        // adding and removing nodes, a new edit drops the redo steps
+        let child = graph.spawn("Child".to_owned(), Some(root), Transform::default());
+        stack.record(
+            EditCommand::AddNode {
+                node: child,
+                position: 0,
+            },
+            &mut graph,
+        );
+        assert_eq!(stack.redo_steps(), 0);
+        stack.execute(
+            EditCommand::RemoveNode {
+                node: root,
+                position: 0,
+            },
+            &mut graph,
+            &mut assets,
+        );
+        assert!(graph.roots().is_empty());
+        stack.undo(&mut graph, &mut assets);
+        assert!(graph.is_attached(child));
+        stack.undo(&mut graph, &mut assets);
+        assert!(graph.get(root).unwrap().children().is_empty());
+        stack.redo(&mut graph, &mut assets);
+        assert_eq!(graph.get(root).unwrap().children(), &[child]);
+
+        // an undone node is removed once its step can not be redone anymore
+        stack.undo(&mut graph, &mut assets);
+        stack.seal();
+        stack.record(
+            EditCommand::Transform {
+                node: root,
+                before: moved(3.0),
+                after: moved(4.0),
+            },
+            &mut graph,
+        );
+        assert!(!graph.contains(child));
+
+        // the history is bounded
+        for x in 0..MAX_UNDO_STEPS + 10 {
+            stack.seal();
+            stack.record(
+                EditCommand::Transform {
+                    node: root,
+                    before: moved(x as f32),
+                    after: moved(x as f32 + 1.0),
+                },
+                &mut graph,
+            );
+        }
+        assert_eq!(stack.undo_steps(), MAX_UNDO_STEPS);
+        stack.undo(&mut graph, &mut assets);
+        stack.clear();
+        assert_eq!((stack.undo_steps(), stack.redo_steps()), (0, 0));
+    }
+}
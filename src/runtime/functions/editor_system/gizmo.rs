@@ -0,0 +1,636 @@
+use crate::runtime::{
+    core::mathematics::{
+        cross, dot,
+        ray::Ray,
+        transform::{quaternion_from_axis_angle, quaternion_mul, quaternion_normalize, Transform},
+        Array4, Matrix4,
+    },
+    functions::{
+        render_system::overlay::{Color, OverlayBatch},
+        scene_system::{
+            camera::CameraInfo,
+            graph::{NodeHandle, SceneGraph},
+        },
+    },
+};
+
+// the gizmo grows with its distance to the camera to keep its size on screen
+const SIZE_PER_DISTANCE: f32 = 0.15;
+// pick distance around handles, relative to the gizmo size
+const PICK_TOLERANCE: f32 = 0.08;
+// extent of the plane handles along both of their axes, relative to the gizmo size
+const PLANE_HANDLE: [f32; 2] = [0.25, 0.5];
+const RING_SEGMENTS: usize = 48;
+const LINE_WIDTH: f32 = 3.0;
+const ARROW_SIZE: f32 = 12.0;
+const BOX_SIZE: f32 = 10.0;
+/// Smallest scale the gizmo and the inspector leave on an axis.
+pub const MIN_SCALE: f32 = 1e-3;
+
+const AXIS_COLORS: [Color; 3] = [
+    [0.9, 0.2, 0.2, 1.0],
+    [0.2, 0.85, 0.2, 1.0],
+    [0.25, 0.45, 1.0, 1.0],
+];
+const HIGHLIGHT: Color = [1.0, 0.85, 0.1, 1.0];
+const PLANE_ALPHA: f32 = 0.35;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+/// Axes the gizmo works along, scaling always uses the local ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GizmoSpace {
+    #[default]
+    World,
+    Local,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GizmoSettings {
+    pub mode: GizmoMode,
+    pub space: GizmoSpace,
+    /// Always snap, not only while Ctrl is held.
+    pub snap: bool,
+    pub translate_snap: f32,
+    /// In degrees.
+    pub rotate_snap: f32,
+    pub scale_snap: f32,
+}
+
+impl Default for GizmoSettings {
+    fn default() -> Self {
+        GizmoSettings {
+            mode: GizmoMode::default(),
+            space: GizmoSpace::default(),
+            snap: false,
+            translate_snap: 0.25,
+            rotate_snap: 15.0,
+            scale_snap: 0.1,
+        }
+    }
+}
+
+/// Pointer state for one frame, the cursor in window pixels.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GizmoInput {
+    pub cursor: [f32; 2],
+    /// The pointer went down this frame.
+    pub pressed: bool,
+    pub held: bool,
+    pub snap: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoHandle {
+    /// Translates along an axis.
+    Axis(usize),
+    /// Translates in the plane normal to an axis.
+    Plane(usize),
+    /// Rotates around an axis.
+    Ring(usize),
+    /// Scales along a local axis.
+    ScaleBox(usize),
+}
+
+/// Where the gizmo is, in world space.
+#[derive(Debug, Clone, Copy)]
+struct GizmoFrame {
+    origin: Array4,
+    axes: [Array4; 3],
+    size: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    handle: GizmoHandle,
+    node: NodeHandle,
+    // as the drag started, handles keep working along the initial axes
+    frame: GizmoFrame,
+    start: Transform,
+    start_point: Array4,
+    // world space deltas to the space of the node's transform
+    parent_inverse: Matrix4,
+}
+
+/// Translate, rotate and scale handles on the selected node.
+///
+/// Handles are hit tested with a ray from the cursor and drawn projected into an `OverlayBatch`.
+pub struct Gizmo {
+    pub settings: GizmoSettings,
+    hovered: Option<GizmoHandle>,
+    drag: Option<Drag>,
+    batch: OverlayBatch,
+}
+
+impl Gizmo {
+    pub fn new() -> Self {
+        Gizmo {
+            settings: GizmoSettings::default(),
+            hovered: None,
+            drag: None,
+            batch: OverlayBatch::default(),
+        }
+    }
+
+    /// A handle is under the cursor or being dragged, clicks belong to the gizmo then.
+    #[inline]
+    pub fn captures_pointer(&self) -> bool {
+        self.hovered.is_some() || self.drag.is_some()
+    }
+
+    #[inline]
+    pub fn batch(&self) -> &OverlayBatch {
+        &self.batch
+    }
+
+    /// Hit tests and drags the handles of `selection`, then lays them out.
+    ///
+    /// Returns whether the node's transform changed.
+    pub fn update(
+        &mut self,
+        graph: &mut SceneGraph,
+        selection: Option<NodeHandle>,
+        camera: &CameraInfo,
+        screen_size: [f32; 2],
+        input: GizmoInput,
+    ) -> bool {
+        self.batch.clear();
+        let Some(node) = selection.filter(|node| graph.contains(*node)) else {
+            self.hovered = None;
+            self.drag = None;
+            return false;
+        };
+        if !input.held || self.drag.is_some_and(|drag| drag.node != node) {
+            self.drag = None;
+        }
+        let ray = camera.screen_ray(input.cursor, screen_size);
+
+        let mut changed = false;
+        if let (Some(drag), Some(ray)) = (self.drag, ray) {
+            let snap = input.snap || self.settings.snap;
+            changed = self.drag_to(&drag, &ray, snap, graph);
+        }
+        let frame = self.frame(graph, node, camera);
+        if self.drag.is_none() {
+            self.hovered = ray.and_then(|ray| self.pick(&frame, &ray));
+            if let (true, Some(handle), Some(ray), Some(scene_node)) =
+                (input.pressed, self.hovered, ray, graph.get(node))
+            {
+                self.drag = handle_point(handle, &frame, &ray).map(|start_point| Drag {
+                    handle,
+                    node,
+                    frame,
+                    start: *scene_node.transform(),
+                    start_point,
+                    parent_inverse: scene_node
+                        .parent()
+                        .and_then(|parent| graph.world_matrix(parent).inverse())
+                        .unwrap_or_else(Matrix4::identity),
+                });
+            }
+        }
+        self.layout(&frame, camera, screen_size);
+        changed
+    }
+
+    fn frame(&self, graph: &SceneGraph, node: NodeHandle, camera: &CameraInfo) -> GizmoFrame {
+        let world = graph.world_matrix(node);
+        let mut origin = world.0[3];
+        origin.0[3] = 1.0;
+        let local =
+            self.settings.space == GizmoSpace::Local || self.settings.mode == GizmoMode::Scale;
+        let axes = [0, 1, 2].map(|index| {
+            let mut axis = unit_axis(index);
+            if local {
+                let mut local_axis = world.0[index];
+                local_axis.0[3] = 0.0;
+                if local_axis.length() > 1e-6 {
+                    local_axis.normalize();
+                    axis = local_axis;
+                }
+            }
+            axis
+        });
+        GizmoFrame {
+            origin,
+            axes,
+            size: ((origin - camera.position).length() * SIZE_PER_DISTANCE).max(1e-3),
+        }
+    }
+
+    /// The handle closest along the ray.
+    fn pick(&self, frame: &GizmoFrame, ray: &Ray) -> Option<GizmoHandle> {
+        let tolerance = frame.size * PICK_TOLERANCE;
+        let [plane_min, plane_max] = PLANE_HANDLE.map(|extent| extent * frame.size);
+        let mut closest: Option<(f32, GizmoHandle)> = None;
+        let mut consider = |distance: f32, handle| {
+            if distance < closest.map_or(f32::INFINITY, |(closest, _)| closest) {
+                closest = Some((distance, handle));
+            }
+        };
+        for (index, axis) in frame.axes.iter().enumerate() {
+            let on_axis = ray
+                .closest_to_line(frame.origin, *axis)
+                .filter(|(distance, s)| {
+                    (0.0..=frame.size).contains(s)
+                        && (ray.at(*distance) - (frame.origin + *axis * *s)).length() < tolerance
+                })
+                .map(|(distance, _)| distance);
+            let on_plane = ray
+                .intersect_plane(frame.origin, *axis)
+                .map(|distance| (distance, ray.at(distance) - frame.origin));
+            match self.settings.mode {
+                GizmoMode::Translate => {
+                    if let Some(distance) = on_axis {
+                        consider(distance, GizmoHandle::Axis(index));
+                    }
+                    let [u, v] = [frame.axes[(index + 1) % 3], frame.axes[(index + 2) % 3]];
+                    let in_handle = |offset: &Array4, axis: &Array4| {
+                        (plane_min..=plane_max).contains(&dot(offset, axis))
+                    };
+                    if let Some((distance, offset)) = on_plane {
+                        if in_handle(&offset, &u) && in_handle(&offset, &v) {
+                            consider(distance, GizmoHandle::Plane(index));
+                        }
+                    }
+                }
+                GizmoMode::Rotate => {
+                    if let Some((distance, offset)) = on_plane {
+                        if (offset.length() - frame.size).abs() < tolerance {
+                            consider(distance, GizmoHandle::Ring(index));
+                        }
+                    }
+                }
+                GizmoMode::Scale => {
+                    let end = frame.origin + *axis * frame.size;
+                    if ray.distance_to_point(end) < tolerance * 1.5 {
+                        consider(
+                            dot(&(end - ray.origin), &ray.direction),
+                            GizmoHandle::ScaleBox(index),
+                        );
+                    } else if let Some(distance) = on_axis {
+                        consider(distance, GizmoHandle::ScaleBox(index));
+                    }
+                }
+            }
+        }
+        closest.map(|(_, handle)| handle)
+    }
+
+    fn drag_to(&self, drag: &Drag, ray: &Ray, snap: bool, graph: &mut SceneGraph) -> bool {
+        let Some(point) = handle_point(drag.handle, &drag.frame, ray) else {
+            return false;
+        };
+        let settings = &self.settings;
+        let frame = &drag.frame;
+        let snapped = |value: f32, step: f32| {
+            if snap && step > 0.0 {
+                (value / step).round() * step
+            } else {
+                value
+            }
+        };
+        let translated = |offset: Array4| {
+            let local = drag.parent_inverse * offset;
+            let [x, y, z] = drag.start.translation;
+            [x + local.0[0], y + local.0[1], z + local.0[2]]
+        };
+
+        let mut transform = drag.start;
+        match drag.handle {
+            GizmoHandle::Axis(index) => {
+                let axis = frame.axes[index];
+                let distance = dot(&(point - drag.start_point), &axis);
+                transform.translation =
+                    translated(axis * snapped(distance, settings.translate_snap));
+            }
+            GizmoHandle::Plane(index) => {
+                let moved = point - drag.start_point;
+                let offset = [(index + 1) % 3, (index + 2) % 3].map(|other| {
+                    let axis = frame.axes[other];
+                    axis * snapped(dot(&moved, &axis), settings.translate_snap)
+                });
+                transform.translation = translated(offset[0] + offset[1]);
+            }
+            GizmoHandle::Ring(index) => {
+                let axis = frame.axes[index];
+                let from = drag.start_point - frame.origin;
+                let to = point - frame.origin;
+                let angle = dot(&cross(&from, &to), &axis).atan2(dot(&from, &to));
+                let angle = snapped(angle.to_degrees(), settings.rotate_snap).to_radians();
+                let mut parent_axis = drag.parent_inverse * axis;
+                parent_axis.0[3] = 0.0;
+                parent_axis.normalize();
+                let [x, y, z, _] = parent_axis.0;
+                transform.rotation = quaternion_normalize(quaternion_mul(
+                    quaternion_from_axis_angle([x, y, z], angle),
+                    drag.start.rotation,
+                ));
+            }
+            GizmoHandle::ScaleBox(index) => {
+                let axis = frame.axes[index];
+                let from = dot(&(drag.start_point - frame.origin), &axis);
+                let to = dot(&(point - frame.origin), &axis);
+                if from.abs() > 1e-6 {
+                    let scale = drag.start.scale[index] * to / from;
+                    let min = if snap {
+                        settings.scale_snap.max(MIN_SCALE)
+                    } else {
+                        MIN_SCALE
+                    };
+                    transform.scale[index] = snapped(scale, settings.scale_snap).max(min);
+                }
+            }
+        }
+
+        let Some(node) = graph.get(drag.node) else {
+            return false;
+        };
+        if *node.transform() == transform {
+            return false;
+        }
+        graph.set_transform(drag.node, transform);
+        true
+    }
+
+    fn layout(&mut self, frame: &GizmoFrame, camera: &CameraInfo, screen_size: [f32; 2]) {
+        let project = |point: Array4| camera.world_to_screen(point, screen_size);
+        let Some(origin) = project(frame.origin) else {
+            return;
+        };
+        let active = self.drag.map(|drag| drag.handle).or(self.hovered);
+        let color = |handle, index: usize| {
+            if active == Some(handle) {
+                HIGHLIGHT
+            } else {
+                AXIS_COLORS[index]
+            }
+        };
+        let [plane_min, plane_max] = PLANE_HANDLE.map(|extent| extent * frame.size);
+
+        for (index, axis) in frame.axes.iter().enumerate() {
+            let [u, v] = [frame.axes[(index + 1) % 3], frame.axes[(index + 2) % 3]];
+            let end = project(frame.origin + *axis * frame.size);
+            match self.settings.mode {
+                GizmoMode::Translate => {
+                    let corners = [
+                        [plane_min, plane_min],
+                        [plane_max, plane_min],
+                        [plane_max, plane_max],
+                        [plane_min, plane_max],
+                    ]
+                    .map(|[a, b]| project(frame.origin + u * a + v * b));
+                    if let [Some(a), Some(b), Some(c), Some(d)] = corners {
+                        let mut plane_color = color(GizmoHandle::Plane(index), index);
+                        plane_color[3] = PLANE_ALPHA;
+                        self.batch.triangle([a, b, c], plane_color);
+                        self.batch.triangle([c, d, a], plane_color);
+                    }
+                    if let Some(end) = end {
+                        let axis_color = color(GizmoHandle::Axis(index), index);
+                        self.batch.line(origin, end, LINE_WIDTH, axis_color);
+                        self.arrow_head(origin, end, axis_color);
+                    }
+                }
+                GizmoMode::Rotate => {
+                    let ring_color = color(GizmoHandle::Ring(index), index);
+                    let points: Vec<_> = (0..=RING_SEGMENTS)
+                        .map(|segment| {
+                            let angle =
+                                segment as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+                            project(frame.origin + (u * angle.cos() + v * angle.sin()) * frame.size)
+                        })
+                        .collect();
+                    for pair in points.windows(2) {
+                        if let [Some(from), Some(to)] = pair {
+                            self.batch.line(*from, *to, LINE_WIDTH, ring_color);
+                        }
+                    }
+                }
+                GizmoMode::Scale => {
+                    if let Some(end) = end {
+                        let box_color = color(GizmoHandle::ScaleBox(index), index);
+                        self.batch.line(origin, end, LINE_WIDTH, box_color);
+                        self.batch.rect(
+                            end[0] - BOX_SIZE * 0.5,
+                            end[1] - BOX_SIZE * 0.5,
+                            BOX_SIZE,
+                            BOX_SIZE,
+                            box_color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn arrow_head(&mut self, from: [f32; 2], tip: [f32; 2], color: Color) {
+        let [dx, dy] = [tip[0] - from[0], tip[1] - from[1]];
+        let length = (dx * dx + dy * dy).sqrt();
+        if length < 1.0 {
+            return;
+        }
+        let [dx, dy] = [dx / length * ARROW_SIZE, dy / length * ARROW_SIZE];
+        self.batch.triangle(
+            [
+                [tip[0] + dx, tip[1] + dy],
+                [tip[0] - dy * 0.5, tip[1] + dx * 0.5],
+                [tip[0] + dy * 0.5, tip[1] - dx * 0.5],
+            ],
+            color,
+        );
+    }
+}
+
+fn unit_axis(index: usize) -> Array4 {
+    let mut axis = Array4::default();
+    axis.0[index] = 1.0;
+    axis
+}
+
+/// Where the ray meets the handle: on its axis line or in its plane.
+fn handle_point(handle: GizmoHandle, frame: &GizmoFrame, ray: &Ray) -> Option<Array4> {
+    match handle {
+        GizmoHandle::Axis(index) | GizmoHandle::ScaleBox(index) => {
+            let axis = frame.axes[index];
+            let (_, s) = ray.closest_to_line(frame.origin, axis)?;
+            Some(frame.origin + axis * s)
+        }
+        GizmoHandle::Plane(index) | GizmoHandle::Ring(index) => {
+            let distance = ray.intersect_plane(frame.origin, frame.axes[index])?;
+            Some(ray.at(distance))
+        }
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{Gizmo, GizmoHandle, GizmoInput, GizmoMode, GizmoSpace};
+    use crate::runtime::{
+        core::mathematics::{transform::Transform, Array4},
+        functions::scene_system::{camera::CameraInfo, graph::SceneGraph},
+    };
+
+    #[test]
+    fn drag_handles() {
+        const SCREEN: [f32; 2] = [1280.0, 720.0];
+        let camera = CameraInfo::default();
+        let mut graph = SceneGraph::default();
+        let parent = graph.spawn(
+            "Parent".to_owned(),
+            None,
+            Transform {
+                translation: [0.0, 5.0, -10.0],
+                scale: [2.0; 3],
+                ..Default::default()
+            },
+        );
+        let node = graph.spawn("Node".to_owned(), Some(parent), Transform::default());
+        let size = 10.0 * super::SIZE_PER_DISTANCE;
+        let screen = |point: [f32; 3]| {
+            camera
+                .world_to_screen(Array4::new([point[0], point[1], point[2], 1.0]), SCREEN)
+                .unwrap()
+        };
+        let input = |cursor: [f32; 2], pressed, held, snap| GizmoInput {
+            cursor,
+            pressed,
+            held,
+            snap,
+        };
+        let mut gizmo = Gizmo::new();
+
+        // dragging the x axis moves along x only, in the parent's scaled space
+        let start = screen([size * 0.6, 5.0, -10.0]);
+        gizmo.update(
+            &mut graph,
+            Some(node),
+            &camera,
+            SCREEN,
+            input(start, false, false, false),
+        );
+        assert_eq!(gizmo.hovered, Some(GizmoHandle::Axis(0)));
+        assert!(!gizmo.batch().vertices.is_empty());
+        gizmo.update(
+            &mut graph,
+            Some(node),
+            &camera,
+            SCREEN,
+            input(start, true, true, false),
+        );
+        let target = screen([size * 0.6 + 1.0, 5.0, -10.0]);
+        let changed = gizmo.update(
+            &mut graph,
+            Some(node),
+            &camera,
+            SCREEN,
+            input(target, false, true, false),
+        );
+        assert!(changed);
+        let [x, y, z] = graph.get(node).unwrap().transform().translation;
+        assert!(
+            (x - 0.5).abs() < 1e-3 && y.abs() < 1e-4 && z.abs() < 1e-4,
+            "{} {} {}",
+            x,
+            y,
+            z
+        );
+
+        // snapping rounds the world space distance
+        let target = screen([size * 0.6 + 0.6, 5.0, -10.0]);
+        gizmo.update(
+            &mut graph,
+            Some(node),
+            &camera,
+            SCREEN,
+            input(target, false, true, true),
+        );
+        assert!((graph.get(node).unwrap().transform().translation[0] - 0.25).abs() < 1e-4);
+        gizmo.update(
+            &mut graph,
+            Some(node),
+            &camera,
+            SCREEN,
+            input(target, false, false, false),
+        );
+        assert!(!gizmo.update(
+            &mut graph,
+            Some(node),
+            &camera,
+            SCREEN,
+            input(start, false, false, false)
+        ));
+
+        // the z ring rotates around z, a quarter turn with snapping
+        graph.set_transform(node, Transform::default());
+        gizmo.settings.mode = GizmoMode::Rotate;
+        gizmo.settings.space = GizmoSpace::Local;
+        let start = screen([size, 5.0, -10.0]);
+        gizmo.update(
+            &mut graph,
+            Some(node),
+            &camera,
+            SCREEN,
+            input(start, true, true, false),
+        );
+        let target = screen([0.1, 5.0 + size, -10.0]);
+        gizmo.update(
+            &mut graph,
+            Some(node),
+            &camera,
+            SCREEN,
+            input(target, false, true, true),
+        );
+        let euler = graph.get(node).unwrap().transform().euler_degrees();
+        assert!((euler[2] - 90.0).abs() < 1e-3, "{:?}", euler);
+        gizmo.update(
+            &mut graph,
+            Some(node),
+            &camera,
+            SCREEN,
+            input(target, false, false, false),
+        );
+
+        // dragging a scale box through the origin stops at a positive scale
+        graph.set_transform(node, Transform::default());
+        gizmo.settings.mode = GizmoMode::Scale;
+        let start = screen([size, 5.0, -10.0]);
+        gizmo.update(
+            &mut graph,
+            Some(node),
+            &camera,
+            SCREEN,
+            input(start, true, true, false),
+        );
+        assert_eq!(gizmo.hovered, Some(GizmoHandle::ScaleBox(0)));
+        let target = screen([-size, 5.0, -10.0]);
+        gizmo.update(
+            &mut graph,
+            Some(node),
+            &camera,
+            SCREEN,
+            input(target, false, true, false),
+        );
+        assert_eq!(
+            graph.get(node).unwrap().transform().scale[0],
+            super::MIN_SCALE
+        );
+        gizmo.update(
+            &mut graph,
+            Some(node),
+            &camera,
+            SCREEN,
+            input(target, false, true, true),
+        );
+        assert_eq!(
+            graph.get(node).unwrap().transform().scale[0],
+            gizmo.settings.scale_snap
+        );
+    }
+}
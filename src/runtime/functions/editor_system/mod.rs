@@ -0,0 +1,153 @@
+use winit::event::MouseButton;
+
+use self::{
+    command::{CommandStack, EditCommand},
+    gizmo::{Gizmo, GizmoInput, GizmoMode, GizmoSpace},
+};
+
+use super::{
+    event_system::input_state::InputState,
+    render_system::{overlay::OverlayBatch, picking::PickResult},
+    scene_system::{camera::CameraInfo, SceneManager},
+};
+
+pub mod command;
+pub mod gizmo;
+pub mod panel;
+
+/// Scene editing on top of the GUI: click selection, the transform gizmo on the
+/// selected node and the undo history all edits go through.
+pub struct EditorManager {
+    pub gizmo: Gizmo,
+    pub history: CommandStack,
+    // a viewport click waiting to be handed to the object picker
+    pick_request: Option<[u32; 2]>,
+    // the scene `history` edits, see `SceneManager::scene_generation`
+    scene_generation: u64,
+}
+
+impl EditorManager {
+    pub fn new() -> Self {
+        EditorManager {
+            gizmo: Gizmo::new(),
+            history: CommandStack::default(),
+            pick_request: None,
+            scene_generation: 0,
+        }
+    }
+
+    pub fn set_gizmo_mode(&mut self, mode: GizmoMode) {
+        self.gizmo.settings.mode = mode;
+    }
+
+    pub fn toggle_gizmo_space(&mut self) {
+        self.gizmo.settings.space = match self.gizmo.settings.space {
+            GizmoSpace::World => GizmoSpace::Local,
+            GizmoSpace::Local => GizmoSpace::World,
+        };
+    }
+
+    /// Runs the gizmo on the scene selection, only while `enabled`, i.e. the GUI is shown.
+    pub fn update(
+        &mut self,
+        input: &InputState,
+        scene_manager: &mut SceneManager,
+        camera: &CameraInfo,
+        screen_size: [f32; 2],
+        enabled: bool,
+    ) {
+        self.forget_replaced_scene(scene_manager);
+        let (x, y) = input.cursor_position();
+        let pressed = input.button_pressed(MouseButton::Left);
+        let selection = scene_manager.selection.filter(|_| enabled);
+        let before = selection
+            .and_then(|node| scene_manager.graph.get(node))
+            .map(|node| *node.transform());
+        let changed = self.gizmo.update(
+            &mut scene_manager.graph,
+            selection,
+            camera,
+            screen_size,
+            GizmoInput {
+                cursor: [x as f32, y as f32],
+                pressed,
+                held: input.button_held(MouseButton::Left),
+                snap: input.modifiers().ctrl(),
+            },
+        );
+        let after = selection
+            .and_then(|node| scene_manager.graph.get(node))
+            .map(|node| *node.transform());
+        if let (true, Some(node), Some(before), Some(after)) = (changed, selection, before, after) {
+            self.history.record(
+                EditCommand::Transform {
+                    node,
+                    before,
+                    after,
+                },
+                &mut scene_manager.graph,
+            );
+        }
+        // clicks on the GUI never get here, clicks on the gizmo are its own
+        if enabled && pressed && !self.gizmo.captures_pointer() && x >= 0.0 && y >= 0.0 {
+            self.pick_request = Some([x as u32, y as u32]);
+        }
+    }
+
+    /// The pixel to pick the object under, see `ObjectPicker::request`.
+    #[inline]
+    pub fn take_pick_request(&mut self) -> Option<[u32; 2]> {
+        self.pick_request.take()
+    }
+
+    /// Selects the picked node, clicks on the background clear the selection.
+    pub fn select_picked(&mut self, pick: PickResult, scene_manager: &mut SceneManager) {
+        let graph = &scene_manager.graph;
+        scene_manager.selection = pick.node.filter(|node| graph.is_attached(*node));
+    }
+
+    pub fn undo(&mut self, scene_manager: &mut SceneManager) {
+        self.forget_replaced_scene(scene_manager);
+        if self
+            .history
+            .undo(&mut scene_manager.graph, &mut scene_manager.assets)
+        {
+            Self::drop_detached_selection(scene_manager);
+        }
+    }
+
+    pub fn redo(&mut self, scene_manager: &mut SceneManager) {
+        self.forget_replaced_scene(scene_manager);
+        if self
+            .history
+            .redo(&mut scene_manager.graph, &mut scene_manager.assets)
+        {
+            Self::drop_detached_selection(scene_manager);
+        }
+    }
+
+    /// Edits of a scene that was replaced can not be undone.
+    fn forget_replaced_scene(&mut self, scene_manager: &SceneManager) {
+        let generation = scene_manager.scene_generation();
+        if self.scene_generation != generation {
+            self.history.clear();
+            self.scene_generation = generation;
+        }
+    }
+
+    fn drop_detached_selection(scene_manager: &mut SceneManager) {
+        let graph = &scene_manager.graph;
+        if scene_manager
+            .selection
+            .is_some_and(|node| !graph.is_attached(node))
+        {
+            scene_manager.selection = None;
+        }
+    }
+
+    /// The gizmo handles, to be drawn below the GUI.
+    #[inline]
+    pub fn overlay(&self) -> &OverlayBatch {
+        self.gizmo.batch()
+    }
+}
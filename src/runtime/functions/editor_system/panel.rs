@@ -0,0 +1,37 @@
+use crate::runtime::functions::gui_system::{ui::Ui, Panel, PanelContext};
+
+use super::gizmo::{GizmoMode, GizmoSpace};
+
+/// Gizmo mode, space and snapping steps, Ctrl snaps while dragging too.
+pub struct GizmoPanel;
+
+impl Panel for GizmoPanel {
+    fn title(&self) -> &str {
+        "Gizmo"
+    }
+
+    fn ui(&mut self, ui: &mut Ui, context: &mut PanelContext) {
+        let editor_manager = &mut *context.editor_manager;
+        let mode = editor_manager.gizmo.settings.mode;
+        if ui.button(&format!("Mode: {:?}##gizmo_mode", mode)) {
+            editor_manager.set_gizmo_mode(match mode {
+                GizmoMode::Translate => GizmoMode::Rotate,
+                GizmoMode::Rotate => GizmoMode::Scale,
+                GizmoMode::Scale => GizmoMode::Translate,
+            });
+        }
+        let space = match editor_manager.gizmo.settings.space {
+            // scaling always works along the local axes
+            _ if mode == GizmoMode::Scale => GizmoSpace::Local,
+            space => space,
+        };
+        if ui.button(&format!("Space: {:?}##gizmo_space", space)) {
+            editor_manager.toggle_gizmo_space();
+        }
+        let settings = &mut editor_manager.gizmo.settings;
+        ui.checkbox("Snap", &mut settings.snap);
+        ui.slider("Move step", &mut settings.translate_snap, 0.05..=5.0);
+        ui.slider("Angle step", &mut settings.rotate_snap, 1.0..=90.0);
+        ui.slider("Scale step", &mut settings.scale_snap, 0.01..=1.0);
+    }
+}
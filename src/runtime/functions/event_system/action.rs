@@ -0,0 +1,401 @@
+use std::{fmt::Debug, path::Path};
+
+use winit::event::{ModifiersState, MouseButton, VirtualKeyCode};
+
+use super::{
+    gamepad::{GamepadAxis, GamepadButton},
+    input_state::InputState,
+};
+
+pub const DEFAULT_BINDINGS_PATH: &str = "assets/config/input.json";
+
+/// Deflection at which an axis bound to a digital trigger counts as pressed.
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+/// Engine actions that input can be bound to.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Exit,
+    Report,
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    ToggleWireframe,
+    Screenshot,
+    CyclePresentMode,
+    ToggleDebugOverlay,
+    ToggleGui,
+    GizmoTranslate,
+    GizmoRotate,
+    GizmoScale,
+    ToggleGizmoSpace,
+    Undo,
+    Redo,
+}
+
+/// When an action is considered to fire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionTrigger {
+    /// The frame the bound input went down.
+    Pressed,
+    /// The frame the bound input went up.
+    #[allow(dead_code)]
+    Released,
+    /// Every frame the bound input is down.
+    Held,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputSource {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    GamepadButton(GamepadButton),
+    GamepadAxis {
+        axis: GamepadAxis,
+        direction: AxisDirection,
+    },
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+impl AxisDirection {
+    /// Deflection towards this direction, zero when pointing the other way.
+    fn apply(&self, value: f32) -> f32 {
+        match self {
+            AxisDirection::Positive => value.max(0.0),
+            AxisDirection::Negative => (-value).max(0.0),
+        }
+    }
+}
+
+/// Modifier keys that have to be held together with the bound input.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    #[serde(default)]
+    pub ctrl: bool,
+    #[serde(default)]
+    pub shift: bool,
+    #[serde(default)]
+    pub alt: bool,
+}
+
+impl Chord {
+    pub const NONE: Chord = Chord {
+        ctrl: false,
+        shift: false,
+        alt: false,
+    };
+
+    fn satisfied_by(&self, modifiers: ModifiersState) -> bool {
+        (!self.ctrl || modifiers.ctrl())
+            && (!self.shift || modifiers.shift())
+            && (!self.alt || modifiers.alt())
+    }
+
+    /// `self` asks for every modifier of `other` plus at least one more.
+    fn extends(&self, other: &Chord) -> bool {
+        self != other
+            && (self.ctrl || !other.ctrl)
+            && (self.shift || !other.shift)
+            && (self.alt || !other.alt)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub action: Action,
+    pub input: InputSource,
+    #[serde(default, skip_serializing_if = "is_no_chord")]
+    pub modifiers: Chord,
+}
+
+fn is_no_chord(chord: &Chord) -> bool {
+    *chord == Chord::NONE
+}
+
+/// Maps actions to keys, mouse buttons and modifier chords.
+///
+/// When two bindings share an input, the one with more satisfied modifiers wins,
+/// so `Ctrl+S` does not also fire the action bound to a plain `S`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ActionMap {
+    pub bindings: Vec<Binding>,
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        let key = |action, key| Binding {
+            action,
+            input: InputSource::Key(key),
+            modifiers: Chord::NONE,
+        };
+        let ctrl = |action, key| Binding {
+            action,
+            input: InputSource::Key(key),
+            modifiers: Chord {
+                ctrl: true,
+                ..Chord::NONE
+            },
+        };
+        let stick = |action, axis, direction| Binding {
+            action,
+            input: InputSource::GamepadAxis { axis, direction },
+            modifiers: Chord::NONE,
+        };
+        let pad = |action, button| Binding {
+            action,
+            input: InputSource::GamepadButton(button),
+            modifiers: Chord::NONE,
+        };
+        ActionMap {
+            bindings: vec![
+                key(Action::Exit, VirtualKeyCode::Escape),
+                key(Action::Report, VirtualKeyCode::R),
+                key(Action::MoveForward, VirtualKeyCode::W),
+                key(Action::MoveBackward, VirtualKeyCode::S),
+                key(Action::MoveLeft, VirtualKeyCode::A),
+                key(Action::MoveRight, VirtualKeyCode::D),
+                key(Action::ToggleWireframe, VirtualKeyCode::F1),
+                key(Action::Screenshot, VirtualKeyCode::F12),
+                key(Action::CyclePresentMode, VirtualKeyCode::F2),
+                key(Action::ToggleDebugOverlay, VirtualKeyCode::F3),
+                key(Action::ToggleGui, VirtualKeyCode::F4),
+                key(Action::GizmoTranslate, VirtualKeyCode::Key1),
+                key(Action::GizmoRotate, VirtualKeyCode::Key2),
+                key(Action::GizmoScale, VirtualKeyCode::Key3),
+                key(Action::ToggleGizmoSpace, VirtualKeyCode::X),
+                ctrl(Action::Undo, VirtualKeyCode::Z),
+                ctrl(Action::Redo, VirtualKeyCode::Y),
+                stick(
+                    Action::MoveForward,
+                    GamepadAxis::LeftStickY,
+                    AxisDirection::Positive,
+                ),
+                stick(
+                    Action::MoveBackward,
+                    GamepadAxis::LeftStickY,
+                    AxisDirection::Negative,
+                ),
+                stick(
+                    Action::MoveLeft,
+                    GamepadAxis::LeftStickX,
+                    AxisDirection::Negative,
+                ),
+                stick(
+                    Action::MoveRight,
+                    GamepadAxis::LeftStickX,
+                    AxisDirection::Positive,
+                ),
+                pad(Action::ToggleWireframe, GamepadButton::North),
+                pad(Action::Screenshot, GamepadButton::Select),
+            ],
+        }
+    }
+}
+
+impl ActionMap {
+    pub fn load<P: AsRef<Path> + Debug>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = std::fs::File::open(path.as_ref())?;
+        let reader = std::io::BufReader::new(file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Loads the bindings from `path`, falling back to the built-in ones.
+    pub fn load_or_default<P: AsRef<Path> + Debug>(path: P) -> Self {
+        match Self::load(&path) {
+            Ok(action_map) => {
+                log::info!("Loaded input bindings from {:?}", path);
+                action_map
+            }
+            Err(err) => {
+                log::warn!(
+                    "Unable to load input bindings {:?}: {}, using defaults",
+                    path,
+                    err
+                );
+                ActionMap::default()
+            }
+        }
+    }
+
+    pub fn triggered(&self, action: Action, trigger: ActionTrigger, input: &InputState) -> bool {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.action == action)
+            .any(|binding| self.binding_triggered(binding, trigger, input))
+    }
+
+    /// Analog strength of `action` in `0.0..=1.0`, digital inputs give 0 or 1.
+    pub fn value(&self, action: Action, input: &InputState) -> f32 {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.action == action)
+            .map(|binding| match binding.input {
+                InputSource::GamepadAxis { axis, direction } => {
+                    direction.apply(input.gamepad_axis(axis))
+                }
+                _ if self.binding_triggered(binding, ActionTrigger::Held, input) => 1.0,
+                _ => 0.0,
+            })
+            .fold(0.0, f32::max)
+    }
+
+    fn binding_triggered(
+        &self,
+        binding: &Binding,
+        trigger: ActionTrigger,
+        input: &InputState,
+    ) -> bool {
+        let source_triggered = match (binding.input, trigger) {
+            (InputSource::Key(key), ActionTrigger::Pressed) => input.key_pressed(key),
+            (InputSource::Key(key), ActionTrigger::Released) => input.key_released(key),
+            (InputSource::Key(key), ActionTrigger::Held) => input.key_held(key),
+            (InputSource::Mouse(button), ActionTrigger::Pressed) => input.button_pressed(button),
+            (InputSource::Mouse(button), ActionTrigger::Released) => input.button_released(button),
+            (InputSource::Mouse(button), ActionTrigger::Held) => input.button_held(button),
+            (InputSource::GamepadButton(button), ActionTrigger::Pressed) => {
+                input.gamepad_button_pressed(button)
+            }
+            (InputSource::GamepadButton(button), ActionTrigger::Released) => {
+                input.gamepad_button_released(button)
+            }
+            (InputSource::GamepadButton(button), ActionTrigger::Held) => {
+                input.gamepad_button_held(button)
+            }
+            (InputSource::GamepadAxis { axis, direction }, trigger) => {
+                let down = direction.apply(input.gamepad_axis(axis)) >= AXIS_PRESS_THRESHOLD;
+                let was_down =
+                    direction.apply(input.previous_gamepad_axis(axis)) >= AXIS_PRESS_THRESHOLD;
+                match trigger {
+                    ActionTrigger::Pressed => down && !was_down,
+                    ActionTrigger::Released => !down && was_down,
+                    ActionTrigger::Held => down,
+                }
+            }
+        };
+        let modifiers = input.modifiers();
+        source_triggered
+            && binding.modifiers.satisfied_by(modifiers)
+            && !self.bindings.iter().any(|other| {
+                other.input == binding.input
+                    && other.modifiers.extends(&binding.modifiers)
+                    && other.modifiers.satisfied_by(modifiers)
+            })
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use winit::event::{ElementState, ModifiersState, VirtualKeyCode};
+
+    use super::{
+        Action, ActionMap, ActionTrigger, Binding, Chord, InputSource, DEFAULT_BINDINGS_PATH,
+    };
+    use crate::runtime::functions::event_system::{
+        gamepad::{GamepadAxis, GamepadButton, GamepadEvent},
+        input_state::{InputEvent, InputState},
+    };
+
+    #[test]
+    fn default_bindings() {
+        let actions = ActionMap::default();
+        let mut input = InputState::new();
+        input.process_key(VirtualKeyCode::W, ElementState::Pressed);
+        assert!(actions.triggered(Action::MoveForward, ActionTrigger::Pressed, &input));
+        assert!(actions.triggered(Action::MoveForward, ActionTrigger::Held, &input));
+        assert!(!actions.triggered(Action::MoveBackward, ActionTrigger::Held, &input));
+
+        input.end_frame();
+        input.process_key(VirtualKeyCode::W, ElementState::Released);
+        assert!(actions.triggered(Action::MoveForward, ActionTrigger::Released, &input));
+        assert!(!actions.triggered(Action::MoveForward, ActionTrigger::Held, &input));
+    }
+
+    #[test]
+    fn chord_shadows_plain_binding() {
+        let mut actions = ActionMap::default();
+        let ctrl = Chord {
+            ctrl: true,
+            ..Chord::NONE
+        };
+        actions
+            .bindings
+            .retain(|binding| binding.action != Action::Screenshot);
+        actions.bindings.push(Binding {
+            action: Action::Screenshot,
+            input: InputSource::Key(VirtualKeyCode::S),
+            modifiers: ctrl,
+        });
+
+        let mut input = InputState::new();
+        input.process_key(VirtualKeyCode::S, ElementState::Pressed);
+        assert!(actions.triggered(Action::MoveBackward, ActionTrigger::Held, &input));
+        assert!(!actions.triggered(Action::Screenshot, ActionTrigger::Pressed, &input));
+
+        input.process_event(&InputEvent::Modifiers(ModifiersState::CTRL));
+        assert!(!actions.triggered(Action::MoveBackward, ActionTrigger::Held, &input));
+        assert!(actions.triggered(Action::Screenshot, ActionTrigger::Pressed, &input));
+    }
+
+    #[test]
+    fn azerty_rebinding_from_json() {
+        let json = r#"{
+            "bindings": [
+                { "action": "MoveForward", "input": { "Key": "Z" } },
+                { "action": "MoveLeft", "input": { "Key": "Q" } },
+                { "action": "Screenshot", "input": { "Key": "P" }, "modifiers": { "ctrl": true } },
+                { "action": "Report", "input": { "Mouse": "Middle" } }
+            ]
+        }"#;
+        let actions: ActionMap = serde_json::from_str(json).unwrap();
+        assert_eq!(actions.bindings.len(), 4);
+
+        let mut input = InputState::new();
+        input.process_key(VirtualKeyCode::Z, ElementState::Pressed);
+        input.process_key(VirtualKeyCode::Q, ElementState::Pressed);
+        assert!(actions.triggered(Action::MoveForward, ActionTrigger::Held, &input));
+        assert!(actions.triggered(Action::MoveLeft, ActionTrigger::Held, &input));
+        assert!(!actions.triggered(Action::MoveBackward, ActionTrigger::Held, &input));
+
+        let round_trip: ActionMap =
+            serde_json::from_str(&serde_json::to_string(&actions).unwrap()).unwrap();
+        assert_eq!(round_trip.bindings, actions.bindings);
+    }
+
+    #[test]
+    fn gamepad_bindings() {
+        let actions = ActionMap::default();
+        let mut input = InputState::new();
+        input.process_gamepad_event(&GamepadEvent::Connected(0));
+        input.process_gamepad_event(&GamepadEvent::Axis {
+            id: 0,
+            axis: GamepadAxis::LeftStickY,
+            value: -0.83,
+        });
+        assert!((actions.value(Action::MoveBackward, &input) - 0.8).abs() < 1e-6);
+        assert_eq!(actions.value(Action::MoveForward, &input), 0.0);
+        assert!(actions.triggered(Action::MoveBackward, ActionTrigger::Pressed, &input));
+
+        input.end_frame();
+        assert!(!actions.triggered(Action::MoveBackward, ActionTrigger::Pressed, &input));
+        assert!(actions.triggered(Action::MoveBackward, ActionTrigger::Held, &input));
+
+        input.process_gamepad_event(&GamepadEvent::Button {
+            id: 0,
+            button: GamepadButton::North,
+            pressed: true,
+        });
+        assert!(actions.triggered(Action::ToggleWireframe, ActionTrigger::Pressed, &input));
+    }
+
+    #[test]
+    fn shipped_bindings_match_defaults() {
+        let actions = ActionMap::load(DEFAULT_BINDINGS_PATH).unwrap();
+        assert_eq!(actions.bindings, ActionMap::default().bindings);
+    }
+}
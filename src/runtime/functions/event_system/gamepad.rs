@@ -0,0 +1,211 @@
+use std::collections::VecDeque;
+
+pub type GamepadId = usize;
+
+/// Stick and trigger deflection below this is treated as zero.
+pub const DEFAULT_DEAD_ZONE: f32 = 0.15;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// Sticks go from -1.0 to 1.0 (up and right are positive), triggers from 0.0 to 1.0.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    /// The other axis of the same stick, sticks get a radial dead zone.
+    pub fn stick_pair(&self) -> Option<GamepadAxis> {
+        match self {
+            GamepadAxis::LeftStickX => Some(GamepadAxis::LeftStickY),
+            GamepadAxis::LeftStickY => Some(GamepadAxis::LeftStickX),
+            GamepadAxis::RightStickX => Some(GamepadAxis::RightStickY),
+            GamepadAxis::RightStickY => Some(GamepadAxis::RightStickX),
+            GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => None,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum GamepadEvent {
+    Connected(GamepadId),
+    Disconnected(GamepadId),
+    Button {
+        id: GamepadId,
+        button: GamepadButton,
+        pressed: bool,
+    },
+    /// Raw axis value, dead zones are applied by the input state.
+    Axis {
+        id: GamepadId,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+/// Source of gamepad events, polled once per frame.
+pub trait GamepadBackend {
+    fn poll_event(&mut self) -> Option<GamepadEvent>;
+}
+
+/// Backend fed by hand, for tests and scripted input.
+#[allow(dead_code)]
+#[derive(Debug, Default)]
+pub struct VirtualGamepad {
+    events: VecDeque<GamepadEvent>,
+}
+
+#[allow(dead_code)]
+impl VirtualGamepad {
+    pub fn new() -> Self {
+        VirtualGamepad::default()
+    }
+
+    pub fn connect(&mut self, id: GamepadId) {
+        self.events.push_back(GamepadEvent::Connected(id));
+    }
+
+    pub fn disconnect(&mut self, id: GamepadId) {
+        self.events.push_back(GamepadEvent::Disconnected(id));
+    }
+
+    pub fn set_button(&mut self, id: GamepadId, button: GamepadButton, pressed: bool) {
+        self.events.push_back(GamepadEvent::Button {
+            id,
+            button,
+            pressed,
+        });
+    }
+
+    pub fn set_axis(&mut self, id: GamepadId, axis: GamepadAxis, value: f32) {
+        self.events
+            .push_back(GamepadEvent::Axis { id, axis, value });
+    }
+}
+
+impl GamepadBackend for VirtualGamepad {
+    fn poll_event(&mut self) -> Option<GamepadEvent> {
+        self.events.pop_front()
+    }
+}
+
+/// The platform backend if the `gamepad` feature is enabled and it initializes.
+pub fn default_backend() -> Option<Box<dyn GamepadBackend>> {
+    #[cfg(feature = "gamepad")]
+    match gilrs::Gilrs::new() {
+        Ok(gilrs) => return Some(Box::new(GilrsBackend { gilrs })),
+        Err(err) => log::warn!("Gamepad support unavailable: {}", err),
+    }
+    None
+}
+
+#[cfg(feature = "gamepad")]
+pub struct GilrsBackend {
+    gilrs: gilrs::Gilrs,
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadBackend for GilrsBackend {
+    fn poll_event(&mut self) -> Option<GamepadEvent> {
+        use gilrs::EventType;
+
+        // skip events that have no engine counterpart instead of ending the poll
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            let id = usize::from(id);
+            let converted = match event {
+                EventType::Connected => Some(GamepadEvent::Connected(id)),
+                EventType::Disconnected => Some(GamepadEvent::Disconnected(id)),
+                EventType::ButtonPressed(button, _) => {
+                    convert_button(button).map(|button| GamepadEvent::Button {
+                        id,
+                        button,
+                        pressed: true,
+                    })
+                }
+                EventType::ButtonReleased(button, _) => {
+                    convert_button(button).map(|button| GamepadEvent::Button {
+                        id,
+                        button,
+                        pressed: false,
+                    })
+                }
+                // analog triggers are reported as buttons by gilrs
+                EventType::ButtonChanged(gilrs::Button::LeftTrigger2, value, _) => {
+                    Some(GamepadEvent::Axis {
+                        id,
+                        axis: GamepadAxis::LeftTrigger,
+                        value,
+                    })
+                }
+                EventType::ButtonChanged(gilrs::Button::RightTrigger2, value, _) => {
+                    Some(GamepadEvent::Axis {
+                        id,
+                        axis: GamepadAxis::RightTrigger,
+                        value,
+                    })
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    convert_axis(axis).map(|axis| GamepadEvent::Axis { id, axis, value })
+                }
+                _ => None,
+            };
+            if converted.is_some() {
+                return converted;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(feature = "gamepad")]
+fn convert_button(button: gilrs::Button) -> Option<GamepadButton> {
+    Some(match button {
+        gilrs::Button::South => GamepadButton::South,
+        gilrs::Button::East => GamepadButton::East,
+        gilrs::Button::North => GamepadButton::North,
+        gilrs::Button::West => GamepadButton::West,
+        gilrs::Button::LeftTrigger => GamepadButton::LeftBumper,
+        gilrs::Button::RightTrigger => GamepadButton::RightBumper,
+        gilrs::Button::Select => GamepadButton::Select,
+        gilrs::Button::Start => GamepadButton::Start,
+        gilrs::Button::LeftThumb => GamepadButton::LeftStick,
+        gilrs::Button::RightThumb => GamepadButton::RightStick,
+        gilrs::Button::DPadUp => GamepadButton::DPadUp,
+        gilrs::Button::DPadDown => GamepadButton::DPadDown,
+        gilrs::Button::DPadLeft => GamepadButton::DPadLeft,
+        gilrs::Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+#[cfg(feature = "gamepad")]
+fn convert_axis(axis: gilrs::Axis) -> Option<GamepadAxis> {
+    Some(match axis {
+        gilrs::Axis::LeftStickX => GamepadAxis::LeftStickX,
+        gilrs::Axis::LeftStickY => GamepadAxis::LeftStickY,
+        gilrs::Axis::RightStickX => GamepadAxis::RightStickX,
+        gilrs::Axis::RightStickY => GamepadAxis::RightStickY,
+        _ => return None,
+    })
+}
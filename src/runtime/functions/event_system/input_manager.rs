@@ -0,0 +1,171 @@
+use winit::event::{DeviceEvent, WindowEvent};
+
+use super::{
+    action::{Action, ActionMap, ActionTrigger},
+    gamepad::GamepadBackend,
+    input_state::{InputEvent, InputState},
+    replay::InputMode,
+};
+
+/// Owns the raw input state, the action bindings and the gamepad backend.
+///
+/// In replay mode live events are ignored and each frame is fed from the recording.
+pub struct InputManager {
+    pub state: InputState,
+    pub action_map: ActionMap,
+    gamepad_backend: Option<Box<dyn GamepadBackend>>,
+    mode: InputMode,
+}
+
+impl InputManager {
+    pub fn new(
+        action_map: ActionMap,
+        gamepad_backend: Option<Box<dyn GamepadBackend>>,
+        mode: InputMode,
+    ) -> Self {
+        InputManager {
+            state: InputState::new(),
+            action_map,
+            gamepad_backend,
+            mode,
+        }
+    }
+
+    pub fn process_window_event(&mut self, event: &WindowEvent) {
+        if let Some(event) = InputEvent::from_window_event(event) {
+            self.process_live_event(event);
+        }
+    }
+
+    pub fn process_device_event(&mut self, event: &DeviceEvent) {
+        if let Some(event) = InputEvent::from_device_event(event) {
+            self.process_live_event(event);
+        }
+    }
+
+    /// Drains pending gamepad events into the input state.
+    pub fn poll_gamepads(&mut self) {
+        let Some(backend) = self.gamepad_backend.as_mut() else {
+            return;
+        };
+        while let Some(event) = backend.poll_event() {
+            match &mut self.mode {
+                InputMode::Live => {}
+                InputMode::Record { recorder, .. } => recorder.record(InputEvent::Gamepad(event)),
+                InputMode::Replay(_) => continue,
+            }
+            self.state.process_gamepad_event(&event);
+        }
+    }
+
+    /// Returns the delta time the frame should run with, `None` once a replay is over.
+    pub fn begin_frame(&mut self, live_delta_t: f32) -> Option<f32> {
+        match &mut self.mode {
+            InputMode::Replay(replay) => {
+                let (delta_t, events) = replay.next_frame()?;
+                for timed in events {
+                    self.state.process_event(&timed.event);
+                }
+                Some(delta_t)
+            }
+            _ => Some(live_delta_t),
+        }
+    }
+
+    pub fn end_frame(&mut self, delta_t: f32) {
+        if let InputMode::Record { recorder, .. } = &mut self.mode {
+            recorder.end_frame(delta_t);
+        }
+        self.state.end_frame();
+    }
+
+    /// Saves the recording, if any, call when the session ends.
+    pub fn finish(&mut self) {
+        if let InputMode::Record { recorder, path } =
+            std::mem::replace(&mut self.mode, InputMode::Live)
+        {
+            let recording = recorder.finish();
+            match recording.save(&path) {
+                Ok(_) => log::info!(
+                    "Saved {} frames of input to {:?}",
+                    recording.frames.len(),
+                    path
+                ),
+                Err(err) => log::error!("Unable to save input recording {:?}: {}", path, err),
+            }
+        }
+    }
+
+    #[inline]
+    pub fn triggered(&self, action: Action, trigger: ActionTrigger) -> bool {
+        self.action_map.triggered(action, trigger, &self.state)
+    }
+
+    #[inline]
+    pub fn value(&self, action: Action) -> f32 {
+        self.action_map.value(action, &self.state)
+    }
+
+    fn process_live_event(&mut self, event: InputEvent) {
+        match &mut self.mode {
+            InputMode::Live => {}
+            InputMode::Record { recorder, .. } => recorder.record(event),
+            InputMode::Replay(_) => return,
+        }
+        self.state.process_event(&event);
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use winit::event::{ElementState, VirtualKeyCode};
+
+    use super::InputManager;
+    use crate::runtime::functions::{
+        event_system::{
+            action::ActionMap,
+            input_state::InputEvent,
+            replay::{InputMode, InputRecorder, InputReplay},
+            update_camera,
+        },
+        scene_system::SceneManager,
+    };
+
+    #[test]
+    fn replay_reproduces_camera_path() {
+        let mut recorder = InputRecorder::new();
+        recorder.record(InputEvent::Key {
+            key: VirtualKeyCode::W,
+            state: ElementState::Pressed,
+        });
+        recorder.end_frame(0.016);
+        recorder.record(InputEvent::Key {
+            key: VirtualKeyCode::D,
+            state: ElementState::Pressed,
+        });
+        recorder.end_frame(0.033);
+        recorder.record(InputEvent::Key {
+            key: VirtualKeyCode::W,
+            state: ElementState::Released,
+        });
+        recorder.end_frame(0.025);
+        let recording = recorder.finish();
+
+        let run = || {
+            let mut input_manager = InputManager::new(
+                ActionMap::default(),
+                None,
+                InputMode::Replay(InputReplay::new(recording.clone(), None)),
+            );
+            let mut scene_manager = SceneManager::new();
+            while let Some(delta_t) = input_manager.begin_frame(1.0) {
+                update_camera(delta_t, &input_manager, &mut scene_manager);
+                input_manager.end_frame(delta_t);
+            }
+            scene_manager.camera().position
+        };
+        let first = run();
+        assert!(first.almost_eq(&run()));
+        assert!(!first.almost_eq(&SceneManager::new().camera().position));
+    }
+}
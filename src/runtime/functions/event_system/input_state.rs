@@ -0,0 +1,440 @@
+use std::collections::{HashMap, HashSet};
+
+use winit::event::{
+    DeviceEvent, ElementState, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode,
+    WindowEvent,
+};
+
+use super::gamepad::{GamepadAxis, GamepadButton, GamepadEvent, GamepadId, DEFAULT_DEAD_ZONE};
+
+/// Input relevant part of the winit events, in a form that can be recorded and replayed.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    Key {
+        key: VirtualKeyCode,
+        state: ElementState,
+    },
+    MouseButton {
+        button: MouseButton,
+        state: ElementState,
+    },
+    Modifiers(ModifiersState),
+    CursorMoved {
+        x: f64,
+        y: f64,
+    },
+    MouseMotion {
+        dx: f64,
+        dy: f64,
+    },
+    FocusLost,
+    Gamepad(GamepadEvent),
+}
+
+impl InputEvent {
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => Some(InputEvent::Key {
+                key: *key,
+                state: *state,
+            }),
+            WindowEvent::MouseInput { state, button, .. } => Some(InputEvent::MouseButton {
+                button: *button,
+                state: *state,
+            }),
+            WindowEvent::ModifiersChanged(modifiers) => Some(InputEvent::Modifiers(*modifiers)),
+            WindowEvent::CursorMoved { position, .. } => Some(InputEvent::CursorMoved {
+                x: position.x,
+                y: position.y,
+            }),
+            WindowEvent::Focused(false) => Some(InputEvent::FocusLost),
+            _ => None,
+        }
+    }
+
+    pub fn from_device_event(event: &DeviceEvent) -> Option<Self> {
+        match event {
+            DeviceEvent::MouseMotion { delta } => Some(InputEvent::MouseMotion {
+                dx: delta.0,
+                dy: delta.1,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct GamepadState {
+    buttons: HashSet<GamepadButton>,
+    // raw values as reported by the backend
+    axes: HashMap<GamepadAxis, f32>,
+}
+
+/// Keyboard, mouse and gamepad state accumulated across the events of one frame.
+///
+/// Held keys/buttons persist until released, while the `pressed`/`released`
+/// sets and the mouse deltas only live until `end_frame` is called.
+/// Buttons and axes of all connected gamepads are merged.
+#[derive(Debug)]
+pub struct InputState {
+    held_keys: HashSet<VirtualKeyCode>,
+    pressed_keys: HashSet<VirtualKeyCode>,
+    released_keys: HashSet<VirtualKeyCode>,
+    held_buttons: HashSet<MouseButton>,
+    pressed_buttons: HashSet<MouseButton>,
+    released_buttons: HashSet<MouseButton>,
+    modifiers: ModifiersState,
+    cursor_position: (f64, f64),
+    mouse_delta: (f64, f64),
+    gamepads: HashMap<GamepadId, GamepadState>,
+    pressed_pad_buttons: HashSet<GamepadButton>,
+    released_pad_buttons: HashSet<GamepadButton>,
+    previous_axes: HashMap<GamepadAxis, f32>,
+    dead_zone: f32,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        InputState::new()
+    }
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        InputState {
+            held_keys: HashSet::new(),
+            pressed_keys: HashSet::new(),
+            released_keys: HashSet::new(),
+            held_buttons: HashSet::new(),
+            pressed_buttons: HashSet::new(),
+            released_buttons: HashSet::new(),
+            modifiers: ModifiersState::empty(),
+            cursor_position: (0.0, 0.0),
+            mouse_delta: (0.0, 0.0),
+            gamepads: HashMap::new(),
+            pressed_pad_buttons: HashSet::new(),
+            released_pad_buttons: HashSet::new(),
+            previous_axes: HashMap::new(),
+            dead_zone: DEFAULT_DEAD_ZONE,
+        }
+    }
+
+    pub fn process_event(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::Key { key, state } => self.process_key(key, state),
+            InputEvent::MouseButton { button, state } => self.process_button(button, state),
+            InputEvent::Modifiers(modifiers) => self.modifiers = modifiers,
+            InputEvent::CursorMoved { x, y } => self.cursor_position = (x, y),
+            InputEvent::MouseMotion { dx, dy } => {
+                self.mouse_delta.0 += dx;
+                self.mouse_delta.1 += dy;
+            }
+            InputEvent::FocusLost => self.release_all(),
+            InputEvent::Gamepad(ref event) => self.process_gamepad_event(event),
+        }
+    }
+
+    pub fn process_key(&mut self, key: VirtualKeyCode, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                // OS key-repeat sends `Pressed` again while held, only the first one counts
+                if self.held_keys.insert(key) {
+                    self.pressed_keys.insert(key);
+                }
+            }
+            ElementState::Released => {
+                if self.held_keys.remove(&key) {
+                    self.released_keys.insert(key);
+                }
+            }
+        }
+    }
+
+    pub fn process_button(&mut self, button: MouseButton, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                if self.held_buttons.insert(button) {
+                    self.pressed_buttons.insert(button);
+                }
+            }
+            ElementState::Released => {
+                if self.held_buttons.remove(&button) {
+                    self.released_buttons.insert(button);
+                }
+            }
+        }
+    }
+
+    pub fn process_gamepad_event(&mut self, event: &GamepadEvent) {
+        match *event {
+            GamepadEvent::Connected(id) => {
+                log::info!("Gamepad {} connected", id);
+                self.gamepads.entry(id).or_default();
+            }
+            GamepadEvent::Disconnected(id) => {
+                log::info!("Gamepad {} disconnected", id);
+                if let Some(pad) = self.gamepads.remove(&id) {
+                    for button in pad.buttons {
+                        if !self.gamepad_button_held(button) {
+                            self.released_pad_buttons.insert(button);
+                        }
+                    }
+                }
+            }
+            GamepadEvent::Button {
+                id,
+                button,
+                pressed,
+            } => {
+                let was_held = self.gamepad_button_held(button);
+                let pad = self.gamepads.entry(id).or_default();
+                if pressed {
+                    pad.buttons.insert(button);
+                } else {
+                    pad.buttons.remove(&button);
+                }
+                match (was_held, self.gamepad_button_held(button)) {
+                    (false, true) => {
+                        self.pressed_pad_buttons.insert(button);
+                    }
+                    (true, false) => {
+                        self.released_pad_buttons.insert(button);
+                    }
+                    _ => {}
+                }
+            }
+            GamepadEvent::Axis { id, axis, value } => {
+                self.gamepads
+                    .entry(id)
+                    .or_default()
+                    .axes
+                    .insert(axis, value);
+            }
+        }
+    }
+
+    /// Clears the per-frame state, call once after the frame has consumed the input.
+    pub fn end_frame(&mut self) {
+        self.pressed_keys.clear();
+        self.released_keys.clear();
+        self.pressed_buttons.clear();
+        self.released_buttons.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.pressed_pad_buttons.clear();
+        self.released_pad_buttons.clear();
+        self.previous_axes = ALL_AXES
+            .iter()
+            .map(|axis| (*axis, self.gamepad_axis(*axis)))
+            .collect();
+    }
+
+    #[inline]
+    pub fn key_held(&self, key: VirtualKeyCode) -> bool {
+        self.held_keys.contains(&key)
+    }
+
+    #[inline]
+    pub fn key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    #[inline]
+    pub fn key_released(&self, key: VirtualKeyCode) -> bool {
+        self.released_keys.contains(&key)
+    }
+
+    #[inline]
+    pub fn button_held(&self, button: MouseButton) -> bool {
+        self.held_buttons.contains(&button)
+    }
+
+    #[inline]
+    pub fn button_pressed(&self, button: MouseButton) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+
+    #[inline]
+    pub fn button_released(&self, button: MouseButton) -> bool {
+        self.released_buttons.contains(&button)
+    }
+
+    #[inline]
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    #[inline]
+    pub fn cursor_position(&self) -> (f64, f64) {
+        self.cursor_position
+    }
+
+    #[allow(dead_code)]
+    #[inline]
+    pub fn mouse_delta(&self) -> (f64, f64) {
+        self.mouse_delta
+    }
+
+    #[inline]
+    pub fn gamepad_button_held(&self, button: GamepadButton) -> bool {
+        self.gamepads
+            .values()
+            .any(|pad| pad.buttons.contains(&button))
+    }
+
+    #[inline]
+    pub fn gamepad_button_pressed(&self, button: GamepadButton) -> bool {
+        self.pressed_pad_buttons.contains(&button)
+    }
+
+    #[inline]
+    pub fn gamepad_button_released(&self, button: GamepadButton) -> bool {
+        self.released_pad_buttons.contains(&button)
+    }
+
+    /// Dead-zone filtered axis value, the strongest deflection over all gamepads.
+    pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepads
+            .values()
+            .map(|pad| self.filter_dead_zone(pad, axis))
+            .fold(0.0, |strongest: f32, value| {
+                if value.abs() > strongest.abs() {
+                    value
+                } else {
+                    strongest
+                }
+            })
+    }
+
+    /// `gamepad_axis` as it was in the previous frame.
+    #[inline]
+    pub fn previous_gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.previous_axes.get(&axis).copied().unwrap_or(0.0)
+    }
+
+    /// Sticks use a radial dead zone so diagonals are not snapped to the axes,
+    /// the remaining range is rescaled to start at zero.
+    fn filter_dead_zone(&self, pad: &GamepadState, axis: GamepadAxis) -> f32 {
+        let value = pad.axes.get(&axis).copied().unwrap_or(0.0);
+        let magnitude = match axis.stick_pair() {
+            Some(pair) => {
+                let other = pad.axes.get(&pair).copied().unwrap_or(0.0);
+                (value * value + other * other).sqrt()
+            }
+            None => value.abs(),
+        };
+        if magnitude <= self.dead_zone {
+            return 0.0;
+        }
+        let rescaled = ((magnitude - self.dead_zone) / (1.0 - self.dead_zone)).min(1.0);
+        value / magnitude * rescaled
+    }
+
+    fn release_all(&mut self) {
+        self.released_keys.extend(self.held_keys.drain());
+        self.released_buttons.extend(self.held_buttons.drain());
+        self.modifiers = ModifiersState::empty();
+    }
+}
+
+const ALL_AXES: [GamepadAxis; 6] = [
+    GamepadAxis::LeftStickX,
+    GamepadAxis::LeftStickY,
+    GamepadAxis::RightStickX,
+    GamepadAxis::RightStickY,
+    GamepadAxis::LeftTrigger,
+    GamepadAxis::RightTrigger,
+];
+
+#[allow(unused_imports)]
+mod test {
+    use winit::event::{DeviceEvent, ElementState, MouseButton, VirtualKeyCode};
+
+    use super::{InputEvent, InputState};
+    use crate::runtime::functions::event_system::gamepad::{
+        GamepadAxis, GamepadBackend, GamepadButton, VirtualGamepad,
+    };
+
+    #[test]
+    fn key_state_across_frames() {
+        let mut input = InputState::new();
+        input.process_key(VirtualKeyCode::W, ElementState::Pressed);
+        input.process_key(VirtualKeyCode::D, ElementState::Pressed);
+        assert!(input.key_pressed(VirtualKeyCode::W));
+        assert!(input.key_held(VirtualKeyCode::W) && input.key_held(VirtualKeyCode::D));
+
+        input.end_frame();
+        // key-repeat must not retrigger a press
+        input.process_key(VirtualKeyCode::W, ElementState::Pressed);
+        assert!(!input.key_pressed(VirtualKeyCode::W));
+        assert!(input.key_held(VirtualKeyCode::W));
+
+        input.process_key(VirtualKeyCode::W, ElementState::Released);
+        assert!(input.key_released(VirtualKeyCode::W));
+        assert!(!input.key_held(VirtualKeyCode::W));
+        assert!(input.key_held(VirtualKeyCode::D));
+    }
+
+    #[test]
+    fn mouse_state_across_frames() {
+        let mut input = InputState::new();
+        input.process_button(MouseButton::Left, ElementState::Pressed);
+        for delta in [(2.0, -1.0), (1.0, -1.0)] {
+            let motion = InputEvent::from_device_event(&DeviceEvent::MouseMotion { delta });
+            input.process_event(&motion.unwrap());
+        }
+        assert!(input.button_pressed(MouseButton::Left));
+        assert_eq!(input.mouse_delta(), (3.0, -2.0));
+
+        input.end_frame();
+        assert!(input.button_held(MouseButton::Left));
+        assert!(!input.button_pressed(MouseButton::Left));
+        assert_eq!(input.mouse_delta(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn gamepad_dead_zone_and_hot_plug() {
+        let mut pad = VirtualGamepad::new();
+        let mut input = InputState::new();
+        input.dead_zone = 0.2;
+        pad.connect(0);
+        pad.set_axis(0, GamepadAxis::LeftStickX, 0.1);
+        pad.set_axis(0, GamepadAxis::LeftStickY, 0.1);
+        pad.set_axis(0, GamepadAxis::RightTrigger, 0.6);
+        pad.set_button(0, GamepadButton::South, true);
+        while let Some(event) = pad.poll_event() {
+            input.process_gamepad_event(&event);
+        }
+        assert_eq!(input.gamepads.len(), 1);
+        assert_eq!(input.gamepad_axis(GamepadAxis::LeftStickX), 0.0);
+        assert!((input.gamepad_axis(GamepadAxis::RightTrigger) - 0.5).abs() < 1e-6);
+        assert!(input.gamepad_button_pressed(GamepadButton::South));
+
+        input.end_frame();
+        // a diagonal outside the radial dead zone keeps both components
+        pad.set_axis(0, GamepadAxis::LeftStickX, 0.6);
+        pad.set_axis(0, GamepadAxis::LeftStickY, 0.8);
+        while let Some(event) = pad.poll_event() {
+            input.process_gamepad_event(&event);
+        }
+        assert!((input.gamepad_axis(GamepadAxis::LeftStickX) - 0.6).abs() < 1e-6);
+        assert!((input.gamepad_axis(GamepadAxis::LeftStickY) - 0.8).abs() < 1e-6);
+        assert!(!input.gamepad_button_pressed(GamepadButton::South));
+        assert!(input.gamepad_button_held(GamepadButton::South));
+
+        pad.disconnect(0);
+        while let Some(event) = pad.poll_event() {
+            input.process_gamepad_event(&event);
+        }
+        assert_eq!(input.gamepads.len(), 0);
+        assert!(input.gamepad_button_released(GamepadButton::South));
+        assert_eq!(input.gamepad_axis(GamepadAxis::LeftStickY), 0.0);
+    }
+}
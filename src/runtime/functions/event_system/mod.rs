@@ -1,146 +1,323 @@
 use winit::{
-    event::{Event, VirtualKeyCode, WindowEvent},
+    event::{Event, WindowEvent},
     event_loop::ControlFlow,
 };
 
+use self::{
+    action::{Action, ActionTrigger},
+    input_manager::InputManager,
+};
+
+use crate::runtime::core::ecs::schedule::Stage;
+
 use super::{
+    editor_system::{gizmo::GizmoMode, EditorManager},
+    gui_system::{GuiManager, PanelContext},
     render_system::{fps_manager::FpsManager, RenderManager},
-    scene_system::{camera::MoveDirection, SceneManager},
+    scene_system::{camera::MoveDirection, OpenMode, SceneManager, UPLOAD_BUDGET},
     window_system::WindowManager,
 };
 
+pub mod action;
+pub mod gamepad;
+pub mod input_manager;
+pub mod input_state;
+pub mod replay;
+
 #[inline]
+#[allow(clippy::too_many_arguments)]
 pub fn handle_event(
     event: Event<'_, ()>,
     control_flow: &mut ControlFlow,
-    fps_manager: &mut FpsManager,
     _window_manager: &WindowManager,
     scene_manager: &mut SceneManager,
-    render_manager: &RenderManager,
+    render_manager: &mut RenderManager,
+    input_manager: &mut InputManager,
+    gui_manager: &mut GuiManager,
+    editor_manager: &mut EditorManager,
 ) {
     match event {
         Event::NewEvents(_) => {
+            let fps_manager = scene_manager.world.resource_mut::<FpsManager>();
             fps_manager.tick();
             if fps_manager.elapsed() >= 1.0 {
                 fps_manager.update(std::time::Instant::now());
-                println!("FPS: {}", fps_manager.get_fps());
+                let stats = fps_manager.stats();
+                log::info!("FPS: {} | {}", fps_manager.get_fps(), stats);
+                log::debug!("Frame time histogram: {:?}", stats.histogram);
+                if let Some(timings) = render_manager.profiler.latest() {
+                    log::debug!("Frame timings: {:?}", timings);
+                }
             }
         }
-        Event::WindowEvent { event, .. } => match event {
-            // WindowEvent::Resized(_) => todo!(),
-            // WindowEvent::Moved(_) => todo!(),
-            WindowEvent::CloseRequested => control_flow.set_exit(),
-            // WindowEvent::Destroyed => todo!(),
-            // WindowEvent::DroppedFile(_) => todo!(),
-            // WindowEvent::HoveredFile(_) => todo!(),
-            // WindowEvent::HoveredFileCancelled => todo!(),
-            // WindowEvent::ReceivedCharacter(_) => todo!(),
-            // WindowEvent::Focused(_) => todo!(),
-            WindowEvent::KeyboardInput {
-                input:
-                    winit::event::KeyboardInput {
-                        state: winit::event::ElementState::Pressed,
-                        virtual_keycode: Some(keycode),
-                        ..
-                    },
-                ..
-            } => handle_keycode(
-                fps_manager.get_delta_t(),
-                keycode,
-                control_flow,
-                render_manager,
-                scene_manager,
-            ),
-            // WindowEvent::ModifiersChanged(_) => todo!(),
-            // WindowEvent::Ime(_) => todo!(),
-            // WindowEvent::CursorMoved {
-            //     device_id,
-            //     position,
-            //     modifiers,
-            // } => todo!(),
-            // WindowEvent::CursorEntered { device_id } => todo!(),
-            // WindowEvent::CursorLeft { device_id } => todo!(),
-            // WindowEvent::MouseWheel {
-            //     device_id,
-            //     delta,
-            //     phase,
-            //     modifiers,
-            // } => todo!(),
-            // WindowEvent::MouseInput {
-            //     device_id,
-            //     state,
-            //     button,
-            //     modifiers,
-            // } => todo!(),
-            // WindowEvent::TouchpadMagnify {
-            //     device_id,
-            //     delta,
-            //     phase,
-            // } => todo!(),
-            // WindowEvent::SmartMagnify { device_id } => todo!(),
-            // WindowEvent::TouchpadRotate {
-            //     device_id,
-            //     delta,
-            //     phase,
-            // } => todo!(),
-            // WindowEvent::TouchpadPressure {
-            //     device_id,
-            //     pressure,
-            //     stage,
-            // } => todo!(),
-            // WindowEvent::AxisMotion {
-            //     device_id,
-            //     axis,
-            //     value,
-            // } => todo!(),
-            // WindowEvent::Touch(_) => todo!(),
-            // WindowEvent::ScaleFactorChanged {
-            //     scale_factor,
-            //     new_inner_size,
-            // } => todo!(),
-            // WindowEvent::ThemeChanged(_) => todo!(),
-            // WindowEvent::Occluded(_) => todo!(),
-            _ => {}
-        },
-        // Event::DeviceEvent { device_id, event } => todo!(),
+        Event::WindowEvent { event, .. } => {
+            if !gui_manager.process_window_event(&event) {
+                input_manager.process_window_event(&event);
+            }
+            match event {
+                // WindowEvent::Resized(_) => todo!(),
+                // WindowEvent::Moved(_) => todo!(),
+                WindowEvent::CloseRequested => control_flow.set_exit(),
+                // WindowEvent::Destroyed => todo!(),
+                // shift replaces the scene, the model is added to it otherwise
+                WindowEvent::DroppedFile(path) => {
+                    let mode = if input_manager.state.modifiers().shift() {
+                        OpenMode::Replace
+                    } else {
+                        OpenMode::Add
+                    };
+                    scene_manager.open_model(path, mode);
+                }
+                // WindowEvent::HoveredFile(_) => todo!(),
+                // WindowEvent::HoveredFileCancelled => todo!(),
+                // WindowEvent::ReceivedCharacter(_) => todo!(),
+                // WindowEvent::Focused(_) => todo!(),
+                // WindowEvent::KeyboardInput is tracked by the input state
+                // WindowEvent::ModifiersChanged(_) => todo!(),
+                // WindowEvent::Ime(_) => todo!(),
+                // WindowEvent::CursorMoved {
+                //     device_id,
+                //     position,
+                //     modifiers,
+                // } => todo!(),
+                // WindowEvent::CursorEntered { device_id } => todo!(),
+                // WindowEvent::CursorLeft { device_id } => todo!(),
+                // WindowEvent::MouseWheel {
+                //     device_id,
+                //     delta,
+                //     phase,
+                //     modifiers,
+                // } => todo!(),
+                // WindowEvent::MouseInput {
+                //     device_id,
+                //     state,
+                //     button,
+                //     modifiers,
+                // } => todo!(),
+                // WindowEvent::TouchpadMagnify {
+                //     device_id,
+                //     delta,
+                //     phase,
+                // } => todo!(),
+                // WindowEvent::SmartMagnify { device_id } => todo!(),
+                // WindowEvent::TouchpadRotate {
+                //     device_id,
+                //     delta,
+                //     phase,
+                // } => todo!(),
+                // WindowEvent::TouchpadPressure {
+                //     device_id,
+                //     pressure,
+                //     stage,
+                // } => todo!(),
+                // WindowEvent::AxisMotion {
+                //     device_id,
+                //     axis,
+                //     value,
+                // } => todo!(),
+                // WindowEvent::Touch(_) => todo!(),
+                // WindowEvent::ScaleFactorChanged {
+                //     scale_factor,
+                //     new_inner_size,
+                // } => todo!(),
+                // WindowEvent::ThemeChanged(_) => todo!(),
+                // WindowEvent::Occluded(_) => todo!(),
+                _ => {}
+            }
+        }
+        Event::DeviceEvent { event, .. } => input_manager.process_device_event(&event),
         // Event::UserEvent(_) => todo!(),
         // Event::Suspended => todo!(),
         // Event::Resumed => todo!(),
         Event::MainEventsCleared => {
-            render_manager.tick(scene_manager.camera.get_mvp());
+            input_manager.poll_gamepads();
+            let frame_delta_t = scene_manager.world.resource::<FpsManager>().get_delta_t();
+            let Some(delta_t) = input_manager.begin_frame(frame_delta_t) else {
+                log::info!("Input replay finished.");
+                control_flow.set_exit();
+                return;
+            };
+            let update_start = std::time::Instant::now();
+            handle_actions(
+                input_manager,
+                control_flow,
+                scene_manager,
+                render_manager,
+                gui_manager,
+                editor_manager,
+            );
+            let reloaded = scene_manager.reload_changed();
+            for (path, result) in reloaded.into_iter().chain(render_manager.reload_shaders()) {
+                match result {
+                    Ok(()) => {
+                        gui_manager.notify(log::Level::Info, format!("Reloaded {}", path.display()))
+                    }
+                    Err(err) => gui_manager.notify(
+                        log::Level::Error,
+                        format!("Unable to reload {}: {}", path.display(), err),
+                    ),
+                }
+            }
+            for (path, result) in scene_manager.finish_loading() {
+                match result {
+                    Ok(()) => {
+                        gui_manager.notify(log::Level::Info, format!("Loaded {}", path.display()))
+                    }
+                    Err(err) => gui_manager.notify(
+                        log::Level::Error,
+                        format!("Unable to load {}: {}", path.display(), err),
+                    ),
+                }
+            }
+            let gpu_context = &render_manager.gpu_context;
+            scene_manager.upload_assets(&gpu_context.device, &gpu_context.queue, UPLOAD_BUDGET);
+            scene_manager.run_stage(Stage::Input);
+            scene_manager.simulate(delta_t, |step, scene_manager| {
+                update_camera(step, input_manager, scene_manager)
+            });
+            scene_manager.run_stage(Stage::Update);
+            render_manager
+                .profiler
+                .record_update(update_start.elapsed());
+            let camera = scene_manager.render_camera();
+            render_manager.update_debug_overlay(
+                scene_manager.world.resource(),
+                &camera,
+                &scene_manager.assets,
+            );
+            let screen_size = [
+                render_manager.gpu_context.surface_config.width as f32,
+                render_manager.gpu_context.surface_config.height as f32,
+            ];
+            gui_manager.run(
+                screen_size,
+                &mut PanelContext {
+                    scene_manager,
+                    render_manager,
+                    editor_manager,
+                },
+            );
+            // picks finish a frame or more after the click
+            if let Some(pick) = render_manager.picker.take_result() {
+                editor_manager.select_picked(pick, scene_manager);
+            }
+            editor_manager.update(
+                &input_manager.state,
+                scene_manager,
+                &camera,
+                screen_size,
+                gui_manager.visible(),
+            );
+            if let Some(pixel) = editor_manager.take_pick_request() {
+                render_manager.picker.request(pixel);
+            }
+            // a drag ends with the pointer release, wherever it started
+            if !gui_manager.pointer_down() {
+                editor_manager.history.seal();
+            }
+            // the scene as edited this frame is what gets drawn
+            scene_manager.run_stage(Stage::TransformPropagation);
+            scene_manager.run_stage(Stage::RenderExtract);
+            // assets whose last node went this frame are not drawn anymore
+            scene_manager.assets.free_unused();
+            scene_manager
+                .graph
+                .extract(&scene_manager.assets, &mut render_manager.draw_list);
+            render_manager.clear_color = scene_manager.environment.clear_color;
+            render_manager.tick(
+                camera.get_mvp(),
+                &scene_manager.assets,
+                &[editor_manager.overlay(), gui_manager.output()],
+            );
+            input_manager.end_frame(delta_t);
+            scene_manager
+                .world
+                .resource_mut::<FpsManager>()
+                .wait_for_next_frame();
             profiling::finish_frame!();
         }
         // Event::RedrawRequested(_) => todo!(),
         // Event::RedrawEventsCleared => todo!(),
-        // Event::LoopDestroyed => todo!(),
+        Event::LoopDestroyed => {
+            input_manager.finish();
+            render_manager.profiler.finish();
+        }
         _ => {}
     }
 }
 
 #[inline]
-fn handle_keycode(
-    delta_t: f32,
-    key: VirtualKeyCode,
+fn handle_actions(
+    input_manager: &InputManager,
     control_flow: &mut ControlFlow,
-    render_manager: &RenderManager,
     scene_manager: &mut SceneManager,
+    render_manager: &mut RenderManager,
+    gui_manager: &mut GuiManager,
+    editor_manager: &mut EditorManager,
 ) {
-    match key {
-        VirtualKeyCode::Escape => control_flow.set_exit(),
-        VirtualKeyCode::R => println!("Report: {:?}", render_manager.report()),
-        // movement
-        VirtualKeyCode::W => scene_manager
-            .camera
-            .camera_move(MoveDirection::Forward, delta_t),
-        VirtualKeyCode::A => scene_manager
-            .camera
-            .camera_move(MoveDirection::Left, delta_t),
-        VirtualKeyCode::S => scene_manager
-            .camera
-            .camera_move(MoveDirection::Backward, delta_t),
-        VirtualKeyCode::D => scene_manager
-            .camera
-            .camera_move(MoveDirection::Right, delta_t),
-        _ => {}
+    let pressed = |action| input_manager.triggered(action, ActionTrigger::Pressed);
+
+    if pressed(Action::Exit) {
+        control_flow.set_exit();
+    }
+    if pressed(Action::Report) {
+        render_manager.report();
+    }
+    if pressed(Action::ToggleWireframe) {
+        render_manager.toggle_wireframe();
+    }
+    if pressed(Action::Screenshot) {
+        render_manager.request_screenshot();
+    }
+    if pressed(Action::CyclePresentMode) {
+        render_manager.cycle_present_mode();
+    }
+    if pressed(Action::ToggleDebugOverlay) {
+        render_manager.toggle_debug_overlay();
+    }
+    if pressed(Action::ToggleGui) {
+        gui_manager.toggle();
+    }
+    // the gizmo is only shown with the GUI, its keys do nothing while it is hidden
+    if gui_manager.visible() {
+        if pressed(Action::GizmoTranslate) {
+            editor_manager.set_gizmo_mode(GizmoMode::Translate);
+        }
+        if pressed(Action::GizmoRotate) {
+            editor_manager.set_gizmo_mode(GizmoMode::Rotate);
+        }
+        if pressed(Action::GizmoScale) {
+            editor_manager.set_gizmo_mode(GizmoMode::Scale);
+        }
+        if pressed(Action::ToggleGizmoSpace) {
+            editor_manager.toggle_gizmo_space();
+        }
+    }
+    if pressed(Action::Undo) {
+        editor_manager.undo(scene_manager);
+    }
+    if pressed(Action::Redo) {
+        editor_manager.redo(scene_manager);
+    }
+}
+
+/// Movement runs every frame from the held state, so speed does not depend
+/// on the OS key-repeat rate and opposite/diagonal inputs combine,
+/// analog sticks scale the speed by their deflection.
+#[inline]
+pub fn update_camera(delta_t: f32, input_manager: &InputManager, scene_manager: &mut SceneManager) {
+    const MOVEMENT: [(Action, MoveDirection); 4] = [
+        (Action::MoveForward, MoveDirection::Forward),
+        (Action::MoveLeft, MoveDirection::Left),
+        (Action::MoveBackward, MoveDirection::Backward),
+        (Action::MoveRight, MoveDirection::Right),
+    ];
+    for (action, direction) in MOVEMENT {
+        let amount = input_manager.value(action);
+        if amount > 0.0 {
+            scene_manager
+                .camera_mut()
+                .camera_move(direction, delta_t * amount);
+        }
     }
 }
@@ -0,0 +1,199 @@
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+};
+
+use super::input_state::InputEvent;
+
+/// Record all input of the session to this file.
+pub const RECORD_ENV: &str = "RUCCOLO_RECORD_INPUT";
+/// Replay a recorded session from this file instead of live input.
+pub const REPLAY_ENV: &str = "RUCCOLO_REPLAY_INPUT";
+/// Replay with this delta time (in seconds) instead of the recorded ones.
+pub const FIXED_DELTA_ENV: &str = "RUCCOLO_FIXED_DELTA";
+/// Run the replay without window and GPU.
+pub const HEADLESS_ENV: &str = "RUCCOLO_HEADLESS";
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TimedInputEvent {
+    /// Seconds since the recording started.
+    pub time: f64,
+    pub event: InputEvent,
+}
+
+/// Input that arrived during one frame, and the delta time the frame ran with.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordedFrame {
+    pub delta_t: f32,
+    pub events: Vec<TimedInputEvent>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct InputRecording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl InputRecording {
+    pub fn load<P: AsRef<Path> + Debug>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = std::fs::File::open(path.as_ref())?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+pub struct InputRecorder {
+    recording: InputRecording,
+    pending: Vec<TimedInputEvent>,
+    start: std::time::Instant,
+}
+
+impl InputRecorder {
+    pub fn new() -> Self {
+        InputRecorder {
+            recording: InputRecording::default(),
+            pending: Vec::new(),
+            start: std::time::Instant::now(),
+        }
+    }
+
+    pub fn record(&mut self, event: InputEvent) {
+        self.pending.push(TimedInputEvent {
+            time: self.start.elapsed().as_secs_f64(),
+            event,
+        });
+    }
+
+    /// Closes the current frame, the pending events are attached to it.
+    pub fn end_frame(&mut self, delta_t: f32) {
+        self.recording.frames.push(RecordedFrame {
+            delta_t,
+            events: std::mem::take(&mut self.pending),
+        });
+    }
+
+    pub fn finish(self) -> InputRecording {
+        self.recording
+    }
+}
+
+pub struct InputReplay {
+    recording: InputRecording,
+    next_frame: usize,
+    fixed_delta: Option<f32>,
+}
+
+impl InputReplay {
+    pub fn new(recording: InputRecording, fixed_delta: Option<f32>) -> Self {
+        InputReplay {
+            recording,
+            next_frame: 0,
+            fixed_delta,
+        }
+    }
+
+    /// Returns the delta time and the events of the next frame.
+    pub fn next_frame(&mut self) -> Option<(f32, &[TimedInputEvent])> {
+        let frame = self.recording.frames.get(self.next_frame)?;
+        self.next_frame += 1;
+        Some((self.fixed_delta.unwrap_or(frame.delta_t), &frame.events))
+    }
+}
+
+/// Where the input of a session comes from.
+pub enum InputMode {
+    Live,
+    /// Live input, saved to `path` when the session ends.
+    Record {
+        recorder: InputRecorder,
+        path: PathBuf,
+    },
+    Replay(InputReplay),
+}
+
+impl InputMode {
+    /// Picks the mode from the `RUCCOLO_RECORD_INPUT`/`RUCCOLO_REPLAY_INPUT` variables.
+    pub fn from_env() -> Self {
+        if let Some(path) = std::env::var_os(REPLAY_ENV) {
+            let fixed_delta = std::env::var(FIXED_DELTA_ENV)
+                .ok()
+                .and_then(|delta| delta.parse().ok());
+            match InputRecording::load(&path) {
+                Ok(recording) => {
+                    log::info!(
+                        "Replaying {} frames of input from {:?}",
+                        recording.frames.len(),
+                        path
+                    );
+                    return InputMode::Replay(InputReplay::new(recording, fixed_delta));
+                }
+                Err(err) => log::error!("Unable to load input recording {:?}: {}", path, err),
+            }
+        }
+        if let Some(path) = std::env::var_os(RECORD_ENV) {
+            log::info!("Recording input to {:?}", path);
+            return InputMode::Record {
+                recorder: InputRecorder::new(),
+                path: path.into(),
+            };
+        }
+        InputMode::Live
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use winit::event::{ElementState, VirtualKeyCode};
+
+    use super::{InputRecorder, InputRecording, InputReplay};
+    use crate::runtime::functions::event_system::input_state::InputEvent;
+
+    #[test]
+    fn record_and_replay() {
+        let mut recorder = InputRecorder::new();
+        recorder.record(InputEvent::Key {
+            key: VirtualKeyCode::W,
+            state: ElementState::Pressed,
+        });
+        recorder.end_frame(0.016);
+        recorder.end_frame(0.020);
+        recorder.record(InputEvent::Key {
+            key: VirtualKeyCode::W,
+            state: ElementState::Released,
+        });
+        recorder.end_frame(0.018);
+        let recording = recorder.finish();
+
+        let json = serde_json::to_string(&recording).unwrap();
+        let loaded: InputRecording = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.frames.len(), recording.frames.len());
+        for (loaded, recorded) in loaded.frames.iter().zip(&recording.frames) {
+            assert_eq!(loaded.delta_t, recorded.delta_t);
+            let events = |frame: &super::RecordedFrame| {
+                frame
+                    .events
+                    .iter()
+                    .map(|timed| timed.event)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(events(loaded), events(recorded));
+        }
+
+        let mut replay = InputReplay::new(loaded.clone(), None);
+        let (delta_t, events) = replay.next_frame().unwrap();
+        assert_eq!(delta_t, 0.016);
+        assert_eq!(events.len(), 1);
+        let (delta_t, events) = replay.next_frame().unwrap();
+        assert_eq!(delta_t, 0.020);
+        assert!(events.is_empty());
+        replay.next_frame().unwrap();
+        assert!(replay.next_frame().is_none());
+
+        let mut replay = InputReplay::new(loaded, Some(0.01));
+        assert_eq!(replay.next_frame().unwrap().0, 0.01);
+    }
+}
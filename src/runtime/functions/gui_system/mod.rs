@@ -0,0 +1,157 @@
+use winit::event::{ElementState, MouseButton, WindowEvent};
+
+use self::ui::{GuiInput, Ui};
+
+use super::{
+    editor_system::EditorManager,
+    render_system::{overlay::OverlayBatch, RenderManager},
+    scene_system::SceneManager,
+};
+
+pub mod ui;
+
+/// How long a notification stays on screen.
+const NOTIFICATION_TIME: std::time::Duration = std::time::Duration::from_secs(6);
+// older ones are dropped early
+const MAX_NOTIFICATIONS: usize = 5;
+
+struct Notification {
+    level: log::Level,
+    text: String,
+    shown: std::time::Instant,
+}
+
+/// What panels get to look at and edit while they are drawn.
+pub struct PanelContext<'a> {
+    pub scene_manager: &'a mut SceneManager,
+    pub render_manager: &'a mut RenderManager,
+    pub editor_manager: &'a mut EditorManager,
+}
+
+/// A window of tooling, registered once with `GuiManager::register_panel`
+/// and drawn every frame the GUI is visible.
+pub trait Panel {
+    fn title(&self) -> &str;
+    fn ui(&mut self, ui: &mut Ui, context: &mut PanelContext);
+}
+
+pub struct GuiManager {
+    ui: Ui,
+    panels: Vec<Box<dyn Panel>>,
+    visible: bool,
+    // accumulated from the window events until the next frame
+    input: GuiInput,
+    // shown whether the panels are visible or not
+    notifications: Vec<Notification>,
+}
+
+impl GuiManager {
+    pub fn new() -> Self {
+        GuiManager {
+            ui: Ui::new(),
+            panels: Vec::new(),
+            visible: false,
+            input: GuiInput::default(),
+            notifications: Vec::new(),
+        }
+    }
+
+    /// Logs `text` and shows it on screen for a while, for what the user has to know about.
+    pub fn notify(&mut self, level: log::Level, text: String) {
+        log::log!(level, "{}", text);
+        if self.notifications.len() == MAX_NOTIFICATIONS {
+            self.notifications.remove(0);
+        }
+        self.notifications.push(Notification {
+            level,
+            text,
+            shown: std::time::Instant::now(),
+        });
+    }
+
+    pub fn register_panel(&mut self, panel: Box<dyn Panel>) {
+        self.panels.push(panel);
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    #[inline]
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    /// The left button is down, seen from all window events whether captured or not.
+    #[inline]
+    pub fn pointer_down(&self) -> bool {
+        self.input.pointer_down
+    }
+
+    /// Returns whether the GUI consumed the event, the game should not see it then.
+    pub fn process_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.input.cursor = [position.x as f32, position.y as f32];
+                false
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                match state {
+                    ElementState::Pressed => {
+                        let captured = self.captures_pointer();
+                        self.input.pointer_down = true;
+                        self.input.pointer_pressed = true;
+                        captured
+                    }
+                    ElementState::Released => {
+                        // a drag started outside the GUI has to see its release over a panel
+                        let captured = self.visible && self.ui.is_active();
+                        self.input.pointer_down = false;
+                        self.input.pointer_released = true;
+                        captured
+                    }
+                }
+            }
+            WindowEvent::MouseInput { .. } | WindowEvent::MouseWheel { .. } => {
+                self.captures_pointer()
+            }
+            _ => false,
+        }
+    }
+
+    /// The pointer is over a panel or dragging one of its widgets.
+    pub fn captures_pointer(&self) -> bool {
+        self.visible && (self.ui.is_active() || self.ui.is_over_window(self.input.cursor))
+    }
+
+    /// Draws the registered panels into this frame's output.
+    pub fn run(&mut self, screen_size: [f32; 2], context: &mut PanelContext) {
+        self.ui.begin_frame(self.input, screen_size);
+        self.input.pointer_pressed = false;
+        self.input.pointer_released = false;
+        if self.visible {
+            for panel in self.panels.iter_mut() {
+                let title = panel.title().to_owned();
+                self.ui.window(&title, |ui| panel.ui(ui, context));
+            }
+        }
+        self.notifications
+            .retain(|notification| notification.shown.elapsed() < NOTIFICATION_TIME);
+        self.ui.notifications(
+            self.notifications
+                .iter()
+                .map(|notification| (notification.level, notification.text.as_str())),
+        );
+        self.ui.end_frame();
+    }
+
+    /// Quads to draw on top of the frame, see `RenderManager::tick`.
+    #[inline]
+    pub fn output(&self) -> &OverlayBatch {
+        self.ui.batch()
+    }
+}
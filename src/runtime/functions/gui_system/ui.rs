@@ -0,0 +1,652 @@
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    ops::RangeInclusive,
+};
+
+use crate::runtime::functions::render_system::{
+    font::{ADVANCE, GLYPH_HEIGHT, LINE_HEIGHT},
+    overlay::{Color, OverlayBatch},
+};
+
+pub type WidgetId = u64;
+
+const TEXT_SCALE: f32 = 2.0;
+const ROW_HEIGHT: f32 = LINE_HEIGHT as f32 * TEXT_SCALE + 4.0;
+const SPACING: f32 = 4.0;
+const PADDING: f32 = 6.0;
+const WINDOW_WIDTH: f32 = 320.0;
+const WINDOW_MARGIN: f32 = 8.0;
+const TREE_INDENT: f32 = 12.0;
+// share of the row width taken by the caption of value fields
+const CAPTION_WIDTH: f32 = 0.35;
+
+const WINDOW_BACKGROUND: Color = [0.08, 0.08, 0.1, 0.85];
+const TITLE_BACKGROUND: Color = [0.2, 0.3, 0.5, 0.95];
+const WIDGET: Color = [0.25, 0.25, 0.3, 1.0];
+const WIDGET_HOVERED: Color = [0.35, 0.35, 0.45, 1.0];
+const WIDGET_ACTIVE: Color = [0.45, 0.5, 0.7, 1.0];
+const ACCENT: Color = [0.55, 0.75, 1.0, 1.0];
+const TEXT: Color = [1.0, 1.0, 1.0, 1.0];
+const TEXT_DIM: Color = [0.7, 0.7, 0.7, 1.0];
+const TEXT_WARNING: Color = [1.0, 0.8, 0.3, 1.0];
+const TEXT_ERROR: Color = [1.0, 0.45, 0.4, 1.0];
+
+/// Pointer state for one GUI frame, positions in window pixels.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GuiInput {
+    pub cursor: [f32; 2],
+    pub pointer_down: bool,
+    /// The pointer went down since the last frame.
+    pub pointer_pressed: bool,
+    /// The pointer went up since the last frame.
+    pub pointer_released: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct WindowState {
+    position: [f32; 2],
+    // of the last frame, for hit testing between frames
+    height: f32,
+    collapsed: bool,
+    // dragged by the user, otherwise stacked below the windows before it
+    moved: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct WindowLayout {
+    id: WidgetId,
+    left: f32,
+    width: f32,
+    cursor_y: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Interaction {
+    hovered: bool,
+    active: bool,
+    clicked: bool,
+}
+
+/// Immediate-mode widgets, everything is declared again every frame and
+/// laid out into an `OverlayBatch`.
+///
+/// Widgets are identified by their window and label, labels can carry a
+/// hidden suffix after `##` to tell identical ones apart.
+pub struct Ui {
+    input: GuiInput,
+    previous_cursor: [f32; 2],
+    screen_size: [f32; 2],
+    batch: OverlayBatch,
+    windows: HashMap<String, WindowState>,
+    // windows laid out this frame, in order
+    laid_out: Vec<String>,
+    current: Option<WindowLayout>,
+    active: Option<WidgetId>,
+}
+
+impl Ui {
+    pub fn new() -> Self {
+        Ui {
+            input: GuiInput::default(),
+            previous_cursor: [0.0; 2],
+            screen_size: [0.0; 2],
+            batch: OverlayBatch::default(),
+            windows: HashMap::new(),
+            laid_out: Vec::new(),
+            current: None,
+            active: None,
+        }
+    }
+
+    pub fn begin_frame(&mut self, input: GuiInput, screen_size: [f32; 2]) {
+        self.previous_cursor = self.input.cursor;
+        self.input = input;
+        self.screen_size = screen_size;
+        self.batch.clear();
+        self.laid_out.clear();
+    }
+
+    pub fn end_frame(&mut self) {
+        if !self.input.pointer_down {
+            self.active = None;
+        }
+    }
+
+    #[inline]
+    pub fn batch(&self) -> &OverlayBatch {
+        &self.batch
+    }
+
+    /// Whether `point` is over a window, as laid out last frame.
+    pub fn is_over_window(&self, point: [f32; 2]) -> bool {
+        self.windows.values().any(|window| {
+            let [x, y] = window.position;
+            contains([x, y, WINDOW_WIDTH, window.height], point)
+        })
+    }
+
+    /// A widget is being dragged or held.
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// A movable, collapsible window, widgets inside `add_contents` stack downwards.
+    pub fn window(&mut self, title: &str, add_contents: impl FnOnce(&mut Ui)) {
+        if self.current.is_some() {
+            log::warn!("Nested GUI windows are not supported: {}", title);
+            return;
+        }
+        let id = widget_id(0, title);
+        let default_position = self.default_window_position();
+        let mut state = *self.windows.entry(title.to_owned()).or_insert(WindowState {
+            position: default_position,
+            height: ROW_HEIGHT,
+            collapsed: false,
+            moved: false,
+        });
+        // restacked every frame, windows above may have grown
+        if !state.moved {
+            state.position = default_position;
+        }
+
+        let [x, y] = state.position;
+        let toggle_rect = [x + WINDOW_WIDTH - ROW_HEIGHT, y, ROW_HEIGHT, ROW_HEIGHT];
+        let toggle = self.interact(widget_id(id, "##collapse"), toggle_rect);
+        if toggle.clicked {
+            state.collapsed = !state.collapsed;
+        }
+        let title_bar = self.interact(id, [x, y, WINDOW_WIDTH - ROW_HEIGHT, ROW_HEIGHT]);
+        if title_bar.active && !self.input.pointer_pressed {
+            state.position[0] += self.input.cursor[0] - self.previous_cursor[0];
+            state.position[1] += self.input.cursor[1] - self.previous_cursor[1];
+            state.moved = true;
+        }
+        let [x, y] = state.position;
+
+        let outer = std::mem::take(&mut self.batch);
+        self.current = Some(WindowLayout {
+            id,
+            left: x + PADDING,
+            width: WINDOW_WIDTH - PADDING * 2.0,
+            cursor_y: y + ROW_HEIGHT + PADDING,
+        });
+        if !state.collapsed {
+            add_contents(self);
+        }
+        let layout = self.current.take().unwrap();
+        let contents = std::mem::replace(&mut self.batch, outer);
+
+        state.height = if state.collapsed {
+            ROW_HEIGHT
+        } else {
+            layout.cursor_y - y + PADDING - SPACING
+        };
+        self.batch
+            .rect(x, y, WINDOW_WIDTH, state.height, WINDOW_BACKGROUND);
+        self.batch
+            .rect(x, y, WINDOW_WIDTH, ROW_HEIGHT, TITLE_BACKGROUND);
+        self.text(x + PADDING, y, display_text(title), TEXT);
+        let indicator = if state.collapsed { "+" } else { "-" };
+        let indicator_color = if toggle.hovered { ACCENT } else { TEXT };
+        self.text(
+            x + WINDOW_WIDTH - ROW_HEIGHT + (ROW_HEIGHT - text_width(indicator)) * 0.5,
+            y,
+            indicator,
+            indicator_color,
+        );
+        self.batch.vertices.extend(contents.vertices);
+        self.windows.insert(title.to_owned(), state);
+        self.laid_out.push(title.to_owned());
+    }
+
+    /// Below the windows laid out this frame in the rightmost column with
+    /// space left, starting at the top right.
+    fn default_window_position(&self) -> [f32; 2] {
+        let mut x = (self.screen_size[0] - WINDOW_WIDTH - WINDOW_MARGIN).max(0.0);
+        loop {
+            let y = self
+                .laid_out
+                .iter()
+                .map(|title| &self.windows[title])
+                .filter(|window| !window.moved && window.position[0] == x)
+                .map(|window| window.position[1] + window.height + WINDOW_MARGIN)
+                .fold(WINDOW_MARGIN, f32::max);
+            if y + ROW_HEIGHT * 4.0 <= self.screen_size[1] || x < WINDOW_WIDTH + WINDOW_MARGIN {
+                return [x, y];
+            }
+            x -= WINDOW_WIDTH + WINDOW_MARGIN;
+        }
+    }
+
+    /// Messages outside of the windows, stacked upwards from the bottom left
+    /// corner with the last one at the bottom.
+    pub fn notifications<'a>(
+        &mut self,
+        messages: impl DoubleEndedIterator<Item = (log::Level, &'a str)>,
+    ) {
+        let mut y = self.screen_size[1] - WINDOW_MARGIN;
+        for (level, text) in messages.rev() {
+            y -= ROW_HEIGHT;
+            let color = match level {
+                log::Level::Error => TEXT_ERROR,
+                log::Level::Warn => TEXT_WARNING,
+                _ => TEXT,
+            };
+            let width = text_width(text) + PADDING * 2.0;
+            self.batch
+                .rect(WINDOW_MARGIN, y, width, ROW_HEIGHT, WINDOW_BACKGROUND);
+            self.text(WINDOW_MARGIN + PADDING, y, text, color);
+            y -= SPACING;
+        }
+    }
+
+    pub fn label(&mut self, text: &str) {
+        if let Some([x, y, _, _]) = self.allocate_row() {
+            self.text(x, y, text, TEXT);
+        }
+    }
+
+    /// A label with a dimmed caption in front, e.g. for read-only values.
+    pub fn value(&mut self, caption: &str, value: impl std::fmt::Display) {
+        if let Some([x, y, _, _]) = self.allocate_row() {
+            self.text(x, y, caption, TEXT_DIM);
+            let value = value.to_string();
+            self.text(x + text_width(caption) + text_width(" "), y, &value, TEXT);
+        }
+    }
+
+    pub fn separator(&mut self) {
+        let Some(layout) = self.current.as_mut() else {
+            return;
+        };
+        let y = layout.cursor_y;
+        layout.cursor_y += SPACING * 2.0;
+        let (left, width) = (layout.left, layout.width);
+        self.batch
+            .rect(left, y + SPACING * 0.5, width, 1.0, TEXT_DIM);
+    }
+
+    /// Returns whether the button was clicked.
+    pub fn button(&mut self, text: &str) -> bool {
+        let Some(rect) = self.allocate_row() else {
+            return false;
+        };
+        let id = self.id(text);
+        let interaction = self.interact(id, rect);
+        let [x, y, width, height] = rect;
+        self.batch
+            .rect(x, y, width, height, widget_color(interaction));
+        let text = display_text(text);
+        self.text(x + (width - text_width(text)) * 0.5, y, text, TEXT);
+        interaction.clicked
+    }
+
+    /// Returns whether the value changed.
+    pub fn checkbox(&mut self, text: &str, value: &mut bool) -> bool {
+        let Some(rect) = self.allocate_row() else {
+            return false;
+        };
+        let id = self.id(text);
+        let interaction = self.interact(id, rect);
+        if interaction.clicked {
+            *value = !*value;
+        }
+        let [x, y, _, height] = rect;
+        let box_size = height - 6.0;
+        self.batch
+            .rect(x, y + 3.0, box_size, box_size, widget_color(interaction));
+        if *value {
+            self.batch
+                .rect(x + 4.0, y + 7.0, box_size - 8.0, box_size - 8.0, ACCENT);
+        }
+        self.text(x + box_size + PADDING, y, display_text(text), TEXT);
+        interaction.clicked
+    }
+
+    /// Drags `value` within `range`, returns whether it changed.
+    pub fn slider(&mut self, text: &str, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        let Some(rect) = self.allocate_row() else {
+            return false;
+        };
+        let [x, y, width, height] = rect;
+        let label = display_text(text);
+        let track_left = x + width * 0.4;
+        let track = [track_left, y, width * 0.6, height];
+        let id = self.id(text);
+        let interaction = self.interact(id, track);
+
+        let (min, max) = (*range.start(), *range.end());
+        let previous = *value;
+        if interaction.active {
+            let t = ((self.input.cursor[0] - track[0]) / track[2]).clamp(0.0, 1.0);
+            *value = min + t * (max - min);
+        }
+        *value = value.clamp(min, max);
+
+        self.text(x, y, label, TEXT);
+        self.batch.rect(
+            track[0],
+            track[1],
+            track[2],
+            track[3],
+            widget_color(interaction),
+        );
+        let t = if max > min {
+            (*value - min) / (max - min)
+        } else {
+            0.0
+        };
+        self.batch
+            .rect(track[0] + t * (track[2] - 4.0), y, 4.0, height, ACCENT);
+        let value_text = format!("{:.2}", value);
+        self.text(
+            track[0] + (track[2] - text_width(&value_text)) * 0.5,
+            y,
+            &value_text,
+            TEXT,
+        );
+        *value != previous
+    }
+
+    /// One field per element of `values`, dragging a field sideways changes its value by
+    /// `speed` per pixel within `range`. Returns whether any value changed.
+    pub fn drag_values(
+        &mut self,
+        text: &str,
+        values: &mut [f32],
+        speed: f32,
+        range: RangeInclusive<f32>,
+    ) -> bool {
+        let Some(rect) = self.allocate_row() else {
+            return false;
+        };
+        let [x, y, width, height] = rect;
+        self.text(x, y, display_text(text), TEXT_DIM);
+        let fields_left = x + width * CAPTION_WIDTH;
+        let field_count = values.len().max(1) as f32;
+        let field_width =
+            (width * (1.0 - CAPTION_WIDTH) - SPACING * (field_count - 1.0)) / field_count;
+        let id = self.id(text);
+        let mut changed = false;
+        for (index, value) in values.iter_mut().enumerate() {
+            let field_x = fields_left + index as f32 * (field_width + SPACING);
+            let field = [field_x, y, field_width, height];
+            let interaction = self.interact(widget_id(id, &index.to_string()), field);
+            if interaction.active && !self.input.pointer_pressed {
+                let dragged = (self.input.cursor[0] - self.previous_cursor[0]) * speed;
+                let new_value = (*value + dragged).clamp(*range.start(), *range.end());
+                changed |= new_value != *value;
+                *value = new_value;
+            }
+            self.batch
+                .rect(field_x, y, field_width, height, widget_color(interaction));
+            let value_text = fit_value(*value, field_width);
+            self.text(
+                field_x + (field_width - text_width(&value_text)) * 0.5,
+                y,
+                &value_text,
+                TEXT,
+            );
+        }
+        changed
+    }
+
+    /// A row of a tree indented by `depth`, `expanded` is `None` for leaves and
+    /// toggles with the box in front. Returns whether the label was clicked.
+    pub fn tree_item(
+        &mut self,
+        text: &str,
+        depth: usize,
+        expanded: Option<&mut bool>,
+        selected: bool,
+    ) -> bool {
+        let Some(rect) = self.allocate_row() else {
+            return false;
+        };
+        let [x, y, width, height] = rect;
+        let toggle_x = x + depth as f32 * TREE_INDENT;
+        let label = [
+            toggle_x + height,
+            y,
+            width - (toggle_x - x) - height,
+            height,
+        ];
+        let id = self.id(text);
+        let interaction = self.interact(id, label);
+        if selected || interaction.hovered {
+            let color = if selected {
+                WIDGET_ACTIVE
+            } else {
+                WIDGET_HOVERED
+            };
+            self.batch.rect(label[0], y, label[2], height, color);
+        }
+        if let Some(expanded) = expanded {
+            let toggle = self.interact(widget_id(id, "##expand"), [toggle_x, y, height, height]);
+            if toggle.clicked {
+                *expanded = !*expanded;
+            }
+            let indicator = if *expanded { "-" } else { "+" };
+            let color = if toggle.hovered { ACCENT } else { TEXT_DIM };
+            self.text(
+                toggle_x + (height - text_width(indicator)) * 0.5,
+                y,
+                indicator,
+                color,
+            );
+        }
+        let text = fit_text(display_text(text), label[2] - SPACING);
+        self.text(label[0] + SPACING, y, &text, TEXT);
+        interaction.clicked
+    }
+
+    fn id(&self, text: &str) -> WidgetId {
+        widget_id(self.current.map_or(0, |layout| layout.id), text)
+    }
+
+    fn allocate_row(&mut self) -> Option<[f32; 4]> {
+        let Some(layout) = self.current.as_mut() else {
+            log::warn!("GUI widgets have to be inside a window");
+            return None;
+        };
+        let rect = [layout.left, layout.cursor_y, layout.width, ROW_HEIGHT];
+        layout.cursor_y += ROW_HEIGHT + SPACING;
+        Some(rect)
+    }
+
+    fn interact(&mut self, id: WidgetId, rect: [f32; 4]) -> Interaction {
+        let hovered = contains(rect, self.input.cursor);
+        if hovered && self.input.pointer_pressed && self.active.is_none() {
+            self.active = Some(id);
+        }
+        let active = self.active == Some(id);
+        Interaction {
+            hovered,
+            active,
+            clicked: active && hovered && self.input.pointer_released,
+        }
+    }
+
+    fn text(&mut self, x: f32, y: f32, text: &str, color: Color) {
+        // centred vertically in the row
+        let offset = (ROW_HEIGHT - GLYPH_HEIGHT as f32 * TEXT_SCALE) * 0.5;
+        self.batch.text(x, y + offset, TEXT_SCALE, color, text);
+    }
+}
+
+fn widget_color(interaction: Interaction) -> Color {
+    if interaction.active {
+        WIDGET_ACTIVE
+    } else if interaction.hovered {
+        WIDGET_HOVERED
+    } else {
+        WIDGET
+    }
+}
+
+fn widget_id(parent: WidgetId, text: &str) -> WidgetId {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    parent.hash(&mut hasher);
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The part of a label before `##`.
+fn display_text(text: &str) -> &str {
+    text.split("##").next().unwrap_or_default()
+}
+
+/// `value` with as many decimals as fit into `width`, at most two.
+fn fit_value(value: f32, width: f32) -> String {
+    (0..=2)
+        .rev()
+        .map(|precision| format!("{:.*}", precision, value))
+        .find(|text| text_width(text) <= width)
+        .unwrap_or_else(|| format!("{:.0}", value))
+}
+
+/// `text` cut short with `..` where it is wider than `width`.
+fn fit_text(text: &str, width: f32) -> String {
+    if text_width(text) <= width {
+        return text.to_owned();
+    }
+    let characters = (width / text_width(" ")) as usize;
+    let mut text: String = text.chars().take(characters.saturating_sub(2)).collect();
+    text.push_str("..");
+    text
+}
+
+fn text_width(text: &str) -> f32 {
+    text.chars().count() as f32 * ADVANCE as f32 * TEXT_SCALE
+}
+
+fn contains([x, y, width, height]: [f32; 4], [px, py]: [f32; 2]) -> bool {
+    px >= x && px < x + width && py >= y && py < y + height
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{GuiInput, Ui, PADDING, ROW_HEIGHT, WINDOW_MARGIN, WINDOW_WIDTH};
+
+    #[test]
+    fn widget_interaction() {
+        const SCREEN: [f32; 2] = [1280.0, 720.0];
+        fn frame(ui: &mut Ui, input: GuiInput, mut add_contents: impl FnMut(&mut Ui)) {
+            ui.begin_frame(input, SCREEN);
+            ui.window("Test", |ui| add_contents(ui));
+            ui.end_frame();
+        }
+        let press = |cursor| GuiInput {
+            cursor,
+            pointer_down: true,
+            pointer_pressed: true,
+            pointer_released: false,
+        };
+        let drag = |cursor| GuiInput {
+            cursor,
+            pointer_down: true,
+            ..Default::default()
+        };
+        // inside the first widget row of the first window
+        let row = [
+            SCREEN[0] - WINDOW_WIDTH - WINDOW_MARGIN + PADDING + 10.0,
+            WINDOW_MARGIN + ROW_HEIGHT + PADDING + 5.0,
+        ];
+
+        // buttons click on release
+        let mut ui = Ui::new();
+        let mut clicked = false;
+        frame(&mut ui, press(row), |ui| clicked |= ui.button("Apply"));
+        assert!(!clicked);
+        assert!(ui.is_active());
+        assert!(ui.is_over_window(row));
+        let release = |cursor| GuiInput {
+            cursor,
+            pointer_released: true,
+            ..Default::default()
+        };
+        frame(&mut ui, release(row), |ui| clicked |= ui.button("Apply"));
+        assert!(clicked);
+        assert!(!ui.is_active());
+        assert!(!ui.batch().vertices.is_empty());
+
+        // sliders follow the pointer and clamp
+        let mut ui = Ui::new();
+        let track_right = row[0] - 10.0 + WINDOW_WIDTH - PADDING * 2.0 - 1.0;
+        let mut value = 0.5;
+        frame(&mut ui, press([track_right, row[1]]), |ui| {
+            ui.slider("Speed", &mut value, 0.0..=2.0);
+        });
+        assert!(value > 1.9);
+        frame(&mut ui, drag([SCREEN[0], row[1]]), |ui| {
+            ui.slider("Speed", &mut value, 0.0..=2.0);
+        });
+        assert_eq!(value, 2.0);
+        frame(&mut ui, GuiInput::default(), |_| {});
+
+        // drag fields move by the pointer distance times the speed
+        let mut ui = Ui::new();
+        let mut values = [1.0, 2.0];
+        let field = [row[0] - 10.0 + (WINDOW_WIDTH - PADDING * 2.0) * 0.5, row[1]];
+        frame(&mut ui, press(field), |ui| {
+            ui.drag_values("Position", &mut values, 0.5, -10.0..=10.0);
+        });
+        assert_eq!(values, [1.0, 2.0]);
+        let mut changed = false;
+        frame(&mut ui, drag([field[0] + 4.0, field[1]]), |ui| {
+            changed = ui.drag_values("Position", &mut values, 0.5, -10.0..=10.0);
+        });
+        assert!(changed);
+        assert_eq!(values, [3.0, 2.0]);
+        frame(&mut ui, GuiInput::default(), |_| {});
+
+        // tree rows toggle with the box in front and select with the label
+        let mut expanded = true;
+        let mut selected = false;
+        let toggle = [row[0] - 10.0 + 5.0, row[1]];
+        for input in [press(toggle), release(toggle)] {
+            frame(&mut ui, input, |ui| {
+                selected |= ui.tree_item("Root", 0, Some(&mut expanded), false);
+            });
+        }
+        assert!(!expanded && !selected);
+        let label = [row[0] + ROW_HEIGHT + 10.0, row[1]];
+        for input in [press(label), release(label)] {
+            frame(&mut ui, input, |ui| {
+                selected |= ui.tree_item("Root", 0, Some(&mut expanded), false);
+            });
+        }
+        assert!(!expanded && selected);
+
+        // windows move with their title bar
+        let title = [row[0], WINDOW_MARGIN + 5.0];
+        let moved = [title[0] - 100.0, title[1] + 50.0];
+        frame(&mut ui, press(title), |_| {});
+        frame(&mut ui, drag(moved), |_| {});
+        assert!(ui.is_over_window(moved));
+        assert!(!ui.is_over_window(title));
+
+        // windows that were not moved stack below those growing above them
+        let mut ui = Ui::new();
+        let mut rows = 1;
+        for _ in 0..2 {
+            ui.begin_frame(GuiInput::default(), SCREEN);
+            ui.window("Top", |ui| {
+                for row in 0..rows {
+                    ui.label(&row.to_string());
+                }
+            });
+            ui.window("Bottom", |_| {});
+            ui.end_frame();
+            let top = ui.windows["Top"];
+            let bottom = ui.windows["Bottom"];
+            assert_eq!(
+                bottom.position[1],
+                top.position[1] + top.height + WINDOW_MARGIN
+            );
+            rows = 10;
+        }
+    }
+}
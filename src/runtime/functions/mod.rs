@@ -1,4 +1,6 @@
+pub mod editor_system;
 pub mod event_system;
+pub mod gui_system;
 pub mod render_system;
 pub mod scene_system;
 pub mod window_system;
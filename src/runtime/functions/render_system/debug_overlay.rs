@@ -0,0 +1,241 @@
+use std::{fmt::Write, time::Duration};
+
+use super::{
+    fps_manager::FrameStats,
+    overlay::{Color, OverlayBatch},
+};
+
+const SCALE: f32 = 2.0;
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+const GRAPH_HEIGHT: f32 = 60.0;
+const GRAPH_BAR_WIDTH: f32 = 2.0;
+/// Number of frames the frame graph shows.
+pub const GRAPH_FRAMES: usize = 120;
+// the wgpu report locks every resource registry, no need to take it each frame
+const REPORT_INTERVAL: Duration = Duration::from_millis(500);
+
+const BACKGROUND: Color = [0.0, 0.0, 0.0, 0.6];
+const TEXT: Color = [1.0, 1.0, 1.0, 1.0];
+const BAR_FAST: Color = [0.3, 0.9, 0.3, 0.9];
+const BAR_SLOW: Color = [0.9, 0.8, 0.2, 0.9];
+const BAR_HITCH: Color = [0.9, 0.3, 0.3, 0.9];
+const TARGET_LINE: Color = [1.0, 1.0, 1.0, 0.4];
+
+/// Number of live resources of the GPU backends, from `Instance::generate_report`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GpuResourceCounts {
+    pub buffers: usize,
+    pub textures: usize,
+    pub render_pipelines: usize,
+    pub bind_groups: usize,
+}
+
+/// Everything the debug overlay shows for a frame.
+#[derive(Debug, Clone, Default)]
+pub struct DebugStats {
+    pub fps: u32,
+    pub frame_stats: FrameStats,
+    /// Frame times in milliseconds, oldest first.
+    pub frame_times: Vec<f32>,
+    pub draw_calls: usize,
+    pub triangles: u64,
+    /// Size of the vertex and index buffers of the render queue.
+    pub scene_buffer_bytes: u64,
+    pub gpu_resources: Option<GpuResourceCounts>,
+    pub camera_position: [f32; 3],
+}
+
+impl DebugStats {
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(
+            text,
+            "FPS: {} ({:.2} ms)",
+            self.fps, self.frame_stats.average_ms
+        );
+        let _ = writeln!(
+            text,
+            "min {:.2} / max {:.2} ms, 1% low {:.0} fps",
+            self.frame_stats.min_ms, self.frame_stats.max_ms, self.frame_stats.low_1_percent_fps
+        );
+        let _ = writeln!(
+            text,
+            "Draw calls: {}  Triangles: {}",
+            self.draw_calls, self.triangles
+        );
+        let _ = writeln!(
+            text,
+            "Scene buffers: {:.2} MB",
+            self.scene_buffer_bytes as f64 / (1024.0 * 1024.0)
+        );
+        if let Some(gpu_resources) = self.gpu_resources {
+            let _ = writeln!(
+                text,
+                "GPU resources: {} buffers, {} textures",
+                gpu_resources.buffers, gpu_resources.textures
+            );
+            let _ = writeln!(
+                text,
+                "Pipelines: {}  Bind groups: {}",
+                gpu_resources.render_pipelines, gpu_resources.bind_groups
+            );
+        }
+        let [x, y, z] = self.camera_position;
+        let _ = write!(text, "Camera: {:.2}, {:.2}, {:.2}", x, y, z);
+        text
+    }
+}
+
+/// On-screen frame statistics, laid out into an `OverlayBatch` while visible.
+pub struct DebugOverlay {
+    visible: bool,
+    pub batch: OverlayBatch,
+    gpu_resources: Option<GpuResourceCounts>,
+    last_report: Option<std::time::Instant>,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        DebugOverlay {
+            visible: false,
+            batch: OverlayBatch::default(),
+            gpu_resources: None,
+            last_report: None,
+        }
+    }
+
+    #[inline]
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.batch.clear();
+        self.last_report = None;
+    }
+
+    /// Resource counts, refreshed from the wgpu report every `REPORT_INTERVAL`.
+    pub fn gpu_resources(&mut self, instance: &wgpu::Instance) -> Option<GpuResourceCounts> {
+        let stale = match self.last_report {
+            Some(last_report) => last_report.elapsed() >= REPORT_INTERVAL,
+            None => true,
+        };
+        if stale {
+            self.gpu_resources = gpu_resource_counts(instance);
+            self.last_report = Some(std::time::Instant::now());
+        }
+        self.gpu_resources
+    }
+
+    /// Lays out the text panel and the frame graph below it in the top left corner.
+    pub fn layout(&mut self, stats: &DebugStats) {
+        self.batch.clear();
+        if !self.visible {
+            return;
+        }
+        let text = stats.text();
+
+        // measure first, the background has to go below the text
+        let mut measure = OverlayBatch::default();
+        let (text_width, text_height) = measure.text(0.0, 0.0, SCALE, TEXT, &text);
+        let graph_width = GRAPH_FRAMES as f32 * GRAPH_BAR_WIDTH;
+        let width = text_width.max(graph_width) + PADDING * 2.0;
+        let height = text_height + GRAPH_HEIGHT + PADDING * 3.0;
+        self.batch.rect(MARGIN, MARGIN, width, height, BACKGROUND);
+        self.batch
+            .text(MARGIN + PADDING, MARGIN + PADDING, SCALE, TEXT, &text);
+
+        let graph_left = MARGIN + PADDING;
+        let graph_bottom = MARGIN + PADDING * 2.0 + text_height + GRAPH_HEIGHT;
+        // 30 fps fills the graph, slower frames are clamped
+        let full_scale_ms = 1000.0 / 30.0;
+        let frames = &stats.frame_times[stats.frame_times.len().saturating_sub(GRAPH_FRAMES)..];
+        for (index, frame_ms) in frames.iter().enumerate() {
+            let height = (frame_ms / full_scale_ms).min(1.0) * GRAPH_HEIGHT;
+            let color = if *frame_ms <= 1000.0 / 60.0 {
+                BAR_FAST
+            } else if *frame_ms <= full_scale_ms {
+                BAR_SLOW
+            } else {
+                BAR_HITCH
+            };
+            self.batch.rect(
+                graph_left + index as f32 * GRAPH_BAR_WIDTH,
+                graph_bottom - height,
+                GRAPH_BAR_WIDTH,
+                height,
+                color,
+            );
+        }
+        let target_y = graph_bottom - GRAPH_HEIGHT * (1000.0 / 60.0) / full_scale_ms;
+        self.batch
+            .rect(graph_left, target_y, graph_width, 1.0, TARGET_LINE);
+    }
+}
+
+fn gpu_resource_counts(instance: &wgpu::Instance) -> Option<GpuResourceCounts> {
+    let report = instance.generate_report();
+    // the backends wgpu builds for the platform, any of them may be chosen with `--backend`
+    let mut hubs = vec![report.gl];
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+    hubs.push(report.vulkan);
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    hubs.push(report.metal);
+    #[cfg(windows)]
+    hubs.extend([report.dx12, report.dx11]);
+    hubs.into_iter()
+        .flatten()
+        .map(|hub| GpuResourceCounts {
+            buffers: hub.buffers.num_occupied,
+            textures: hub.textures.num_occupied,
+            render_pipelines: hub.render_pipelines.num_occupied,
+            bind_groups: hub.bind_groups.num_occupied,
+        })
+        .reduce(|counts, other| GpuResourceCounts {
+            buffers: counts.buffers + other.buffers,
+            textures: counts.textures + other.textures,
+            render_pipelines: counts.render_pipelines + other.render_pipelines,
+            bind_groups: counts.bind_groups + other.bind_groups,
+        })
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{DebugOverlay, DebugStats, GpuResourceCounts, GRAPH_FRAMES};
+
+    #[test]
+    fn overlay_layout() {
+        let stats = DebugStats {
+            fps: 60,
+            frame_times: vec![16.0; GRAPH_FRAMES + 10],
+            draw_calls: 3,
+            triangles: 1234,
+            gpu_resources: Some(GpuResourceCounts {
+                buffers: 7,
+                ..Default::default()
+            }),
+            camera_position: [0.0, 5.0, -1.5],
+            ..Default::default()
+        };
+        let text = stats.text();
+        assert!(text.starts_with("FPS: 60"));
+        assert!(text.contains("Draw calls: 3  Triangles: 1234"));
+        assert!(text.contains("GPU resources: 7 buffers, 0 textures"));
+        assert!(text.ends_with("Camera: 0.00, 5.00, -1.50"));
+
+        let mut overlay = DebugOverlay::new();
+        overlay.layout(&stats);
+        assert!(overlay.batch.vertices.is_empty());
+
+        overlay.toggle();
+        overlay.layout(&stats);
+        let glyphs = text.chars().filter(|c| !c.is_whitespace()).count();
+        // background, glyphs, one bar per graphed frame and the 60 fps line
+        assert_eq!(
+            overlay.batch.vertices.len(),
+            (1 + glyphs + GRAPH_FRAMES + 1) * 6
+        );
+    }
+}
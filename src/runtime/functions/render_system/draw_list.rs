@@ -0,0 +1,222 @@
+use std::ops::Range;
+
+use crate::runtime::{
+    core::mathematics::{Array4, Matrix4},
+    functions::scene_system::{
+        graph::{AlphaMode, Material, NodeHandle},
+        models::renderable::SceneRenderData,
+    },
+    resources::asset_system::AssetId,
+};
+
+use super::object::ObjectUniform;
+
+/// The passes the scene is drawn in, in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderPhase {
+    Opaque,
+    /// Opaque where the alpha is above the cutoff, the rest is discarded.
+    Mask,
+    /// Blended over what is behind, back to front.
+    Transparent,
+}
+
+impl RenderPhase {
+    pub const ALL: [RenderPhase; 3] = [
+        RenderPhase::Opaque,
+        RenderPhase::Mask,
+        RenderPhase::Transparent,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderPhase::Opaque => "opaque",
+            RenderPhase::Mask => "mask",
+            RenderPhase::Transparent => "transparent",
+        }
+    }
+}
+
+/// The parts of a material its draws' pipeline depends on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MaterialState {
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+}
+
+impl MaterialState {
+    #[inline]
+    pub fn phase(&self) -> RenderPhase {
+        match self.alpha_mode {
+            AlphaMode::Opaque => RenderPhase::Opaque,
+            AlphaMode::Mask => RenderPhase::Mask,
+            AlphaMode::Blend => RenderPhase::Transparent,
+        }
+    }
+}
+
+impl From<&Material> for MaterialState {
+    fn from(material: &Material) -> Self {
+        MaterialState {
+            alpha_mode: material.alpha_mode,
+            double_sided: material.double_sided,
+        }
+    }
+}
+
+/// One draw of a mesh, extracted from the scene graph.
+#[derive(Debug, Clone, Copy)]
+pub struct DrawItem {
+    pub node: NodeHandle,
+    pub mesh: AssetId<SceneRenderData>,
+    pub object: ObjectUniform,
+    pub material: MaterialState,
+}
+
+/// What the renderer draws this frame, rebuilt every frame by
+/// `SceneGraph::extract` and put in draw order before it is drawn, see `draw_order`.
+#[derive(Debug, Default)]
+pub struct DrawList {
+    pub items: Vec<DrawItem>,
+}
+
+impl DrawList {
+    #[inline]
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    #[inline]
+    pub fn push(&mut self, item: DrawItem) {
+        self.items.push(item);
+    }
+
+    /// Per draw data in draw order, for the object buffer.
+    pub fn objects(&self) -> Vec<ObjectUniform> {
+        self.items.iter().map(|item| item.object).collect()
+    }
+
+    /// The node of every draw, in draw order.
+    pub fn nodes(&self) -> Vec<NodeHandle> {
+        self.items.iter().map(|item| item.node).collect()
+    }
+
+    /// The indices of the draws in the order they are drawn in: by phase, opaque
+    /// and masked ones by pipeline so each is set once, transparent ones back to
+    /// front by the view depth of their origin. Draws of the same phase and
+    /// pipeline keep their order.
+    ///
+    /// `pipelines` are those of the draws, `view_projection` is the camera's.
+    pub fn draw_order<P: Ord>(&self, view_projection: Matrix4, pipelines: &[P]) -> Vec<usize> {
+        // meshes are not split, one depth for each, worked out before sorting
+        let view_depths: Vec<f32> = self
+            .items
+            .iter()
+            .map(|item| match item.material.phase() {
+                RenderPhase::Transparent => {
+                    let origin = item.object.model * Array4::new([0.0, 0.0, 0.0, 1.0]);
+                    (view_projection * origin).0[3]
+                }
+                RenderPhase::Opaque | RenderPhase::Mask => 0.0,
+            })
+            .collect();
+        let mut order: Vec<_> = (0..self.items.len()).collect();
+        order.sort_by(|&draw, &other| {
+            let phase = self.items[draw].material.phase();
+            let other_phase = self.items[other].material.phase();
+            phase.cmp(&other_phase).then_with(|| match phase {
+                RenderPhase::Transparent => view_depths[other].total_cmp(&view_depths[draw]),
+                RenderPhase::Opaque | RenderPhase::Mask => pipelines[draw].cmp(&pipelines[other]),
+            })
+        });
+        order
+    }
+
+    /// Puts the draws in `order`, see `draw_order`.
+    pub fn reorder(&mut self, order: &[usize]) {
+        self.items = order.iter().map(|index| self.items[*index]).collect();
+    }
+
+    /// The indices of the draws of `phase`, the draws have to be sorted by phase.
+    pub fn phase(&self, phase: RenderPhase) -> Range<usize> {
+        let start = self
+            .items
+            .partition_point(|item| item.material.phase() < phase);
+        let end = self
+            .items
+            .partition_point(|item| item.material.phase() <= phase);
+        start..end
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{DrawItem, DrawList, MaterialState, RenderPhase};
+    use crate::runtime::{
+        core::mathematics::{transform::Transform, Matrix4},
+        functions::{
+            render_system::object::ObjectUniform,
+            scene_system::{
+                graph::{AlphaMode, SceneGraph},
+                models::renderable::SceneRenderData,
+            },
+        },
+        resources::asset_system::AssetServer,
+    };
+
+    #[test]
+    fn draw_order() {
+        let mut graph = SceneGraph::default();
+        let node = graph.spawn("Node".to_owned(), None, Transform::default());
+        let mut assets = AssetServer::default();
+        let mesh = assets.add(SceneRenderData::default());
+        let draw = |alpha_mode, z: f32| DrawItem {
+            node,
+            mesh: mesh.id(),
+            object: ObjectUniform::new(
+                Transform {
+                    translation: [0.0, 0.0, z],
+                    ..Default::default()
+                }
+                .matrix(),
+                [1.0; 4],
+            ),
+            material: MaterialState {
+                alpha_mode,
+                double_sided: false,
+            },
+        };
+        let draw_list = DrawList {
+            items: vec![
+                draw(AlphaMode::Blend, -1.0),
+                draw(AlphaMode::Opaque, 0.0),
+                draw(AlphaMode::Blend, -5.0),
+                draw(AlphaMode::Mask, 0.0),
+                draw(AlphaMode::Opaque, 0.0),
+                draw(AlphaMode::Opaque, 0.0),
+                draw(AlphaMode::Blend, -3.0),
+            ],
+        };
+        // the camera looks down -z, w is the distance in front of it
+        let view_projection = Matrix4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, -1.0],
+            [0.0, 0.0, 0.0, 0.0],
+        ]);
+        let pipelines = [0, 1, 0, 2, 0, 1, 0];
+
+        let order = draw_list.draw_order(view_projection, &pipelines);
+        // opaque by pipeline in their order, then masked, then transparent far to near
+        assert_eq!(order, [4, 1, 5, 3, 2, 6, 0]);
+
+        let mut sorted = DrawList {
+            items: draw_list.items.clone(),
+        };
+        sorted.reorder(&order);
+        assert_eq!(sorted.phase(RenderPhase::Opaque), 0..3);
+        assert_eq!(sorted.phase(RenderPhase::Mask), 3..4);
+        assert_eq!(sorted.phase(RenderPhase::Transparent), 4..7);
+        assert_eq!(DrawList::default().phase(RenderPhase::Mask), 0..0);
+    }
+}
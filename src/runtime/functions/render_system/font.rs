@@ -0,0 +1,168 @@
+/// Size of a glyph of the built-in font, in font pixels.
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+/// Distance from one glyph to the next, in font pixels.
+pub const ADVANCE: u32 = GLYPH_WIDTH + 1;
+pub const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 3;
+
+// atlas cells leave a blank row and column around each glyph so linear
+// sampling never bleeds into the neighbour
+const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+
+// one row per glyph line, the most significant of the 5 bits is the leftmost pixel,
+// lowercase letters are drawn with the uppercase glyphs
+#[rustfmt::skip]
+const GLYPHS: [(char, [u8; GLYPH_HEIGHT as usize]); 61] = [
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('!', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100]),
+    ('"', [0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('#', [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+    ('\'', [0b00100, 0b00100, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('(', [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010]),
+    (')', [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000]),
+    ('*', [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    (',', [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    ('/', [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    (';', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000]),
+    ('<', [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010]),
+    ('=', [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000]),
+    ('>', [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('[', [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110]),
+    ('\\', [0b00000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00001, 0b00000]),
+    (']', [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110]),
+    ('_', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111]),
+    ('|', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+];
+
+/// Single channel texture holding every glyph of the built-in font, followed by
+/// a fully lit cell that solid rectangles sample from.
+pub struct FontAtlas {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl FontAtlas {
+    pub fn new() -> Self {
+        let width = CELL_WIDTH * (GLYPHS.len() as u32 + 1);
+        let height = CELL_HEIGHT;
+        let mut pixels = vec![0; (width * height) as usize];
+        for (index, (_, rows)) in GLYPHS.iter().enumerate() {
+            for (y, row) in rows.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if row & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                        let pixel_x = index as u32 * CELL_WIDTH + x;
+                        pixels[(y as u32 * width + pixel_x) as usize] = 255;
+                    }
+                }
+            }
+        }
+        let solid_x = GLYPHS.len() as u32 * CELL_WIDTH;
+        for y in 0..GLYPH_HEIGHT {
+            for x in 0..GLYPH_WIDTH {
+                pixels[(y * width + solid_x + x) as usize] = 255;
+            }
+        }
+        FontAtlas {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Texture coordinates `[u_min, v_min, u_max, v_max]` of a character,
+/// characters the font lacks are drawn as `?`.
+pub fn glyph_uv(character: char) -> [f32; 4] {
+    let character = character.to_ascii_uppercase();
+    let index = GLYPHS
+        .iter()
+        .position(|(glyph, _)| *glyph == character)
+        .or_else(|| GLYPHS.iter().position(|(glyph, _)| *glyph == '?'))
+        .unwrap();
+    cell_uv(index)
+}
+
+/// Texture coordinates of the fully lit cell.
+#[inline]
+pub fn solid_uv() -> [f32; 4] {
+    // sample the cell centre, the edges border blank texels
+    let [u_min, v_min, u_max, v_max] = cell_uv(GLYPHS.len());
+    let (u, v) = ((u_min + u_max) * 0.5, (v_min + v_max) * 0.5);
+    [u, v, u, v]
+}
+
+fn cell_uv(index: usize) -> [f32; 4] {
+    let atlas_width = (CELL_WIDTH * (GLYPHS.len() as u32 + 1)) as f32;
+    let atlas_height = CELL_HEIGHT as f32;
+    let x = (index as u32 * CELL_WIDTH) as f32;
+    [
+        x / atlas_width,
+        0.0,
+        (x + GLYPH_WIDTH as f32) / atlas_width,
+        GLYPH_HEIGHT as f32 / atlas_height,
+    ]
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{glyph_uv, FontAtlas, GLYPHS};
+
+    #[test]
+    fn atlas_layout() {
+        let atlas = FontAtlas::new();
+        assert_eq!(atlas.pixels.len(), (atlas.width * atlas.height) as usize);
+        // the bar of `|` is the middle column of the glyph
+        let bar = GLYPHS.iter().position(|(glyph, _)| *glyph == '|').unwrap() as u32;
+        let x = bar * 6 + 2;
+        assert!((0..7).all(|y| atlas.pixels[(y * atlas.width + x) as usize] == 255));
+        assert_eq!(atlas.pixels[(7 * atlas.width + x) as usize], 0);
+
+        assert_eq!(glyph_uv('a'), glyph_uv('A'));
+        assert_eq!(glyph_uv('\u{e9}'), glyph_uv('?'));
+        assert_ne!(glyph_uv('1'), glyph_uv('?'));
+    }
+}
@@ -1,9 +1,35 @@
+use std::{collections::VecDeque, time::Duration};
+
+/// Caps the frame rate when set, e.g. `RUCCOLO_TARGET_FPS=144`.
+pub const TARGET_FPS_ENV: &str = "RUCCOLO_TARGET_FPS";
+
+// number of frames the statistics are computed over
+const HISTORY_LEN: usize = 1000;
+const HISTOGRAM_BUCKETS: usize = 10;
+// the limiter sleeps until this close to the deadline and spins the rest,
+// OS sleeps are not precise enough for pacing on their own
+const SPIN_THRESHOLD: Duration = Duration::from_micros(1500);
+
 pub struct FpsManager {
     delta_time: f32,
     fps: u32,
     frame_count: u32,
     last_update: std::time::Instant,
     last_frame: std::time::Instant,
+    target_frame_time: Option<Duration>,
+    frame_times: VecDeque<f32>,
+}
+
+/// Frame time statistics over the last frames, times in milliseconds.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameStats {
+    pub average_ms: f32,
+    pub min_ms: f32,
+    pub max_ms: f32,
+    /// Average fps of the slowest 1% of the frames.
+    pub low_1_percent_fps: f32,
+    /// Frame count per bucket, buckets evenly split `min_ms..=max_ms`.
+    pub histogram: [u32; HISTOGRAM_BUCKETS],
 }
 
 impl FpsManager {
@@ -14,6 +40,8 @@ impl FpsManager {
             frame_count: 0,
             last_update: std::time::Instant::now(),
             last_frame: std::time::Instant::now(),
+            target_frame_time: None,
+            frame_times: VecDeque::with_capacity(HISTORY_LEN),
         }
     }
 
@@ -22,6 +50,10 @@ impl FpsManager {
         self.frame_count += 1;
         self.delta_time = self.last_frame.elapsed().as_secs_f32();
         self.last_frame = std::time::Instant::now();
+        if self.frame_times.len() == HISTORY_LEN {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(self.delta_time * 1000.0);
     }
 
     #[inline]
@@ -45,4 +77,109 @@ impl FpsManager {
     pub fn elapsed(&self) -> f32 {
         self.last_update.elapsed().as_secs_f32()
     }
+
+    /// `None` or a non-positive value removes the cap.
+    pub fn set_target_fps(&mut self, target_fps: Option<f32>) {
+        self.target_frame_time = target_fps
+            .filter(|fps| *fps > 0.0)
+            .map(|fps| Duration::from_secs_f32(1.0 / fps));
+    }
+
+    /// Blocks until the frame started by the last `tick` has taken the target frame time.
+    pub fn wait_for_next_frame(&self) {
+        let Some(target_frame_time) = self.target_frame_time else {
+            return;
+        };
+        let deadline = self.last_frame + target_frame_time;
+        loop {
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                break;
+            }
+            let remaining = deadline - now;
+            if remaining > SPIN_THRESHOLD {
+                std::thread::sleep(remaining - SPIN_THRESHOLD);
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+    }
+
+    /// Frame times of the last frames in milliseconds, oldest first.
+    pub fn frame_times(&self) -> impl Iterator<Item = f32> + '_ {
+        self.frame_times.iter().copied()
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        frame_stats(self.frame_times.iter().copied())
+    }
+}
+
+fn frame_stats(frame_times: impl Iterator<Item = f32>) -> FrameStats {
+    let mut sorted: Vec<f32> = frame_times.collect();
+    if sorted.is_empty() {
+        return FrameStats::default();
+    }
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let min_ms = sorted[0];
+    let max_ms = sorted[sorted.len() - 1];
+    let average_ms = sorted.iter().sum::<f32>() / sorted.len() as f32;
+    let slowest = &sorted[(sorted.len() * 99 / 100).min(sorted.len() - 1)..];
+    let slowest_average_ms = slowest.iter().sum::<f32>() / slowest.len() as f32;
+
+    let mut histogram = [0; HISTOGRAM_BUCKETS];
+    let bucket_width = (max_ms - min_ms) / HISTOGRAM_BUCKETS as f32;
+    for time in sorted.iter() {
+        let bucket = if bucket_width > 0.0 {
+            (((time - min_ms) / bucket_width) as usize).min(HISTOGRAM_BUCKETS - 1)
+        } else {
+            0
+        };
+        histogram[bucket] += 1;
+    }
+
+    FrameStats {
+        average_ms,
+        min_ms,
+        max_ms,
+        low_1_percent_fps: 1000.0 / slowest_average_ms,
+        histogram,
+    }
+}
+
+impl std::fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "avg {:.2}ms ({:.0} fps), 1% low {:.0} fps, min {:.2}ms, max {:.2}ms",
+            self.average_ms,
+            1000.0 / self.average_ms,
+            self.low_1_percent_fps,
+            self.min_ms,
+            self.max_ms
+        )
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::frame_stats;
+
+    #[test]
+    fn frame_stats_test() {
+        let mut times = vec![10.0; 99];
+        times.push(50.0);
+        let stats = frame_stats(times.into_iter());
+        assert!((stats.average_ms - 10.4).abs() < 1e-4);
+        assert_eq!(stats.min_ms, 10.0);
+        assert_eq!(stats.max_ms, 50.0);
+        assert!((stats.low_1_percent_fps - 20.0).abs() < 1e-4);
+        assert_eq!(stats.histogram[0], 99);
+        assert_eq!(stats.histogram[9], 1);
+        assert_eq!(stats.histogram.iter().sum::<u32>(), 100);
+
+        let stats = frame_stats(std::iter::empty());
+        assert_eq!(stats.histogram.iter().sum::<u32>(), 0);
+    }
 }
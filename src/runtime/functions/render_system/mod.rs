@@ -1,16 +1,77 @@
+pub mod debug_overlay;
+pub mod draw_list;
+pub mod font;
 pub mod fps_manager;
+pub mod object;
+pub mod overlay;
+pub mod panel;
+pub mod picking;
+pub mod pipeline_cache;
+pub mod preprocessor;
+pub mod profiler;
+mod screenshot;
+pub mod shader;
+pub mod texture;
+
+use std::path::PathBuf;
 
 use wgpu::util::DeviceExt;
 
-use crate::runtime::{core::mathematics::Matrix4, platforms::gpu::GpuContext};
+use crate::runtime::{
+    core::{config::EngineConfig, mathematics::Matrix4},
+    platforms::gpu::GpuContext,
+    resources::{
+        asset_system::AssetServer,
+        file_watcher::{self, WatchBackend},
+    },
+};
+
+use self::{
+    debug_overlay::{DebugOverlay, DebugStats, GRAPH_FRAMES},
+    draw_list::{DrawItem, DrawList, RenderPhase},
+    fps_manager::FpsManager,
+    object::ObjectBuffer,
+    overlay::{OverlayBatch, OverlayRenderer},
+    picking::ObjectPicker,
+    pipeline_cache::{DepthState, PipelineCache, PipelineId, PipelineKey, DEPTH_FORMAT},
+    profiler::FrameProfiler,
+    screenshot::Screenshot,
+    shader::{
+        create_or_embedded, error_summary, shader_paths, ShaderCache, ShaderOrigin, OVERLAY_SHADER,
+    },
+};
 
 use super::scene_system::{camera::CameraInfo, models::renderable::SceneRenderData, VerticesClip};
 
-pub struct RenderManager<'a> {
+pub struct RenderManager {
     pub gpu_context: GpuContext,
-    pipeline: wgpu::RenderPipeline,
-    pub bindgroup: Vec<wgpu::BindGroup>,
-    pub render_queue: Vec<SceneRenderData<'a>>,
+    // created as draws need them, rebuilt when the scene shader changes
+    pipelines: PipelineCache,
+    // only when the adapter supports `POLYGON_MODE_LINE`
+    wireframe: bool,
+    shaders: ShaderCache,
+    // where `shaders` were read from, the embedded ones when those on disk failed at start
+    scene_shader: ShaderOrigin,
+    // follows the surface size, created on the first frame
+    depth_target: Option<DepthTarget>,
+    // reports changes of the shader files in dev builds, see `reload_shaders`
+    shader_watcher: Option<Box<dyn WatchBackend>>,
+    screenshot_requested: bool,
+    screenshot_path: Option<PathBuf>,
+    // where the last screenshot was saved, or why it was not, see `take_screenshot_result`
+    screenshot_result: Option<Result<PathBuf, String>>,
+    present_modes: Vec<wgpu::PresentMode>,
+    pub profiler: FrameProfiler,
+    overlay_renderer: OverlayRenderer,
+    pub debug_overlay: DebugOverlay,
+    // per draw transform and material, indexed like `draw_list`
+    objects: ObjectBuffer,
+    // object ids for clicks, only rendered on frames with a pick request
+    pub picker: ObjectPicker,
+    /// The frame is cleared to this, linear RGBA.
+    pub clear_color: [f32; 4],
+    /// Extracted from the scene graph before every frame.
+    pub draw_list: DrawList,
 }
 
 const VERTICES: [[f32; 3]; 6] = [
@@ -23,11 +84,17 @@ const VERTICES: [[f32; 3]; 6] = [
 ];
 
 #[profiling::all_functions]
-impl RenderManager<'_> {
-    pub async fn new<'a>(window: &winit::window::Window, camera: &CameraInfo) -> RenderManager<'a> {
+impl RenderManager {
+    /// GPU resources shared with the scene, like the font texture, are created through `assets`.
+    pub async fn new(
+        window: &winit::window::Window,
+        camera: &CameraInfo,
+        config: &EngineConfig,
+        assets: &mut AssetServer,
+    ) -> RenderManager {
         let dxc_path = std::path::PathBuf::from("./shared");
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::DX12,
+            backends: config.backend.backends(),
             dx12_shader_compiler: wgpu::Dx12Compiler::Dxc {
                 dxil_path: Some(dxc_path.clone()),
                 dxc_path: Some(dxc_path),
@@ -45,11 +112,13 @@ impl RenderManager<'_> {
         let surface_capabilities = surface.get_capabilities(&adapter);
         println!("Adapter features: {:#?}", adapter.features());
         println!("Adapter limitss: {:#?}", adapter.limits());
+        let optional_features = adapter.features()
+            & (wgpu::Features::POLYGON_MODE_LINE | wgpu::Features::TIMESTAMP_QUERY);
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: Some("Primary Device"),
-                    features: wgpu::Features::PUSH_CONSTANTS,
+                    features: wgpu::Features::PUSH_CONSTANTS | optional_features,
                     limits: wgpu::Limits {
                         max_push_constant_size: 64,
                         ..Default::default()
@@ -63,31 +132,18 @@ impl RenderManager<'_> {
         let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_capabilities.formats[0],
-            width: 1280,
-            height: 720,
-            // present_mode: wgpu::PresentMode::AutoVsync,
-            present_mode: wgpu::PresentMode::AutoNoVsync,
+            width: window.inner_size().width,
+            height: window.inner_size().height,
+            present_mode: if config.vsync {
+                wgpu::PresentMode::AutoVsync
+            } else {
+                wgpu::PresentMode::AutoNoVsync
+            },
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
             view_formats: vec![surface_capabilities.formats[0]],
         };
         surface.configure(&device, &surface_config);
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Basic Shader"),
-            source: wgpu::ShaderSource::Wgsl(
-                include_str!(concat!(
-                    env!("CARGO_MANIFEST_DIR"),
-                    "/assets/shaders/basic.wgsl"
-                ))
-                .into(),
-            ),
-        });
-
-        let vertex_buffer_layout = wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<[f32; 3]>() as _,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![0 => Float32x3],
-        };
         let bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Bindgroup Layout"),
             entries: &[wgpu::BindGroupLayoutEntry {
@@ -102,48 +158,30 @@ impl RenderManager<'_> {
             }],
         });
 
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                // bind_group_layouts: &[&bg_layout],
-                bind_group_layouts: &[],
-                push_constant_ranges: &[wgpu::PushConstantRange {
-                    stages: wgpu::ShaderStages::VERTEX,
-                    range: 0..(std::mem::size_of::<Matrix4>() as u32),
-                }],
-            });
-
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[vertex_buffer_layout],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
+        let objects = ObjectBuffer::new(&device);
+
+        let mut shaders = ShaderCache::default();
+        let (scene_shader, pipelines) = create_or_embedded(&device, |origin| {
+            // the shader has to draw and pick the most common meshes
+            let mut pipelines = PipelineCache::new(&device, objects.layout());
+            let key = PipelineKey::new(surface_config.format);
+            pipelines.insert(&device, &mut shaders, origin, &key)?;
+            pipelines.insert(&device, &mut shaders, origin, &key.picking())?;
+            Ok((origin, pipelines))
+        });
+        let picker = ObjectPicker::new(&device);
+        // release builds only have the embedded shaders
+        let shader_watcher = (cfg!(debug_assertions) && config.hot_reload).then(|| {
+            let mut watcher = file_watcher::default_backend();
+            for path in shader_paths() {
+                watcher.watch(&path);
+            }
+            watcher
         });
 
+        let profiler = FrameProfiler::new(&device, &queue);
+        let overlay_renderer = OverlayRenderer::new(&device, &queue, surface_config.format, assets);
+
         RenderManager {
             gpu_context: GpuContext {
                 instance,
@@ -152,10 +190,117 @@ impl RenderManager<'_> {
                 surface,
                 surface_config,
             },
-            pipeline,
-            bindgroup: Vec::new(),
-            render_queue: Vec::new(),
+            pipelines,
+            wireframe: false,
+            shaders,
+            scene_shader,
+            depth_target: None,
+            shader_watcher,
+            screenshot_requested: false,
+            screenshot_path: None,
+            screenshot_result: None,
+            present_modes: surface_capabilities.present_modes,
+            profiler,
+            overlay_renderer,
+            debug_overlay: DebugOverlay::new(),
+            objects,
+            picker,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            draw_list: DrawList::default(),
+        }
+    }
+
+    /// Rebuilds the pipelines of the shaders changed on disk, only dev builds
+    /// watch them. A shader that does not validate leaves the last good pipelines in use.
+    /// Returns each changed shader with its result, errors on one line.
+    pub fn reload_shaders(&mut self) -> Vec<(PathBuf, Result<(), String>)> {
+        let Some(watcher) = self.shader_watcher.as_mut() else {
+            return Vec::new();
+        };
+        let mut reloaded = Vec::new();
+        for path in watcher.poll_changes() {
+            let result = if path == OVERLAY_SHADER.path() {
+                self.overlay_renderer
+                    .reload_shader(&self.gpu_context.device)
+            } else {
+                // any of them may be included, the scene shader is remade from all
+                self.reload_scene_shader()
+            };
+            match &result {
+                Ok(()) => log::info!("Reloaded shader {:?}", path),
+                Err(err) => log::error!("Unable to reload shader {:?}: {}", path, err),
+            }
+            reloaded.push((path, result.map_err(|err| error_summary(&err))));
         }
+        reloaded
+    }
+
+    fn reload_scene_shader(&mut self) -> Result<(), String> {
+        // the current modules stay for new pipelines until all of them are rebuilt
+        let mut shaders = ShaderCache::default();
+        self.pipelines
+            .rebuild(&self.gpu_context.device, &mut shaders, ShaderOrigin::Disk)?;
+        self.shaders = shaders;
+        self.scene_shader = ShaderOrigin::Disk;
+        Ok(())
+    }
+
+    /// The pipeline of every key, created the first time it is drawn.
+    fn pipeline_ids(&mut self, keys: &[Option<PipelineKey>]) -> Vec<Option<PipelineId>> {
+        keys.iter()
+            .map(|key| {
+                Some(self.pipelines.id(
+                    &self.gpu_context.device,
+                    &mut self.shaders,
+                    self.scene_shader,
+                    key.as_ref()?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Sorts the draw list in draw order, see `DrawList::draw_order`. Returns the
+    /// pipeline key of every draw, `None` for meshes that are not uploaded.
+    fn sort_draws(
+        &mut self,
+        camera_mvp: Matrix4,
+        assets: &AssetServer,
+    ) -> Vec<Option<PipelineKey>> {
+        let key = PipelineKey {
+            polygon_mode: if self.wireframe {
+                wgpu::PolygonMode::Line
+            } else {
+                wgpu::PolygonMode::Fill
+            },
+            ..PipelineKey::new(self.gpu_context.surface_config.format)
+        };
+        let keys: Vec<_> = self
+            .draw_list
+            .items
+            .iter()
+            .map(|item| {
+                let renderable = assets.get(item.mesh)?;
+                let (_, vertex_layout) = renderable.vertexbuffer.as_ref()?;
+                let (_, index_format, _) = renderable.indexbuffer.as_ref()?;
+                Some(PipelineKey {
+                    vertex_layout: vertex_layout.clone(),
+                    topology: renderable.topology,
+                    strip_index_format: renderable.topology.is_strip().then_some(*index_format),
+                    cull_mode: (!item.material.double_sided).then_some(wgpu::Face::Back),
+                    alpha_mode: item.material.alpha_mode,
+                    // blended surfaces do not hide what is behind them
+                    depth: Some(DepthState {
+                        write: item.material.phase() != RenderPhase::Transparent,
+                        ..Default::default()
+                    }),
+                    ..key.clone()
+                })
+            })
+            .collect();
+        let ids = self.pipeline_ids(&keys);
+        let order = self.draw_list.draw_order(camera_mvp, &ids);
+        self.draw_list.reorder(&order);
+        order.into_iter().map(|index| keys[index].clone()).collect()
     }
 
     #[profiling::skip]
@@ -164,58 +309,389 @@ impl RenderManager<'_> {
     }
 
     #[inline]
-    pub fn tick(&self, camera_mvp: Matrix4) {
-        let frame = self.gpu_context.surface.get_current_texture().unwrap();
-        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor {
+    pub fn wireframe(&self) -> bool {
+        self.wireframe
+    }
+
+    pub fn toggle_wireframe(&mut self) {
+        if !self
+            .gpu_context
+            .device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+        {
+            log::warn!("Wireframe rendering needs POLYGON_MODE_LINE, unsupported by the adapter");
+            return;
+        }
+        self.wireframe = !self.wireframe;
+    }
+
+    /// Reconfigures the surface, modes the surface does not support are ignored.
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+        let auto = matches!(
+            present_mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+        );
+        if !auto && !self.present_modes.contains(&present_mode) {
+            log::warn!(
+                "Present mode {:?} is not supported by the surface",
+                present_mode
+            );
+            return;
+        }
+        log::info!("Present mode: {:?}", present_mode);
+        self.gpu_context.surface_config.present_mode = present_mode;
+        self.gpu_context
+            .surface
+            .configure(&self.gpu_context.device, &self.gpu_context.surface_config);
+    }
+
+    /// Switches between vsync (`Fifo`), `Mailbox` and `Immediate`, skipping unsupported ones.
+    pub fn cycle_present_mode(&mut self) {
+        const CYCLE: [wgpu::PresentMode; 3] = [
+            wgpu::PresentMode::Fifo,
+            wgpu::PresentMode::Mailbox,
+            wgpu::PresentMode::Immediate,
+        ];
+        let current = CYCLE
+            .iter()
+            .position(|mode| *mode == self.gpu_context.surface_config.present_mode);
+        let next = (1..=CYCLE.len())
+            .map(|offset| CYCLE[(current.unwrap_or(CYCLE.len() - 1) + offset) % CYCLE.len()])
+            .find(|mode| self.present_modes.contains(mode));
+        if let Some(next) = next {
+            self.set_present_mode(next);
+        }
+    }
+
+    #[inline]
+    pub fn toggle_debug_overlay(&mut self) {
+        self.debug_overlay.toggle();
+    }
+
+    /// Gathers this frame's statistics into the debug overlay, if it is visible.
+    pub fn update_debug_overlay(
+        &mut self,
+        fps_manager: &FpsManager,
+        camera: &CameraInfo,
+        assets: &AssetServer,
+    ) {
+        if !self.debug_overlay.visible() {
+            return;
+        }
+        let mut stats = DebugStats {
+            fps: fps_manager.get_fps(),
+            frame_stats: fps_manager.stats(),
+            frame_times: fps_manager.frame_times().collect(),
+            gpu_resources: self.debug_overlay.gpu_resources(&self.gpu_context.instance),
+            camera_position: [
+                camera.position.0[0],
+                camera.position.0[1],
+                camera.position.0[2],
+            ],
+            ..Default::default()
+        };
+        let skip = stats.frame_times.len().saturating_sub(GRAPH_FRAMES);
+        stats.frame_times.drain(..skip);
+        for (_, renderable) in assets.iter::<SceneRenderData>() {
+            if let (Some((vertexbuffer, _)), Some((indexbuffer, _, _))) =
+                (&renderable.vertexbuffer, &renderable.indexbuffer)
+            {
+                stats.scene_buffer_bytes += vertexbuffer.size() + indexbuffer.size();
+            }
+        }
+        for item in self.draw_list.items.iter() {
+            if let Some((_, _, index_count)) = assets
+                .get(item.mesh)
+                .and_then(|renderable| renderable.indexbuffer.as_ref())
+            {
+                stats.draw_calls += 1;
+                stats.triangles += *index_count as u64 / 3;
+            }
+        }
+        self.debug_overlay.layout(&stats);
+    }
+
+    /// The next frame is saved to disk instead of being presented.
+    pub fn request_screenshot(&mut self) {
+        self.screenshot_requested = true;
+    }
+
+    /// Like `request_screenshot`, saved to `path` instead of `screenshots/`.
+    pub fn request_screenshot_to(&mut self, path: PathBuf) {
+        self.screenshot_requested = true;
+        self.screenshot_path = Some(path);
+    }
+
+    /// Where the screenshot taken since the last call was saved, or why it was not.
+    pub fn take_screenshot_result(&mut self) -> Option<Result<PathBuf, String>> {
+        self.screenshot_result.take()
+    }
+
+    /// Renders the scene, then the debug overlay and `overlays` on top, in order.
+    #[inline]
+    pub fn tick(&mut self, camera_mvp: Matrix4, assets: &AssetServer, overlays: &[&OverlayBatch]) {
+        let encode_start = std::time::Instant::now();
+        let keys = self.sort_draws(camera_mvp, assets);
+        let scene_pipelines = self.pipeline_ids(&keys);
+        self.objects.upload(
+            &self.gpu_context.device,
+            &self.gpu_context.queue,
+            &self.draw_list.objects(),
+        );
+        let size = [
+            self.gpu_context.surface_config.width,
+            self.gpu_context.surface_config.height,
+        ];
+        // surfaces may not be copied from, so the frame of a screenshot is drawn
+        // into a texture that can be and is not presented
+        let target = if std::mem::take(&mut self.screenshot_requested) {
+            FrameTarget::Screenshot(Screenshot::target(
+                &self.gpu_context.device,
+                size,
+                self.gpu_context.surface_config.format,
+            ))
+        } else {
+            FrameTarget::Surface(self.gpu_context.surface.get_current_texture().unwrap())
+        };
+        let view = target.texture().create_view(&wgpu::TextureViewDescriptor {
             // format: Some(self.gpu_context.surface_config.view_formats[0]),
             ..Default::default()
         });
-        let rp_desc = wgpu::RenderPassDescriptor {
-            label: None,
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                    store: false,
-                },
-            })],
-            depth_stencil_attachment: None,
-        };
+        if self.depth_target.as_ref().map(|target| target.size) != Some(size) {
+            self.depth_target = Some(DepthTarget::new(&self.gpu_context.device, size));
+        }
+        let depth_view = &self.depth_target.as_ref().unwrap().view;
 
         let mut command_encoder = self
             .gpu_context
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-        // render pass
+        // render passes, one per phase
+        let clear_color = wgpu::Color {
+            r: self.clear_color[0] as f64,
+            g: self.clear_color[1] as f64,
+            b: self.clear_color[2] as f64,
+            a: self.clear_color[3] as f64,
+        };
+        for phase in RenderPhase::ALL {
+            let draws = self.draw_list.phase(phase);
+            let first = phase == RenderPhase::Opaque;
+            // the first clears the frame even without draws
+            if !first && draws.is_empty() {
+                continue;
+            }
+            self.profiler.begin_pass(&mut command_encoder, phase.name());
+            {
+                let mut pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some(phase.name()),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: if first {
+                                wgpu::LoadOp::Clear(clear_color)
+                            } else {
+                                wgpu::LoadOp::Load
+                            },
+                            // the overlay and screenshots read the frame after these passes
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: depth_view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: if first {
+                                wgpu::LoadOp::Clear(1.0)
+                            } else {
+                                wgpu::LoadOp::Load
+                            },
+                            // transparent draws only test it
+                            store: phase != RenderPhase::Transparent,
+                        }),
+                        stencil_ops: None,
+                    }),
+                });
+                draw_objects(
+                    &mut pass,
+                    camera_mvp,
+                    assets,
+                    &self.objects,
+                    &self.pipelines,
+                    draws
+                        .map(|index| (index, &self.draw_list.items[index], scene_pipelines[index])),
+                );
+            }
+            self.profiler.end_pass(&mut command_encoder);
+        }
+
+        if self
+            .picker
+            .begin(&self.gpu_context.device, size, &self.draw_list)
         {
-            let mut pass = command_encoder.begin_render_pass(&rp_desc);
-            pass.set_pipeline(&self.pipeline);
-            pass.set_push_constants(
-                wgpu::ShaderStages::VERTEX,
-                0,
-                bytemuck::cast_slice(&[camera_mvp]),
-            );
-            for renderable in self.render_queue.iter() {
-                // pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-                // pass.draw(0..6, 0..1);
-                pass.set_vertex_buffer(0, renderable.vertexbuffer.as_ref().unwrap().0.slice(..));
-                pass.set_index_buffer(
-                    renderable.indexbuffer.as_ref().unwrap().0.slice(..),
-                    renderable.indexbuffer.as_ref().unwrap().1,
+            let keys: Vec<_> = keys
+                .iter()
+                .map(|key| key.as_ref().map(PipelineKey::picking))
+                .collect();
+            let picking_pipelines = self.pipeline_ids(&keys);
+            self.profiler.begin_pass(&mut command_encoder, "picking");
+            {
+                let mut pass = self.picker.begin_pass(&mut command_encoder);
+                draw_objects(
+                    &mut pass,
+                    camera_mvp,
+                    assets,
+                    &self.objects,
+                    &self.pipelines,
+                    self.draw_list
+                        .items
+                        .iter()
+                        .zip(picking_pipelines)
+                        .enumerate()
+                        .map(|(index, (item, id))| (index, item, id)),
                 );
-                // pass.set_bind_group(0, &self.bindgroup[0], &[]);
-                pass.draw_indexed(0..renderable.indexbuffer.as_ref().unwrap().2, 0, 0..1);
             }
-            // pass.draw_indexed(indices, 0, 0..1);
+            self.picker.copy_pixel(&mut command_encoder);
+            self.profiler.end_pass(&mut command_encoder);
         }
 
+        // overlays last, the given ones above the debug overlay
+        let overlays: Vec<_> = std::iter::once(&self.debug_overlay.batch)
+            .chain(overlays.iter().copied())
+            .collect();
+        if overlays.iter().any(|batch| !batch.vertices.is_empty()) {
+            self.profiler.begin_pass(&mut command_encoder, "overlay");
+            self.overlay_renderer.draw(
+                &self.gpu_context.device,
+                &self.gpu_context.queue,
+                &mut command_encoder,
+                &view,
+                [
+                    self.gpu_context.surface_config.width as f32,
+                    self.gpu_context.surface_config.height as f32,
+                ],
+                &overlays,
+            );
+            self.profiler.end_pass(&mut command_encoder);
+        }
+
+        let screenshot = match &target {
+            FrameTarget::Screenshot(texture) => Some(Screenshot::copy_from(
+                &self.gpu_context.device,
+                &mut command_encoder,
+                texture,
+                self.gpu_context.surface_config.format,
+            )),
+            FrameTarget::Surface(_) => None,
+        };
+
+        self.profiler.resolve(&mut command_encoder);
+        let command_buffer = command_encoder.finish();
+        self.profiler.record_encode(encode_start.elapsed());
+
         // submit
-        self.gpu_context
-            .queue
-            .submit(Some(command_encoder.finish()));
+        let submit_start = std::time::Instant::now();
+        self.gpu_context.queue.submit(Some(command_buffer));
+        self.profiler.record_submit(submit_start.elapsed());
+        self.profiler.end_frame(&self.gpu_context.device);
+        self.picker.poll(&self.gpu_context.device);
 
-        frame.present();
+        if let Some(screenshot) = screenshot {
+            let result = screenshot.save(&self.gpu_context.device, self.screenshot_path.take());
+            match &result {
+                Ok(path) => log::info!("Screenshot saved to {:?}", path),
+                Err(err) => log::error!("{}", err),
+            }
+            self.screenshot_result = Some(result);
+        }
+
+        if let FrameTarget::Surface(frame) = target {
+            frame.present();
+        }
+    }
+}
+
+/// What a frame is drawn into, see `RenderManager::tick`.
+enum FrameTarget {
+    Surface(wgpu::SurfaceTexture),
+    Screenshot(wgpu::Texture),
+}
+
+impl FrameTarget {
+    fn texture(&self) -> &wgpu::Texture {
+        match self {
+            FrameTarget::Surface(frame) => &frame.texture,
+            FrameTarget::Screenshot(texture) => texture,
+        }
+    }
+}
+
+struct DepthTarget {
+    view: wgpu::TextureView,
+    size: [u32; 2],
+}
+
+impl DepthTarget {
+    fn new(device: &wgpu::Device, size: [u32; 2]) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Target"),
+            size: wgpu::Extent3d {
+                width: size[0].max(1),
+                height: size[1].max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        DepthTarget {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            size,
+        }
+    }
+}
+
+/// Draws `draws`, each an index into the draw list with its draw and pipeline.
+/// Nothing is drawn without its object data or pipeline.
+fn draw_objects<'a, 'b>(
+    pass: &mut wgpu::RenderPass<'a>,
+    camera_mvp: Matrix4,
+    assets: &'a AssetServer,
+    objects: &'a ObjectBuffer,
+    pipelines: &'a PipelineCache,
+    draws: impl Iterator<Item = (usize, &'b DrawItem, Option<PipelineId>)>,
+) {
+    let mut current = None;
+    for (index, item, id) in draws {
+        if index >= objects.count() {
+            continue;
+        }
+        let Some(renderable) = assets.get(item.mesh) else {
+            continue;
+        };
+        let Some((id, pipeline)) = id.and_then(|id| Some((id, pipelines.get(id)?))) else {
+            continue;
+        };
+        // mostly sorted by pipeline, set when it changes
+        if current != Some(id) {
+            pass.set_pipeline(pipeline);
+            pass.set_push_constants(
+                wgpu::ShaderStages::VERTEX,
+                0,
+                bytemuck::cast_slice(&[camera_mvp]),
+            );
+            current = Some(id);
+        }
+        objects.bind(pass, 0, index);
+        pass.set_vertex_buffer(0, renderable.vertexbuffer.as_ref().unwrap().0.slice(..));
+        pass.set_index_buffer(
+            renderable.indexbuffer.as_ref().unwrap().0.slice(..),
+            renderable.indexbuffer.as_ref().unwrap().1,
+        );
+        pass.draw_indexed(0..renderable.indexbuffer.as_ref().unwrap().2, 0, 0..1);
     }
 }
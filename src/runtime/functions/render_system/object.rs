@@ -0,0 +1,153 @@
+use crate::runtime::core::mathematics::Matrix4;
+
+/// Per draw data of the basic shader, see `@group(0)` in `basic.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ObjectUniform {
+    pub model: Matrix4,
+    pub base_color: [f32; 4],
+    /// Added to the color, the material glows without lights.
+    pub emissive: [f32; 3],
+    /// Written by `ObjectBuffer::upload`, the slot index plus one, zero is the background.
+    pub id: u32,
+    /// Masked materials discard fragments with a lower alpha.
+    pub alpha_cutoff: f32,
+    _padding: [u32; 3],
+}
+
+impl ObjectUniform {
+    pub fn new(model: Matrix4, base_color: [f32; 4]) -> Self {
+        ObjectUniform {
+            model,
+            base_color,
+            emissive: [0.0; 3],
+            id: 0,
+            alpha_cutoff: 0.5,
+            _padding: [0; 3],
+        }
+    }
+}
+
+const INITIAL_CAPACITY: usize = 64;
+
+/// One uniform buffer holding every object, each draw binds its slot with a
+/// dynamic offset. Slots follow the order of the render queue.
+pub struct ObjectBuffer {
+    layout: wgpu::BindGroupLayout,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    // dynamic offsets have to be multiples of the uniform offset alignment
+    stride: u64,
+    capacity: usize,
+    count: usize,
+}
+
+impl ObjectBuffer {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Object Bindgroup Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<ObjectUniform>() as _
+                    ),
+                },
+                count: None,
+            }],
+        });
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+        let stride = (std::mem::size_of::<ObjectUniform>() as u64).next_multiple_of(alignment);
+        let (buffer, bind_group) = create_buffer(device, &layout, stride, INITIAL_CAPACITY);
+        ObjectBuffer {
+            layout,
+            buffer,
+            bind_group,
+            stride,
+            capacity: INITIAL_CAPACITY,
+            count: 0,
+        }
+    }
+
+    #[inline]
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    /// Number of objects uploaded.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Replaces the contents with `objects`, the buffer grows as needed.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        objects: &[ObjectUniform],
+    ) {
+        if objects.len() > self.capacity {
+            self.capacity = objects.len().next_power_of_two();
+            (self.buffer, self.bind_group) =
+                create_buffer(device, &self.layout, self.stride, self.capacity);
+        }
+        self.count = objects.len();
+        if objects.is_empty() {
+            return;
+        }
+        let mut contents = vec![0u8; objects.len() * self.stride as usize];
+        for (index, (slot, object)) in contents
+            .chunks_exact_mut(self.stride as usize)
+            .zip(objects)
+            .enumerate()
+        {
+            let object = ObjectUniform {
+                id: index as u32 + 1,
+                ..*object
+            };
+            slot[..std::mem::size_of::<ObjectUniform>()]
+                .copy_from_slice(bytemuck::bytes_of(&object));
+        }
+        queue.write_buffer(&self.buffer, 0, &contents);
+    }
+
+    /// Binds the slot of object `index` to `group`.
+    pub fn bind<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, group: u32, index: usize) {
+        pass.set_bind_group(
+            group,
+            &self.bind_group,
+            &[(index as u64 * self.stride) as u32],
+        );
+    }
+}
+
+fn create_buffer(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    stride: u64,
+    capacity: usize,
+) -> (wgpu::Buffer, wgpu::BindGroup) {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Object Buffer"),
+        size: stride * capacity as u64,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Object Bindgroup"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: &buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(std::mem::size_of::<ObjectUniform>() as _),
+            }),
+        }],
+    });
+    (buffer, bind_group)
+}
@@ -0,0 +1,373 @@
+use crate::runtime::resources::asset_system::{AssetKey, AssetServer, Handle};
+
+use super::{
+    font::{self, FontAtlas, ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH, LINE_HEIGHT},
+    shader::{create_or_embedded, validated, ShaderOrigin, OVERLAY_SHADER},
+    texture::Texture,
+};
+
+pub type Color = [f32; 4];
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OverlayVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: Color,
+}
+
+/// Screen space quads drawn on top of the frame, positions in window pixels
+/// from the top left corner.
+#[derive(Debug, Default)]
+pub struct OverlayBatch {
+    pub vertices: Vec<OverlayVertex>,
+}
+
+impl OverlayBatch {
+    #[inline]
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        self.quad([x, y, x + width, y + height], font::solid_uv(), color);
+    }
+
+    /// A segment `thickness` pixels wide.
+    pub fn line(&mut self, from: [f32; 2], to: [f32; 2], thickness: f32, color: Color) {
+        let [dx, dy] = [to[0] - from[0], to[1] - from[1]];
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0.0 {
+            return;
+        }
+        let [nx, ny] = [
+            -dy / length * thickness * 0.5,
+            dx / length * thickness * 0.5,
+        ];
+        let a = [from[0] + nx, from[1] + ny];
+        let b = [from[0] - nx, from[1] - ny];
+        let c = [to[0] - nx, to[1] - ny];
+        let d = [to[0] + nx, to[1] + ny];
+        self.triangle([a, b, c], color);
+        self.triangle([c, d, a], color);
+    }
+
+    pub fn triangle(&mut self, points: [[f32; 2]; 3], color: Color) {
+        let [u_min, v_min, u_max, v_max] = font::solid_uv();
+        let uv = [(u_min + u_max) * 0.5, (v_min + v_max) * 0.5];
+        self.vertices.extend(points.map(|position| OverlayVertex {
+            position,
+            uv,
+            color,
+        }));
+    }
+
+    /// Draws `text` with the built-in font, every font pixel `scale` window pixels wide.
+    ///
+    /// `\n` starts a new line, returns the size the text covers.
+    pub fn text(&mut self, x: f32, y: f32, scale: f32, color: Color, text: &str) -> (f32, f32) {
+        let (mut column, mut line, mut max_columns) = (0, 0, 0);
+        for character in text.chars() {
+            if character == '\n' {
+                line += 1;
+                column = 0;
+                continue;
+            }
+            if character != ' ' {
+                let left = x + (column * ADVANCE) as f32 * scale;
+                let top = y + (line * LINE_HEIGHT) as f32 * scale;
+                self.quad(
+                    [
+                        left,
+                        top,
+                        left + GLYPH_WIDTH as f32 * scale,
+                        top + GLYPH_HEIGHT as f32 * scale,
+                    ],
+                    font::glyph_uv(character),
+                    color,
+                );
+            }
+            column += 1;
+            max_columns = max_columns.max(column);
+        }
+        (
+            (max_columns * ADVANCE) as f32 * scale,
+            ((line + 1) * LINE_HEIGHT) as f32 * scale,
+        )
+    }
+
+    fn quad(&mut self, [left, top, right, bottom]: [f32; 4], uv: [f32; 4], color: Color) {
+        let [u_min, v_min, u_max, v_max] = uv;
+        let vertex = |x, y, u, v| OverlayVertex {
+            position: [x, y],
+            uv: [u, v],
+            color,
+        };
+        self.vertices.extend_from_slice(&[
+            vertex(left, top, u_min, v_min),
+            vertex(left, bottom, u_min, v_max),
+            vertex(right, bottom, u_max, v_max),
+            vertex(right, bottom, u_max, v_max),
+            vertex(right, top, u_max, v_min),
+            vertex(left, top, u_min, v_min),
+        ]);
+    }
+}
+
+/// Draws `OverlayBatch`es with alpha blending in a pass that loads the frame.
+pub struct OverlayRenderer {
+    pipeline: wgpu::RenderPipeline,
+    // kept to rebuild the pipeline when the shader changes
+    layout: wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    bind_group: wgpu::BindGroup,
+    // keeps the font texture of the bind group alive
+    _atlas_texture: Handle<Texture>,
+    vertex_buffer: wgpu::Buffer,
+    // in vertices
+    vertex_capacity: usize,
+}
+
+const INITIAL_VERTEX_CAPACITY: usize = 6 * 1024;
+
+impl OverlayRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        assets: &mut AssetServer,
+    ) -> Self {
+        let atlas = FontAtlas::new();
+        let atlas_texture = assets.get_or_add(AssetKey::hash_of(&atlas.pixels), || {
+            Texture::with_data(
+                device,
+                queue,
+                "Font Atlas",
+                [atlas.width, atlas.height],
+                wgpu::TextureFormat::R8Unorm,
+                &atlas.pixels,
+            )
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Font Sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Overlay Bindgroup Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Overlay Bindgroup"),
+            layout: &bg_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &assets.get(&atlas_texture).unwrap().view,
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[&bg_layout],
+            push_constant_ranges: &[wgpu::PushConstantRange {
+                stages: wgpu::ShaderStages::VERTEX,
+                range: 0..(std::mem::size_of::<[f32; 2]>() as u32),
+            }],
+        });
+        let pipeline = create_or_embedded(device, |origin| {
+            let source = origin.load(OVERLAY_SHADER.name)?;
+            Ok(create_pipeline(device, &layout, format, &source))
+        });
+
+        OverlayRenderer {
+            pipeline,
+            layout,
+            format,
+            bind_group,
+            _atlas_texture: atlas_texture,
+            vertex_buffer: create_vertex_buffer(device, INITIAL_VERTEX_CAPACITY),
+            vertex_capacity: INITIAL_VERTEX_CAPACITY,
+        }
+    }
+
+    /// Rebuilds the pipeline from `overlay.wgsl` on disk, the current one stays when it does not validate.
+    pub fn reload_shader(&mut self, device: &wgpu::Device) -> Result<(), String> {
+        let source = ShaderOrigin::Disk.load(OVERLAY_SHADER.name)?;
+        self.pipeline = validated(device, || {
+            create_pipeline(device, &self.layout, self.format, &source)
+        })?;
+        Ok(())
+    }
+
+    /// Records a pass drawing `batches` over `view` in order, the buffer grows as needed.
+    ///
+    /// All overlays of a frame have to go through one call, they share the vertex buffer.
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        screen_size: [f32; 2],
+        batches: &[&OverlayBatch],
+    ) {
+        let vertex_count: usize = batches.iter().map(|batch| batch.vertices.len()).sum();
+        if vertex_count == 0 {
+            return;
+        }
+        if vertex_count > self.vertex_capacity {
+            self.vertex_capacity = vertex_count.next_power_of_two();
+            self.vertex_buffer = create_vertex_buffer(device, self.vertex_capacity);
+        }
+        let mut offset = 0;
+        for batch in batches.iter().filter(|batch| !batch.vertices.is_empty()) {
+            queue.write_buffer(
+                &self.vertex_buffer,
+                (offset * std::mem::size_of::<OverlayVertex>()) as u64,
+                bytemuck::cast_slice(&batch.vertices),
+            );
+            offset += batch.vertices.len();
+        }
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Overlay Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_push_constants(
+            wgpu::ShaderStages::VERTEX,
+            0,
+            bytemuck::cast_slice(&screen_size),
+        );
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.draw(0..vertex_count as u32, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    source: &str,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Overlay Shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Overlay Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<OverlayVertex>() as _,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![
+                    0 => Float32x2,
+                    1 => Float32x2,
+                    2 => Float32x4
+                ],
+            }],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Overlay Vertex Buffer"),
+        size: (capacity * std::mem::size_of::<OverlayVertex>()) as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::OverlayBatch;
+
+    #[test]
+    fn text_layout() {
+        let mut batch = OverlayBatch::default();
+        let (width, height) = batch.text(10.0, 20.0, 2.0, [1.0; 4], "FPS 60\nok");
+        // spaces emit no quad
+        assert_eq!(batch.vertices.len(), 7 * 6);
+        assert_eq!((width, height), (6.0 * 6.0 * 2.0, 2.0 * 10.0 * 2.0));
+        assert_eq!(batch.vertices[0].position, [10.0, 20.0]);
+        let second_line = &batch.vertices[5 * 6];
+        assert_eq!(second_line.position, [10.0, 20.0 + 10.0 * 2.0]);
+
+        batch.clear();
+        batch.rect(0.0, 0.0, 4.0, 4.0, [0.0, 0.0, 0.0, 0.5]);
+        assert_eq!(batch.vertices.len(), 6);
+        assert_eq!(batch.vertices[2].position, [4.0, 4.0]);
+
+        batch.clear();
+        batch.line([0.0, 0.0], [10.0, 0.0], 2.0, [1.0; 4]);
+        let positions: Vec<_> = batch.vertices.iter().map(|v| v.position).collect();
+        assert_eq!(
+            positions,
+            [
+                [0.0, 1.0],
+                [0.0, -1.0],
+                [10.0, -1.0],
+                [10.0, -1.0],
+                [10.0, 1.0],
+                [0.0, 1.0]
+            ]
+        );
+    }
+}
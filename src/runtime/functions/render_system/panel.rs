@@ -0,0 +1,46 @@
+use crate::runtime::functions::gui_system::{ui::Ui, Panel, PanelContext};
+
+use super::fps_manager::FpsManager;
+
+/// Frame timings and the renderer toggles.
+pub struct RenderPanel;
+
+impl Panel for RenderPanel {
+    fn title(&self) -> &str {
+        "Renderer"
+    }
+
+    fn ui(&mut self, ui: &mut Ui, context: &mut PanelContext) {
+        let render_manager = &mut *context.render_manager;
+        let fps_manager = context.scene_manager.world.resource::<FpsManager>();
+        let stats = fps_manager.stats();
+        ui.value("FPS", fps_manager.get_fps());
+        ui.value("Frame", format!("{:.2} ms", stats.average_ms));
+        if let Some(timings) = render_manager.profiler.latest() {
+            ui.value("Encode", format!("{:.2} ms", timings.encode_ms));
+            for pass in timings.gpu_passes.iter() {
+                ui.value(
+                    &format!("GPU {}", pass.name),
+                    format!("{:.2} ms", pass.gpu_ms),
+                );
+            }
+        }
+        ui.separator();
+
+        let mut wireframe = render_manager.wireframe();
+        if ui.checkbox("Wireframe", &mut wireframe) {
+            render_manager.toggle_wireframe();
+        }
+        let mut debug_overlay = render_manager.debug_overlay.visible();
+        if ui.checkbox("Debug overlay", &mut debug_overlay) {
+            render_manager.toggle_debug_overlay();
+        }
+        let present_mode = render_manager.gpu_context.surface_config.present_mode;
+        if ui.button(&format!("Present: {:?}##present_mode", present_mode)) {
+            render_manager.cycle_present_mode();
+        }
+        if ui.button("Screenshot") {
+            render_manager.request_screenshot();
+        }
+    }
+}
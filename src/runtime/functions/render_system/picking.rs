@@ -0,0 +1,372 @@
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    Arc,
+};
+
+use crate::runtime::functions::scene_system::graph::NodeHandle;
+
+use super::{draw_list::DrawList, pipeline_cache::DEPTH_FORMAT};
+
+pub const ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+// a copied row has to be aligned, even for a single pixel
+const READBACK_SIZE: u64 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64;
+
+const MAP_PENDING: u8 = 0;
+const MAP_READY: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+/// What was under a pixel when it was picked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PickResult {
+    pub pixel: [u32; 2],
+    /// The node drawn there, `None` for the background.
+    pub node: Option<NodeHandle>,
+}
+
+impl PickResult {
+    /// `nodes` are the draws of the picked frame, ids are their index plus one.
+    fn from_id(pixel: [u32; 2], id: u32, nodes: &[NodeHandle]) -> Self {
+        PickResult {
+            pixel,
+            node: id
+                .checked_sub(1)
+                .and_then(|index| nodes.get(index as usize).copied()),
+        }
+    }
+}
+
+struct InFlight {
+    pixel: [u32; 2],
+    // the draw list's nodes as the id pass drew them
+    nodes: Vec<NodeHandle>,
+    mapped: Arc<AtomicU8>,
+}
+
+struct IdTarget {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    // the nearest draw keeps its id, whatever the draw order
+    depth: wgpu::TextureView,
+    size: [u32; 2],
+}
+
+/// Renders object ids into an `R32Uint` target on frames with a pick request and
+/// reads the requested pixel back without stalling, the result arrives a frame or more later.
+///
+/// One request is in flight at a time, newer requests replace a waiting one.
+pub struct ObjectPicker {
+    target: Option<IdTarget>,
+    readback: wgpu::Buffer,
+    requested: Option<[u32; 2]>,
+    // the pixel copied into `readback` and the state of its mapping
+    in_flight: Option<InFlight>,
+    mapping: bool,
+    result: Option<PickResult>,
+}
+
+impl ObjectPicker {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Picking Readback Buffer"),
+            size: READBACK_SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        ObjectPicker {
+            target: None,
+            readback,
+            requested: None,
+            in_flight: None,
+            mapping: false,
+            result: None,
+        }
+    }
+
+    /// Asks for the object under `pixel`, in surface pixels.
+    pub fn request(&mut self, pixel: [u32; 2]) {
+        self.requested = Some(pixel);
+    }
+
+    /// The latest finished pick, once.
+    pub fn take_result(&mut self) -> Option<PickResult> {
+        self.result.take()
+    }
+
+    /// Takes a waiting request if none is in flight, returns whether the id
+    /// pass has to be recorded this frame, see `begin_pass` and `copy_pixel`.
+    pub fn begin(&mut self, device: &wgpu::Device, size: [u32; 2], draw_list: &DrawList) -> bool {
+        if self.in_flight.is_some() {
+            return false;
+        }
+        let Some(pixel) = self.requested.take() else {
+            return false;
+        };
+        if pixel[0] >= size[0] || pixel[1] >= size[1] {
+            self.result = Some(PickResult::from_id(pixel, 0, &[]));
+            return false;
+        }
+        if self.target.as_ref().map(|target| target.size) != Some(size) {
+            self.target = Some(create_target(device, size));
+        }
+        self.in_flight = Some(InFlight {
+            pixel,
+            nodes: draw_list.nodes(),
+            mapped: Arc::new(AtomicU8::new(MAP_PENDING)),
+        });
+        true
+    }
+
+    /// The id pass, the scene draws go into it with pipelines of `PipelineKey::picking`.
+    pub fn begin_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::RenderPass<'a> {
+        let target = self.target.as_ref().expect("Picking pass without a target");
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Picking Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &target.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    // zero ids, the background
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &target.depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
+        })
+    }
+
+    /// Copies the requested pixel out of the id target, after the id pass.
+    pub fn copy_pixel(&self, encoder: &mut wgpu::CommandEncoder) {
+        let (Some(target), Some(InFlight { pixel, .. })) =
+            (self.target.as_ref(), self.in_flight.as_ref())
+        else {
+            return;
+        };
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &target.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: pixel[0],
+                    y: pixel[1],
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(READBACK_SIZE as u32),
+                    rows_per_image: Some(1),
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Starts mapping the copy once it was submitted and polls for it,
+    /// call after every submit.
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        let Some(in_flight) = self.in_flight.as_ref() else {
+            return;
+        };
+        let mapped = &in_flight.mapped;
+        if !self.mapping {
+            let mapped = mapped.clone();
+            self.readback
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    let state = match result {
+                        Ok(_) => MAP_READY,
+                        Err(err) => {
+                            log::error!("Picking readback failed: {}", err);
+                            MAP_FAILED
+                        }
+                    };
+                    mapped.store(state, Ordering::Release);
+                });
+            self.mapping = true;
+        }
+        device.poll(wgpu::Maintain::Poll);
+        match mapped.load(Ordering::Acquire) {
+            MAP_PENDING => return,
+            MAP_FAILED => {
+                self.in_flight = None;
+                self.mapping = false;
+                return;
+            }
+            _ => {}
+        }
+        let id = {
+            let data = self.readback.slice(..).get_mapped_range();
+            u32::from_ne_bytes([data[0], data[1], data[2], data[3]])
+        };
+        self.readback.unmap();
+        self.result = Some(PickResult::from_id(in_flight.pixel, id, &in_flight.nodes));
+        self.in_flight = None;
+        self.mapping = false;
+    }
+}
+
+fn create_target(device: &wgpu::Device, size: [u32; 2]) -> IdTarget {
+    let create_texture = |label, format, usage| {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        })
+    };
+    let texture = create_texture(
+        "Object Id Target",
+        ID_FORMAT,
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+    );
+    let depth = create_texture(
+        "Object Id Depth",
+        DEPTH_FORMAT,
+        wgpu::TextureUsages::RENDER_ATTACHMENT,
+    );
+    IdTarget {
+        view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        texture,
+        depth: depth.create_view(&wgpu::TextureViewDescriptor::default()),
+        size,
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::ObjectPicker;
+    use crate::runtime::{
+        core::mathematics::{transform::Transform, Matrix4},
+        functions::{
+            render_system::{
+                draw_list::{DrawItem, DrawList, MaterialState},
+                object::{ObjectBuffer, ObjectUniform},
+                pipeline_cache::{PipelineCache, PipelineKey},
+                shader::{ShaderCache, ShaderOrigin},
+            },
+            scene_system::{graph::SceneGraph, models::renderable::SceneRenderData},
+        },
+        resources::asset_system::AssetServer,
+    };
+    use wgpu::util::DeviceExt;
+
+    #[test]
+    fn picks_nearest() {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&Default::default()));
+        let Some(adapter) =
+            adapter.filter(|adapter| adapter.features().contains(wgpu::Features::PUSH_CONSTANTS))
+        else {
+            log::warn!("No adapter with push constants, not picking");
+            return;
+        };
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::PUSH_CONSTANTS,
+                limits: adapter.limits(),
+            },
+            None,
+        ))
+        .unwrap();
+
+        // two quads over the whole target, the near one drawn first
+        let mut graph = SceneGraph::default();
+        let mut assets = AssetServer::default();
+        let mesh = assets.add(SceneRenderData::default());
+        let mut draw = |name: &str, z: f32| DrawItem {
+            node: graph.spawn(name.to_owned(), None, Transform::default()),
+            mesh: mesh.id(),
+            object: ObjectUniform::new(
+                Transform {
+                    translation: [0.0, 0.0, z],
+                    ..Default::default()
+                }
+                .matrix(),
+                [1.0; 4],
+            ),
+            material: MaterialState::default(),
+        };
+        let draw_list = DrawList {
+            items: vec![draw("Near", 0.25), draw("Far", 0.75)],
+        };
+        let quad: [[f32; 3]; 6] = [
+            [-1.0, -1.0, 0.0],
+            [1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [-1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [-1.0, 1.0, 0.0],
+        ];
+        let vertices = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&quad),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let mut objects = ObjectBuffer::new(&device);
+        objects.upload(&device, &queue, &draw_list.objects());
+        let mut pipelines = PipelineCache::new(&device, objects.layout());
+        let key = PipelineKey::new(wgpu::TextureFormat::Bgra8UnormSrgb).picking();
+        let id = pipelines
+            .insert(
+                &device,
+                &mut ShaderCache::default(),
+                ShaderOrigin::Embedded,
+                &key,
+            )
+            .unwrap();
+
+        let mut picker = ObjectPicker::new(&device);
+        picker.request([8, 8]);
+        assert!(picker.begin(&device, [16, 16], &draw_list));
+        let mut encoder = device.create_command_encoder(&Default::default());
+        {
+            let mut pass = picker.begin_pass(&mut encoder);
+            pass.set_pipeline(pipelines.get(id).unwrap());
+            pass.set_push_constants(
+                wgpu::ShaderStages::VERTEX,
+                0,
+                bytemuck::cast_slice(&[Matrix4::identity()]),
+            );
+            pass.set_vertex_buffer(0, vertices.slice(..));
+            for index in 0..draw_list.items.len() {
+                objects.bind(&mut pass, 0, index);
+                pass.draw(0..quad.len() as u32, 0..1);
+            }
+        }
+        picker.copy_pixel(&mut encoder);
+        queue.submit(Some(encoder.finish()));
+
+        let result = (0..100).find_map(|_| {
+            picker.poll(&device);
+            device.poll(wgpu::Maintain::Wait);
+            picker.take_result()
+        });
+        assert_eq!(
+            result.and_then(|result| result.node),
+            Some(draw_list.items[0].node)
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use crate::runtime::{
+    core::mathematics::Matrix4,
+    functions::scene_system::{graph::AlphaMode, models::renderable::POSITION_ATTRIBUTES},
+};
+
+use super::{
+    picking::ID_FORMAT,
+    shader::{validated, ShaderCache, ShaderFeatures, ShaderKey, ShaderOrigin, BASIC_SHADER},
+};
+
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// How a pipeline tests and writes the depth target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DepthState {
+    pub format: wgpu::TextureFormat,
+    pub write: bool,
+    pub compare: wgpu::CompareFunction,
+}
+
+impl Default for DepthState {
+    fn default() -> Self {
+        DepthState {
+            format: DEPTH_FORMAT,
+            write: true,
+            compare: wgpu::CompareFunction::Less,
+        }
+    }
+}
+
+/// What a pipeline renders into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PipelineTarget {
+    /// Colors with `fs_main`.
+    Color(wgpu::TextureFormat),
+    /// Object ids with `fs_pick`, for the picking pass.
+    Ids,
+}
+
+/// Everything a pipeline of the scene shader is made of, draws with equal
+/// keys share a pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub vertex_layout: wgpu::VertexBufferLayout<'static>,
+    pub topology: wgpu::PrimitiveTopology,
+    /// Only set for strips, they are restarted at the maximum index.
+    pub strip_index_format: Option<wgpu::IndexFormat>,
+    /// `None` for double sided materials.
+    pub cull_mode: Option<wgpu::Face>,
+    pub alpha_mode: AlphaMode,
+    pub polygon_mode: wgpu::PolygonMode,
+    pub depth: Option<DepthState>,
+    pub sample_count: u32,
+    pub target: PipelineTarget,
+}
+
+impl PipelineKey {
+    /// Opaque single sided triangles of positions, filled, depth tested and not
+    /// multisampled, the key of most draws.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        PipelineKey {
+            vertex_layout: wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<[f32; 3]>() as _,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &POSITION_ATTRIBUTES,
+            },
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            cull_mode: Some(wgpu::Face::Back),
+            alpha_mode: AlphaMode::Opaque,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            depth: Some(DepthState::default()),
+            sample_count: 1,
+            target: PipelineTarget::Color(format),
+        }
+    }
+
+    /// The key drawing the same geometry into the picking target, which does not
+    /// blend. Every draw tests and writes depth so the nearest one is picked,
+    /// transparent ones included. Masked materials still discard their holes.
+    pub fn picking(&self) -> Self {
+        PipelineKey {
+            alpha_mode: match self.alpha_mode {
+                AlphaMode::Blend => AlphaMode::Opaque,
+                alpha_mode => alpha_mode,
+            },
+            polygon_mode: wgpu::PolygonMode::Fill,
+            depth: Some(DepthState::default()),
+            sample_count: 1,
+            target: PipelineTarget::Ids,
+            ..self.clone()
+        }
+    }
+
+    /// The permutation of the scene shader the pipeline is made of.
+    pub fn shader(&self) -> ShaderKey {
+        ShaderKey {
+            shader: BASIC_SHADER.name,
+            features: ShaderFeatures {
+                alpha_mask: self.alpha_mode == AlphaMode::Mask,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Index of a pipeline in a `PipelineCache`, in the order they were created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PipelineId(usize);
+
+/// The pipelines of the scene shader, created the first time their key is drawn.
+///
+/// A pipeline that does not validate is logged once and its draws are skipped.
+pub struct PipelineCache {
+    // shared by all, push constants stay set across pipelines
+    layout: wgpu::PipelineLayout,
+    ids: HashMap<PipelineKey, PipelineId>,
+    keys: Vec<PipelineKey>,
+    pipelines: Vec<Option<wgpu::RenderPipeline>>,
+}
+
+impl PipelineCache {
+    /// `objects` is the layout of the per draw data, see `ObjectBuffer`.
+    pub fn new(device: &wgpu::Device, objects: &wgpu::BindGroupLayout) -> Self {
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Scene Pipeline Layout"),
+            bind_group_layouts: &[objects],
+            push_constant_ranges: &[wgpu::PushConstantRange {
+                stages: wgpu::ShaderStages::VERTEX,
+                range: 0..(std::mem::size_of::<Matrix4>() as u32),
+            }],
+        });
+        PipelineCache {
+            layout,
+            ids: HashMap::new(),
+            keys: Vec::new(),
+            pipelines: Vec::new(),
+        }
+    }
+
+    /// The pipeline of `key`, created with the shaders of `origin` if it is new.
+    pub fn id(
+        &mut self,
+        device: &wgpu::Device,
+        shaders: &mut ShaderCache,
+        origin: ShaderOrigin,
+        key: &PipelineKey,
+    ) -> PipelineId {
+        if let Some(id) = self.ids.get(key) {
+            return *id;
+        }
+        let pipeline = self.create(device, shaders, origin, key);
+        if let Err(err) = &pipeline {
+            log::error!("Unable to create the pipeline of {:?}: {}", key, err);
+        }
+        self.push(key.clone(), pipeline.ok())
+    }
+
+    /// Like `id`, returning the error instead of caching the failure.
+    pub fn insert(
+        &mut self,
+        device: &wgpu::Device,
+        shaders: &mut ShaderCache,
+        origin: ShaderOrigin,
+        key: &PipelineKey,
+    ) -> Result<PipelineId, String> {
+        if let Some(id) = self.ids.get(key) {
+            return Ok(*id);
+        }
+        let pipeline = self.create(device, shaders, origin, key)?;
+        Ok(self.push(key.clone(), Some(pipeline)))
+    }
+
+    /// `None` when the pipeline failed to validate.
+    #[inline]
+    pub fn get(&self, id: PipelineId) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines.get(id.0).and_then(Option::as_ref)
+    }
+
+    /// Recreates every pipeline with the shaders of `origin`, like when they
+    /// changed on disk. Ids stay the same. Nothing is replaced when a pipeline
+    /// that worked does not validate anymore.
+    pub fn rebuild(
+        &mut self,
+        device: &wgpu::Device,
+        shaders: &mut ShaderCache,
+        origin: ShaderOrigin,
+    ) -> Result<(), String> {
+        let mut pipelines = Vec::with_capacity(self.pipelines.len());
+        for (key, pipeline) in self.keys.iter().zip(self.pipelines.iter()) {
+            match self.create(device, shaders, origin, key) {
+                Ok(rebuilt) => pipelines.push(Some(rebuilt)),
+                Err(_) if pipeline.is_none() => pipelines.push(None),
+                Err(err) => return Err(err),
+            }
+        }
+        self.pipelines = pipelines;
+        Ok(())
+    }
+
+    fn push(&mut self, key: PipelineKey, pipeline: Option<wgpu::RenderPipeline>) -> PipelineId {
+        let id = PipelineId(self.keys.len());
+        self.ids.insert(key.clone(), id);
+        self.keys.push(key);
+        self.pipelines.push(pipeline);
+        id
+    }
+
+    fn create(
+        &self,
+        device: &wgpu::Device,
+        shaders: &mut ShaderCache,
+        origin: ShaderOrigin,
+        key: &PipelineKey,
+    ) -> Result<wgpu::RenderPipeline, String> {
+        let shader = shaders.module(device, key.shader(), origin)?;
+        validated(device, || {
+            create_pipeline(device, &self.layout, shader, key)
+        })
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    key: &PipelineKey,
+) -> wgpu::RenderPipeline {
+    let (entry_point, format, blend) = match key.target {
+        PipelineTarget::Color(format) => {
+            let blend = match key.alpha_mode {
+                AlphaMode::Opaque | AlphaMode::Mask => wgpu::BlendState::REPLACE,
+                // `fs_main` premultiplies
+                AlphaMode::Blend => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            };
+            ("fs_main", format, Some(blend))
+        }
+        // integer targets do not blend
+        PipelineTarget::Ids => ("fs_pick", ID_FORMAT, None),
+    };
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Scene Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: std::slice::from_ref(&key.vertex_layout),
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: key.topology,
+            strip_index_format: key.strip_index_format,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: key.cull_mode,
+            unclipped_depth: false,
+            polygon_mode: key.polygon_mode,
+            conservative: false,
+        },
+        depth_stencil: key.depth.map(|depth| wgpu::DepthStencilState {
+            format: depth.format,
+            depth_write_enabled: depth.write,
+            depth_compare: depth.compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: key.sample_count,
+            ..Default::default()
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
+
+#[allow(unused_imports)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::{DepthState, PipelineKey, PipelineTarget};
+    use crate::runtime::functions::{
+        render_system::draw_list::MaterialState, scene_system::graph::AlphaMode,
+    };
+
+    #[test]
+    fn pipeline_keys() {
+        let format = wgpu::TextureFormat::Bgra8UnormSrgb;
+        let key = |material: MaterialState| PipelineKey {
+            cull_mode: (!material.double_sided).then_some(wgpu::Face::Back),
+            alpha_mode: material.alpha_mode,
+            ..PipelineKey::new(format)
+        };
+        let opaque = key(MaterialState::default());
+        let double_sided = key(MaterialState {
+            double_sided: true,
+            ..Default::default()
+        });
+        let blend = key(MaterialState {
+            alpha_mode: AlphaMode::Blend,
+            ..Default::default()
+        });
+        // equal keys share a pipeline
+        let keys: HashSet<_> = [&opaque, &double_sided, &blend, &key(Default::default())]
+            .into_iter()
+            .cloned()
+            .collect();
+        assert_eq!(keys.len(), 3);
+
+        // ids do not blend, the blended draw is picked like an opaque one
+        assert_eq!(blend.picking(), opaque.picking());
+        assert_ne!(double_sided.picking(), opaque.picking());
+        assert_eq!(opaque.picking().target, PipelineTarget::Ids);
+        // blended draws do not write depth, picking them still has to
+        let blend_over = PipelineKey {
+            depth: Some(DepthState {
+                write: false,
+                ..Default::default()
+            }),
+            ..blend.clone()
+        };
+        assert_eq!(blend_over.picking(), opaque.picking());
+        assert!(opaque.picking().depth.is_some_and(|depth| depth.write));
+    }
+}
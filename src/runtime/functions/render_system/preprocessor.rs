@@ -0,0 +1,271 @@
+use std::{collections::HashSet, fmt::Display};
+
+/// A shader with its includes resolved and its conditionals applied, as it is compiled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessedShader {
+    pub source: String,
+    /// The files read, the shader first.
+    pub files: Vec<String>,
+    // index into `files` and line of every line of `source`, both from the files
+    lines: Vec<(usize, usize)>,
+}
+
+impl ProcessedShader {
+    /// The file and line a line of `source` came from, lines count from 1.
+    pub fn location(&self, line: usize) -> Option<(&str, usize)> {
+        let (file, file_line) = *self.lines.get(line.checked_sub(1)?)?;
+        Some((&self.files[file], file_line))
+    }
+
+    /// Points the `name:line:column` locations of a compiler error at the file
+    /// the line is in, rather than into `source`.
+    pub fn map_error(&self, err: &str) -> String {
+        let mut mapped = String::with_capacity(err.len());
+        for line in err.lines() {
+            match line.split_once("┌─ ") {
+                Some((indent, location)) => {
+                    let mut parts = location.trim_end().rsplitn(3, ':');
+                    let column = parts.next();
+                    let source_line = parts.next().and_then(|line| line.parse().ok());
+                    match (column, source_line.and_then(|line| self.location(line))) {
+                        (Some(column), Some((file, file_line))) => {
+                            mapped += &format!("{}┌─ {}:{}:{}", indent, file, file_line, column)
+                        }
+                        _ => mapped += line,
+                    }
+                }
+                None => mapped += line,
+            }
+            mapped.push('\n');
+        }
+        mapped
+    }
+}
+
+/// A directive that could not be applied, with where it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreprocessError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for PreprocessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+/// An open `#ifdef` or `#ifndef`.
+struct Conditional {
+    line: usize,
+    // the enclosing lines are kept
+    outer: bool,
+    taken: bool,
+    has_else: bool,
+}
+
+struct Preprocessor<L> {
+    load: L,
+    defines: HashSet<String>,
+    shader: ProcessedShader,
+}
+
+/// Resolves the directives of a WGSL shader, each on a line of its own:
+///
+/// - `#include "name.wgsl"` inserts a file, loaded by `load` by name. Files
+///   are included once, later includes of the same file are skipped.
+/// - `#define NAME` defines a name for the rest of the shader, `defines` are defined from the start.
+/// - `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif` keep lines depending on defined names.
+pub fn preprocess(
+    shader: &str,
+    defines: &[&str],
+    load: impl FnMut(&str) -> Result<String, String>,
+) -> Result<ProcessedShader, PreprocessError> {
+    let mut preprocessor = Preprocessor {
+        load,
+        defines: defines.iter().map(|define| define.to_string()).collect(),
+        shader: ProcessedShader::default(),
+    };
+    let source = (preprocessor.load)(shader).map_err(|message| PreprocessError {
+        file: shader.to_owned(),
+        line: 0,
+        message,
+    })?;
+    preprocessor.file(shader, &source)?;
+    Ok(preprocessor.shader)
+}
+
+impl<L: FnMut(&str) -> Result<String, String>> Preprocessor<L> {
+    fn file(&mut self, name: &str, source: &str) -> Result<(), PreprocessError> {
+        let file = self.shader.files.len();
+        self.shader.files.push(name.to_owned());
+        let error = |line: usize, message: String| PreprocessError {
+            file: name.to_owned(),
+            line,
+            message,
+        };
+        let mut conditionals: Vec<Conditional> = Vec::new();
+        for (index, text) in source.lines().enumerate() {
+            let line = index + 1;
+            let active = match conditionals.last() {
+                Some(conditional) => conditional.outer && conditional.taken,
+                None => true,
+            };
+            let Some(directive) = text.trim().strip_prefix('#') else {
+                if active {
+                    self.shader.source += text;
+                    self.shader.source.push('\n');
+                    self.shader.lines.push((file, line));
+                }
+                continue;
+            };
+            let (directive, argument) = directive
+                .split_once(char::is_whitespace)
+                .map_or((directive, ""), |(directive, argument)| {
+                    (directive, argument.trim())
+                });
+            match directive {
+                "ifdef" | "ifndef" | "define" if argument.is_empty() => {
+                    return Err(error(line, format!("#{} without a name", directive)));
+                }
+                "ifdef" | "ifndef" => {
+                    let defined = self.defines.contains(argument);
+                    conditionals.push(Conditional {
+                        line,
+                        outer: active,
+                        taken: defined == (directive == "ifdef"),
+                        has_else: false,
+                    });
+                }
+                "else" => match conditionals.last_mut() {
+                    Some(conditional) if !conditional.has_else => {
+                        conditional.taken = !conditional.taken;
+                        conditional.has_else = true;
+                    }
+                    _ => return Err(error(line, "#else without #ifdef".to_owned())),
+                },
+                "endif" => {
+                    if conditionals.pop().is_none() {
+                        return Err(error(line, "#endif without #ifdef".to_owned()));
+                    }
+                }
+                _ if !active => {}
+                "define" => {
+                    self.defines.insert(argument.to_owned());
+                }
+                "include" => {
+                    let include = argument
+                        .strip_prefix('"')
+                        .and_then(|argument| argument.strip_suffix('"'))
+                        .ok_or_else(|| {
+                            error(
+                                line,
+                                format!("Expected a quoted file name, found {}", argument),
+                            )
+                        })?;
+                    if self.shader.files.iter().any(|file| file == include) {
+                        continue;
+                    }
+                    let source = (self.load)(include).map_err(|err| {
+                        error(line, format!("Unable to include {}: {}", include, err))
+                    })?;
+                    self.file(include, &source)?;
+                }
+                _ => return Err(error(line, format!("Unknown directive #{}", directive))),
+            }
+        }
+        match conditionals.pop() {
+            Some(conditional) => Err(error(conditional.line, "#ifdef without #endif".to_owned())),
+            None => Ok(()),
+        }
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{preprocess, PreprocessError};
+
+    #[test]
+    fn includes_and_defines() {
+        let files = HashMap::from([
+            (
+                "main.wgsl",
+                "#include \"view.wgsl\"\n#include \"lighting.wgsl\"\nfn main() {\n#ifdef ALPHA_MASK\n    discard;\n#else\n    shade();\n#endif\n}\n",
+            ),
+            ("view.wgsl", "struct View {}\n"),
+            (
+                "lighting.wgsl",
+                "#include \"view.wgsl\"\n#ifndef SKINNED\n#define STATIC\n#endif\n#ifdef STATIC\nfn shade() {}\n#endif\n",
+            ),
+        ]);
+        let load = |name: &str| {
+            files
+                .get(name)
+                .map(|source| source.to_string())
+                .ok_or_else(|| "not found".to_owned())
+        };
+
+        let shader = preprocess("main.wgsl", &[], load).unwrap();
+        assert_eq!(
+            shader.source,
+            "struct View {}\nfn shade() {}\nfn main() {\n    shade();\n}\n"
+        );
+        // included once
+        assert_eq!(shader.files, ["main.wgsl", "view.wgsl", "lighting.wgsl"]);
+        assert_eq!(shader.location(1), Some(("view.wgsl", 1)));
+        assert_eq!(shader.location(2), Some(("lighting.wgsl", 6)));
+        assert_eq!(shader.location(4), Some(("main.wgsl", 7)));
+        assert_eq!(shader.location(6), None);
+
+        let masked = preprocess("main.wgsl", &["ALPHA_MASK", "SKINNED"], load).unwrap();
+        assert_eq!(
+            masked.source,
+            "struct View {}\nfn main() {\n    discard;\n}\n"
+        );
+
+        // compiler errors point into the files
+        let err = "Shader parsing error: expected ';'\n   ┌─ wgsl:4:5\n   │\n";
+        assert_eq!(
+            shader.map_error(err),
+            "Shader parsing error: expected ';'\n   ┌─ main.wgsl:7:5\n   │\n"
+        );
+
+        let error = |files: &[(&'static str, &'static str)]| {
+            let files: HashMap<_, _> = files.iter().copied().collect();
+            preprocess("main.wgsl", &[], |name: &str| {
+                files
+                    .get(name)
+                    .map(|source| source.to_string())
+                    .ok_or_else(|| "not found".to_owned())
+            })
+            .unwrap_err()
+        };
+        assert_eq!(
+            error(&[("main.wgsl", "\n#include \"missing.wgsl\"\n")]),
+            PreprocessError {
+                file: "main.wgsl".to_owned(),
+                line: 2,
+                message: "Unable to include missing.wgsl: not found".to_owned(),
+            }
+        );
+        let unterminated = error(&[
+            ("main.wgsl", "#include \"view.wgsl\"\n"),
+            ("view.wgsl", "struct View {}\n#ifdef SKINNED\n"),
+        ]);
+        assert_eq!(
+            unterminated.to_string(),
+            "view.wgsl:2: #ifdef without #endif"
+        );
+        assert_eq!(error(&[("main.wgsl", "#endif\n")]).line, 1);
+        assert_eq!(error(&[("main.wgsl", "#pragma once\n")]).line, 1);
+        assert_eq!(
+            error(&[("main.wgsl", "\n#define\n")]).to_string(),
+            "main.wgsl:2: #define without a name"
+        );
+    }
+}
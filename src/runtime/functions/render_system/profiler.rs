@@ -0,0 +1,350 @@
+use std::{
+    collections::VecDeque,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Write the recorded frame timings to this file on exit, `.csv` or `.json`.
+pub const PERF_OUTPUT_ENV: &str = "RUCCOLO_PERF_OUTPUT";
+
+// ten minutes at 60 fps
+const MAX_RECORDED_FRAMES: usize = 60 * 60 * 10;
+const MAX_GPU_PASSES: usize = 16;
+
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct PassTiming {
+    pub name: String,
+    pub gpu_ms: f32,
+}
+
+/// CPU and GPU timings of one frame, in milliseconds.
+#[derive(serde::Serialize, Debug, Clone, Default, PartialEq)]
+pub struct FrameTimings {
+    pub frame: u64,
+    pub update_ms: f32,
+    pub encode_ms: f32,
+    pub submit_ms: f32,
+    /// Filled in a few frames late, once the timestamp queries have been read back.
+    pub gpu_passes: Vec<PassTiming>,
+}
+
+/// Collects per-frame timings, GPU pass times need `Features::TIMESTAMP_QUERY`.
+pub struct FrameProfiler {
+    frames: VecDeque<FrameTimings>,
+    current: FrameTimings,
+    gpu_timer: Option<GpuTimer>,
+    output: Option<PathBuf>,
+}
+
+impl FrameProfiler {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let gpu_timer = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| GpuTimer::new(device, queue.get_timestamp_period()));
+        if gpu_timer.is_none() {
+            log::info!("TIMESTAMP_QUERY unsupported, GPU pass timings are disabled");
+        }
+        FrameProfiler {
+            frames: VecDeque::new(),
+            current: FrameTimings::default(),
+            gpu_timer,
+            output: std::env::var_os(PERF_OUTPUT_ENV).map(PathBuf::from),
+        }
+    }
+
+    #[inline]
+    pub fn record_update(&mut self, duration: Duration) {
+        self.current.update_ms = duration.as_secs_f32() * 1000.0;
+    }
+
+    #[inline]
+    pub fn record_encode(&mut self, duration: Duration) {
+        self.current.encode_ms = duration.as_secs_f32() * 1000.0;
+    }
+
+    #[inline]
+    pub fn record_submit(&mut self, duration: Duration) {
+        self.current.submit_ms = duration.as_secs_f32() * 1000.0;
+    }
+
+    /// Marks the start of a GPU pass, call before beginning the pass.
+    pub fn begin_pass(&mut self, encoder: &mut wgpu::CommandEncoder, name: &str) {
+        if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+            gpu_timer.begin_pass(encoder, name);
+        }
+    }
+
+    /// Marks the end of the pass started last, call after the pass has been dropped.
+    pub fn end_pass(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+            gpu_timer.end_pass(encoder);
+        }
+    }
+
+    /// Copies this frame's timestamps for readback, call last before `finish`ing the encoder.
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+            gpu_timer.resolve(encoder);
+        }
+    }
+
+    /// Closes the frame, call after the submission.
+    pub fn end_frame(&mut self, device: &wgpu::Device) {
+        let frame = self.current.frame;
+        if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+            gpu_timer.start_readback(frame);
+            if let Some((measured_frame, passes)) = gpu_timer.poll_readback(device) {
+                match self
+                    .frames
+                    .iter_mut()
+                    .rev()
+                    .find(|timings| timings.frame == measured_frame)
+                {
+                    Some(timings) => timings.gpu_passes = passes,
+                    None if measured_frame == frame => self.current.gpu_passes = passes,
+                    None => {}
+                }
+            }
+        }
+
+        if self.frames.len() == MAX_RECORDED_FRAMES {
+            self.frames.pop_front();
+        }
+        let next = FrameTimings {
+            frame: frame + 1,
+            ..Default::default()
+        };
+        self.frames
+            .push_back(std::mem::replace(&mut self.current, next));
+    }
+
+    /// The most recent frame that has its GPU timings, if any are measured.
+    pub fn latest(&self) -> Option<&FrameTimings> {
+        if self.gpu_timer.is_some() {
+            self.frames
+                .iter()
+                .rev()
+                .find(|timings| !timings.gpu_passes.is_empty())
+        } else {
+            self.frames.back()
+        }
+    }
+
+    /// Writes the timings as `.json`, anything else is written as CSV.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::to_writer(file, &self.frames)?;
+        } else {
+            write_csv(file, self.frames.iter())?;
+        }
+        Ok(())
+    }
+
+    /// Saves to `RUCCOLO_PERF_OUTPUT` if it was set, call when the session ends.
+    pub fn finish(&mut self) {
+        let Some(path) = self.output.take() else {
+            return;
+        };
+        match self.save(&path) {
+            Ok(_) => log::info!("Saved {} frame timings to {:?}", self.frames.len(), path),
+            Err(err) => log::error!("Unable to save frame timings {:?}: {}", path, err),
+        }
+    }
+}
+
+fn write_csv<'a>(
+    mut writer: impl Write,
+    frames: impl Iterator<Item = &'a FrameTimings> + Clone,
+) -> std::io::Result<()> {
+    let mut pass_names: Vec<&str> = Vec::new();
+    for timings in frames.clone() {
+        for pass in timings.gpu_passes.iter() {
+            if !pass_names.contains(&pass.name.as_str()) {
+                pass_names.push(&pass.name);
+            }
+        }
+    }
+
+    write!(writer, "frame,update_ms,encode_ms,submit_ms")?;
+    for name in pass_names.iter() {
+        write!(writer, ",gpu_{}_ms", name)?;
+    }
+    writeln!(writer)?;
+    for timings in frames {
+        write!(
+            writer,
+            "{},{},{},{}",
+            timings.frame, timings.update_ms, timings.encode_ms, timings.submit_ms
+        )?;
+        for name in pass_names.iter() {
+            match timings.gpu_passes.iter().find(|pass| pass.name == *name) {
+                Some(pass) => write!(writer, ",{}", pass.gpu_ms)?,
+                None => write!(writer, ",")?,
+            }
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+/// Timestamp queries around passes, read back without stalling.
+///
+/// While a readback is in flight no new timestamps are written, so only every
+/// few frames gets GPU timings.
+struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    // nanoseconds per timestamp tick
+    period: f32,
+    passes: Vec<String>,
+    open_pass: bool,
+    in_flight: Option<(u64, Vec<String>)>,
+    mapped: Arc<AtomicBool>,
+}
+
+impl GpuTimer {
+    fn new(device: &wgpu::Device, period: f32) -> Self {
+        let query_count = (MAX_GPU_PASSES * 2) as u32;
+        let size = query_count as u64 * std::mem::size_of::<u64>() as u64;
+        GpuTimer {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("Timestamp Queries"),
+                ty: wgpu::QueryType::Timestamp,
+                count: query_count,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Timestamp Resolve Buffer"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Timestamp Readback Buffer"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            period,
+            passes: Vec::new(),
+            open_pass: false,
+            in_flight: None,
+            mapped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    #[inline]
+    fn recording(&self) -> bool {
+        self.in_flight.is_none()
+    }
+
+    fn begin_pass(&mut self, encoder: &mut wgpu::CommandEncoder, name: &str) {
+        if !self.recording() || self.open_pass || self.passes.len() == MAX_GPU_PASSES {
+            return;
+        }
+        encoder.write_timestamp(&self.query_set, (self.passes.len() * 2) as u32);
+        self.passes.push(name.to_owned());
+        self.open_pass = true;
+    }
+
+    fn end_pass(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.open_pass {
+            return;
+        }
+        encoder.write_timestamp(&self.query_set, (self.passes.len() * 2 - 1) as u32);
+        self.open_pass = false;
+    }
+
+    fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.recording() || self.passes.is_empty() {
+            return;
+        }
+        let query_count = (self.passes.len() * 2) as u32;
+        encoder.resolve_query_set(&self.query_set, 0..query_count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            query_count as u64 * std::mem::size_of::<u64>() as u64,
+        );
+    }
+
+    fn start_readback(&mut self, frame: u64) {
+        if !self.recording() || self.passes.is_empty() {
+            return;
+        }
+        let passes = std::mem::take(&mut self.passes);
+        let size = (passes.len() * 2 * std::mem::size_of::<u64>()) as u64;
+        let mapped = self.mapped.clone();
+        self.readback_buffer
+            .slice(..size)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                mapped.store(result.is_ok(), Ordering::Release)
+            });
+        self.in_flight = Some((frame, passes));
+    }
+
+    fn poll_readback(&mut self, device: &wgpu::Device) -> Option<(u64, Vec<PassTiming>)> {
+        self.in_flight.as_ref()?;
+        device.poll(wgpu::Maintain::Poll);
+        if !self.mapped.swap(false, Ordering::Acquire) {
+            return None;
+        }
+        let (frame, passes) = self.in_flight.take()?;
+        let size = (passes.len() * 2 * std::mem::size_of::<u64>()) as u64;
+        let timings = {
+            let data = self.readback_buffer.slice(..size).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            passes
+                .into_iter()
+                .zip(timestamps.chunks(2))
+                .map(|(name, range)| PassTiming {
+                    name,
+                    gpu_ms: range[1].wrapping_sub(range[0]) as f32 * self.period / 1_000_000.0,
+                })
+                .collect()
+        };
+        self.readback_buffer.unmap();
+        Some((frame, timings))
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{write_csv, FrameTimings, PassTiming};
+
+    #[test]
+    fn csv_output() {
+        let frames = [
+            FrameTimings {
+                frame: 0,
+                update_ms: 0.5,
+                encode_ms: 1.0,
+                submit_ms: 0.25,
+                gpu_passes: vec![PassTiming {
+                    name: "main".to_owned(),
+                    gpu_ms: 2.0,
+                }],
+            },
+            FrameTimings {
+                frame: 1,
+                ..Default::default()
+            },
+        ];
+        let mut output = Vec::new();
+        write_csv(&mut output, frames.iter()).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "frame,update_ms,encode_ms,submit_ms,gpu_main_ms\n0,0.5,1,0.25,2\n1,0,0,0,\n"
+        );
+    }
+}
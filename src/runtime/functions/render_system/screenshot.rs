@@ -0,0 +1,197 @@
+use std::{io::Write, path::PathBuf, sync::mpsc};
+
+const SCREENSHOT_DIR: &str = "screenshots";
+
+/// A frame copied into a mappable buffer, rows padded to `COPY_BYTES_PER_ROW_ALIGNMENT`.
+pub struct Screenshot {
+    buffer: wgpu::Buffer,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+}
+
+impl Screenshot {
+    /// A texture to draw the frame of a screenshot into, surfaces may not allow
+    /// copying out of their textures.
+    pub fn target(
+        device: &wgpu::Device,
+        size: [u32; 2],
+        format: wgpu::TextureFormat,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Screenshot Target"),
+            size: wgpu::Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    }
+
+    /// Records a copy of `texture` into `encoder`, the data is available after submission.
+    pub fn copy_from(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let width = texture.width();
+        let height = texture.height();
+        let unpadded_bytes_per_row = width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Screenshot Buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Screenshot {
+            buffer,
+            format,
+            width,
+            height,
+            padded_bytes_per_row,
+        }
+    }
+
+    /// Blocks until the copy is done and writes it as a binary PPM, to `screenshots/`
+    /// unless a path is given. Returns where it was written.
+    pub fn save(self, device: &wgpu::Device, path: Option<PathBuf>) -> Result<PathBuf, String> {
+        let swap_rb = match self.format {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            format => {
+                return Err(format!(
+                    "Screenshot of surface format {:?} is not supported",
+                    format
+                ))
+            }
+        };
+
+        let slice = self.buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|err| err.to_string())?
+            .map_err(|err| format!("Unable to read the screenshot: {}", err))?;
+
+        let mut pixels = Vec::with_capacity((self.width * self.height * 3) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                for pixel in row[..(self.width * 4) as usize].chunks(4) {
+                    if swap_rb {
+                        pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+                    } else {
+                        pixels.extend_from_slice(&pixel[..3]);
+                    }
+                }
+            }
+        }
+        self.buffer.unmap();
+
+        let path = path.unwrap_or_else(|| {
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis();
+            std::path::Path::new(SCREENSHOT_DIR).join(format!("ruccolo_{}.ppm", timestamp))
+        });
+        write_ppm(&path, self.width, self.height, &pixels)
+            .map_err(|err| format!("Unable to save screenshot {:?}: {}", path, err))?;
+        Ok(path)
+    }
+}
+
+fn write_ppm(
+    path: &std::path::Path,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write!(file, "P6\n{} {}\n255\n", width, height)?;
+    file.write_all(pixels)?;
+    file.flush()
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::Screenshot;
+
+    #[test]
+    fn saves_the_drawn_frame() {
+        let instance = wgpu::Instance::default();
+        let Some(adapter) = pollster::block_on(instance.request_adapter(&Default::default()))
+        else {
+            log::warn!("No adapter, not taking a screenshot");
+            return;
+        };
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&Default::default(), None)).unwrap();
+
+        // a width whose rows need padding
+        let format = wgpu::TextureFormat::Bgra8Unorm;
+        let target = Screenshot::target(&device, [3, 2], format);
+        let view = target.create_view(&Default::default());
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::RED),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        let screenshot = Screenshot::copy_from(&device, &mut encoder, &target, format);
+        queue.submit(Some(encoder.finish()));
+
+        let path = std::env::temp_dir().join(format!("ruccolo_shot_{}.ppm", std::process::id()));
+        assert_eq!(
+            screenshot.save(&device, Some(path.clone())),
+            Ok(path.clone())
+        );
+        let ppm = std::fs::read(&path).unwrap();
+        let header = b"P6\n3 2\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(&ppm[header.len()..], [255, 0, 0].repeat(6));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use super::preprocessor::preprocess;
+
+/// Where dev builds read shaders from, relative to the working directory.
+pub const SHADER_DIR: &str = "assets/shaders";
+
+/// A WGSL file of `assets/shaders`, embedded at compile time and read from
+/// disk in dev builds so it can be edited while the engine runs.
+#[derive(Debug, Clone, Copy)]
+pub struct ShaderFile {
+    pub name: &'static str,
+    embedded: &'static str,
+}
+
+macro_rules! shader_file {
+    ($name:literal) => {
+        ShaderFile {
+            name: $name,
+            embedded: include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/assets/shaders/",
+                $name
+            )),
+        }
+    };
+}
+
+pub const BASIC_SHADER: ShaderFile = shader_file!("basic.wgsl");
+pub const OVERLAY_SHADER: ShaderFile = shader_file!("overlay.wgsl");
+/// The camera of the frame, included by the scene shaders.
+pub const VIEW_SHADER: ShaderFile = shader_file!("view.wgsl");
+
+/// The files embedded for release builds, dev builds include any file of `SHADER_DIR`.
+pub const SHADER_FILES: [ShaderFile; 3] = [BASIC_SHADER, OVERLAY_SHADER, VIEW_SHADER];
+
+impl ShaderFile {
+    pub fn path(&self) -> PathBuf {
+        Path::new(SHADER_DIR).join(self.name)
+    }
+
+    pub fn find(name: &str) -> Option<ShaderFile> {
+        SHADER_FILES.into_iter().find(|file| file.name == name)
+    }
+}
+
+/// The shaders dev builds watch: the embedded ones and any other WGSL file of
+/// `SHADER_DIR` they may include.
+pub fn shader_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = SHADER_FILES.iter().map(ShaderFile::path).collect();
+    let entries = std::fs::read_dir(SHADER_DIR)
+        .into_iter()
+        .flatten()
+        .flatten();
+    for path in entries.map(|entry| entry.path()) {
+        if path.extension().is_some_and(|ext| ext == "wgsl") && !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Where shader files are read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderOrigin {
+    /// Any file of `assets/shaders`, only dev builds read it.
+    Disk,
+    /// The files as they were when the engine was built.
+    Embedded,
+}
+
+impl ShaderOrigin {
+    pub fn load(self, name: &str) -> Result<String, String> {
+        match self {
+            ShaderOrigin::Disk => {
+                // names are relative to the shader directory and stay inside it
+                let relative = Path::new(name);
+                if !relative
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)))
+                {
+                    return Err(format!("Shader file {} is outside {}", name, SHADER_DIR));
+                }
+                std::fs::read_to_string(Path::new(SHADER_DIR).join(relative))
+                    .map_err(|err| err.to_string())
+            }
+            ShaderOrigin::Embedded => ShaderFile::find(name)
+                .map(|file| file.embedded.to_owned())
+                .ok_or_else(|| format!("No shader file {}", name)),
+        }
+    }
+}
+
+/// Makes what `create` makes of the shaders on disk in dev builds, and of the
+/// embedded ones in release builds or when those on disk fail, so a broken file still starts.
+pub fn create_or_embedded<T>(
+    device: &wgpu::Device,
+    mut create: impl FnMut(ShaderOrigin) -> Result<T, String>,
+) -> T {
+    if cfg!(debug_assertions) {
+        match validated(device, || create(ShaderOrigin::Disk)).and_then(|created| created) {
+            Ok(created) => return created,
+            Err(err) => log::error!("Using the embedded shaders: {}", err),
+        }
+    }
+    validated(device, || create(ShaderOrigin::Embedded))
+        .and_then(|created| created)
+        .expect("Invalid embedded shaders")
+}
+
+/// Compile time features of a shader, each defines a name for `#ifdef`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ShaderFeatures {
+    /// `HAS_NORMAL_MAP`
+    pub normal_map: bool,
+    /// `ALPHA_MASK`, fragments below the alpha cutoff are discarded.
+    pub alpha_mask: bool,
+    /// `SKINNED`
+    pub skinned: bool,
+}
+
+impl ShaderFeatures {
+    pub fn defines(&self) -> Vec<&'static str> {
+        [
+            (self.normal_map, "HAS_NORMAL_MAP"),
+            (self.alpha_mask, "ALPHA_MASK"),
+            (self.skinned, "SKINNED"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, define)| enabled.then_some(define))
+        .collect()
+    }
+}
+
+/// A permutation of a shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShaderKey {
+    pub shader: &'static str,
+    pub features: ShaderFeatures,
+}
+
+/// Shader modules by permutation, each preprocessed and compiled once.
+#[derive(Debug, Default)]
+pub struct ShaderCache {
+    // with the origin of their files, a module of the other origin is not reused
+    modules: HashMap<ShaderKey, (ShaderOrigin, wgpu::ShaderModule)>,
+}
+
+impl ShaderCache {
+    /// The module of a permutation, made of `origin`'s files unless cached.
+    /// Errors of the preprocessor and of the compiler point into the files.
+    pub fn module(
+        &mut self,
+        device: &wgpu::Device,
+        key: ShaderKey,
+        origin: ShaderOrigin,
+    ) -> Result<&wgpu::ShaderModule, String> {
+        if self.modules.get(&key).map(|(cached, _)| *cached) != Some(origin) {
+            let shader = preprocess(key.shader, &key.features.defines(), |name| {
+                origin.load(name)
+            })
+            .map_err(|err| err.to_string())?;
+            let module = validated(device, || {
+                device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some(key.shader),
+                    source: wgpu::ShaderSource::Wgsl(shader.source.as_str().into()),
+                })
+            })
+            .map_err(|err| shader.map_error(&err))?;
+            self.modules.insert(key, (origin, module));
+        }
+        Ok(&self.modules[&key].1)
+    }
+}
+
+/// Runs `create`, returning the validation error of the shader modules and
+/// pipelines it creates instead of panicking on it.
+pub fn validated<T>(device: &wgpu::Device, create: impl FnOnce() -> T) -> Result<T, String> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let created = create();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(err) => Err(err.to_string()),
+        None => Ok(created),
+    }
+}
+
+/// The error and where it is from a shader error of `validated`, on one line for the overlay.
+pub fn error_summary(err: &str) -> String {
+    let mut lines = err.lines().map(str::trim);
+    let error = lines
+        .clone()
+        .find(|line| line.contains("error") && !line.starts_with("Validation Error"));
+    let location = lines.find_map(|line| line.strip_prefix("┌─"));
+    match (error, location) {
+        (Some(error), Some(location)) => format!("{} ({})", error, location.trim()),
+        (Some(error), None) => error.to_owned(),
+        _ => err.lines().next().unwrap_or_default().to_owned(),
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{
+        error_summary, shader_paths, ShaderFeatures, ShaderFile, ShaderOrigin, SHADER_FILES,
+    };
+    use crate::runtime::functions::render_system::preprocessor::preprocess;
+
+    #[test]
+    fn shader_files() {
+        // dev builds find the files they watch
+        for shader in SHADER_FILES {
+            assert!(
+                ShaderOrigin::Disk.load(shader.name).is_ok(),
+                "{:?} missing",
+                shader.path()
+            );
+        }
+        assert!(ShaderFile::find("missing.wgsl").is_none());
+        assert!(ShaderOrigin::Disk.load("missing.wgsl").is_err());
+        assert!(ShaderOrigin::Disk.load("../config/engine.json").is_err());
+        assert!(ShaderOrigin::Disk.load("/etc/hostname").is_err());
+        let watched = shader_paths();
+        assert!(SHADER_FILES
+            .iter()
+            .all(|shader| watched.contains(&shader.path())));
+        // every permutation of the embedded scene shader resolves
+        let all = ShaderFeatures {
+            normal_map: true,
+            alpha_mask: true,
+            skinned: true,
+        };
+        assert_eq!(all.defines(), ["HAS_NORMAL_MAP", "ALPHA_MASK", "SKINNED"]);
+        for features in [ShaderFeatures::default(), all] {
+            preprocess("basic.wgsl", &features.defines(), |name| {
+                ShaderOrigin::Embedded.load(name)
+            })
+            .unwrap();
+        }
+
+        let err = "Validation Error\n\nCaused by:\n    In Device::create_shader_module\n      \
+                   note: label = `Basic Shader`\n    \n\
+                   Shader 'Basic Shader' parsing error: expected ';', found '}'\n   \
+                   ┌─ wgsl:30:5\n   │\n30 │     }\n   │     ^ expected ';'\n";
+        assert_eq!(
+            error_summary(err),
+            "Shader 'Basic Shader' parsing error: expected ';', found '}' (wgsl:30:5)"
+        );
+        assert_eq!(error_summary("Device lost\nmore"), "Device lost");
+    }
+}
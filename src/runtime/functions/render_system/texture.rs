@@ -0,0 +1,45 @@
+use wgpu::util::DeviceExt;
+
+use crate::runtime::resources::asset_system::Asset;
+
+/// A sampled 2D texture with a view of all of it.
+#[derive(Debug)]
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl Asset for Texture {}
+
+impl Texture {
+    /// A texture of one mip level filled with `data`, tightly packed rows of `format`.
+    pub fn with_data(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        label: &str,
+        [width, height]: [u32; 2],
+        format: wgpu::TextureFormat,
+        data: &[u8],
+    ) -> Self {
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            data,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Texture { texture, view }
+    }
+}
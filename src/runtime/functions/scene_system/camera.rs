@@ -1,4 +1,4 @@
-use crate::runtime::core::mathematics::{cross, Array4, Matrix4};
+use crate::runtime::core::mathematics::{cross, ray::Ray, Array4, Matrix4};
 
 #[derive(Debug, Clone, Copy)]
 pub struct CameraInfo {
@@ -61,6 +61,32 @@ impl CameraInfo {
         }
     }
 
+    /// Moves back along the view direction until a sphere around `center` fits the view.
+    pub fn frame(&mut self, center: [f32; 3], radius: f32) {
+        // half angles of the view, from the scales of `projection_matrix`
+        let half_fov_y = (self.fov2.tan() * 0.5).abs().atan();
+        let half_fov_x = (self.fov2.tan() * self.aspect).abs().atan();
+        let distance = radius.max(self.znear) / half_fov_y.min(half_fov_x).sin();
+        let mut direction = self.lookat;
+        direction.normalize();
+        let [x, y, z] = center;
+        self.position = Array4::new([
+            x - direction.0[0] * distance,
+            y - direction.0[1] * distance,
+            z - direction.0[2] * distance,
+            1.0,
+        ]);
+    }
+
+    /// Blends from `previous` (alpha 0) to `self` (alpha 1).
+    pub fn interpolate(&self, previous: &CameraInfo, alpha: f32) -> CameraInfo {
+        CameraInfo {
+            position: previous.position + (self.position - previous.position) * alpha,
+            lookat: previous.lookat + (self.lookat - previous.lookat) * alpha,
+            ..*self
+        }
+    }
+
     #[inline]
     fn modelview_transform_matrix(&self) -> Matrix4 {
         let mut gt_perp = cross(&self.lookat, &self.updir);
@@ -120,6 +146,33 @@ impl CameraInfo {
     pub fn get_mvp(&self) -> Matrix4 {
         self.projection_matrix() * self.modelview_transform_matrix()
     }
+
+    /// Ray from the near plane through `cursor`, in window pixels from the top left,
+    /// unprojected with the inverse view-projection.
+    pub fn screen_ray(&self, cursor: [f32; 2], screen_size: [f32; 2]) -> Option<Ray> {
+        let inverse = self.get_mvp().inverse()?;
+        let x = cursor[0] / screen_size[0] * 2.0 - 1.0;
+        let y = 1.0 - cursor[1] / screen_size[1] * 2.0;
+        let unproject = |z: f32| {
+            let point = inverse * Array4::new([x, y, z, 1.0]);
+            point * (1.0 / point.0[3])
+        };
+        let near = unproject(-1.0);
+        Some(Ray::new(near, unproject(1.0) - near))
+    }
+
+    /// Window pixels of a world position, `None` behind the camera.
+    pub fn world_to_screen(&self, point: Array4, screen_size: [f32; 2]) -> Option<[f32; 2]> {
+        let clip = self.get_mvp() * Array4::new([point.0[0], point.0[1], point.0[2], 1.0]);
+        if clip.0[3] <= self.znear * 0.5 {
+            return None;
+        }
+        let [x, y] = [clip.0[0] / clip.0[3], clip.0[1] / clip.0[3]];
+        Some([
+            (x + 1.0) * 0.5 * screen_size[0],
+            (1.0 - y) * 0.5 * screen_size[1],
+        ])
+    }
 }
 
 #[allow(unused_imports)]
@@ -151,4 +204,25 @@ mod test {
         let transed_updir = transform * camera.updir;
         assert!(transed_updir.almost_eq(&Array4::new([0.0, 1.0, 0.0, 0.0])));
     }
+
+    #[test]
+    fn screen_ray() {
+        const SCREEN: [f32; 2] = [1280.0, 720.0];
+        let camera = CameraInfo::default();
+        let center = camera
+            .screen_ray([SCREEN[0] * 0.5, SCREEN[1] * 0.5], SCREEN)
+            .unwrap();
+        assert!((center.direction - camera.lookat).length() < 1e-4);
+
+        // points along a ray project back onto its cursor
+        let cursor = [300.0, 500.0];
+        let ray = camera.screen_ray(cursor, SCREEN).unwrap();
+        let [x, y] = camera.world_to_screen(ray.at(20.0), SCREEN).unwrap();
+        assert!((x - cursor[0]).abs() < 0.01 && (y - cursor[1]).abs() < 0.01);
+        // below the centre of the screen is below the camera
+        assert!(ray.direction.0[1] < 0.0);
+        assert!(camera
+            .world_to_screen(camera.position - camera.lookat, SCREEN)
+            .is_none());
+    }
 }
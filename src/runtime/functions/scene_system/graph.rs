@@ -0,0 +1,804 @@
+use crate::runtime::{
+    core::mathematics::{transform::Transform, Array4, Matrix4},
+    functions::render_system::{
+        draw_list::{DrawItem, DrawList, MaterialState},
+        object::ObjectUniform,
+        texture::Texture,
+    },
+    resources::asset_system::{Asset, AssetServer, Handle},
+};
+
+use super::models::{
+    gltf::{
+        GltfCamera, GltfCameraType, GltfLight, GltfLightType, GltfMaterial, GltfMaterialAlphaMode,
+    },
+    renderable::SceneRenderData,
+};
+
+/// Color of the boxes drawn for meshes still loading.
+pub const PLACEHOLDER_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+/// Refers to a node of a `SceneGraph`, stays valid while the node lives and
+/// never points at another node once it is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeHandle {
+    index: u32,
+    generation: u32,
+}
+
+/// A node of the scene graph, its components say what it is.
+#[derive(Debug, Clone)]
+pub struct SceneNode {
+    pub name: String,
+    parent: Option<NodeHandle>,
+    children: Vec<NodeHandle>,
+    // relative to the parent
+    transform: Transform,
+    // cached, up to date after `SceneGraph::update_transforms` unless `dirty`
+    world: Matrix4,
+    dirty: bool,
+    pub mesh: Option<Mesh>,
+    pub light: Option<Light>,
+    pub camera: Option<SceneCamera>,
+}
+
+impl SceneNode {
+    #[inline]
+    pub fn parent(&self) -> Option<NodeHandle> {
+        self.parent
+    }
+
+    #[inline]
+    pub fn children(&self) -> &[NodeHandle] {
+        &self.children
+    }
+
+    /// Relative to the parent, set it with `SceneGraph::set_transform`.
+    #[inline]
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+}
+
+/// How the alpha of the base color is used, glTF's `alphaMode`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AlphaMode {
+    #[default]
+    Opaque,
+    /// Fully opaque above `Material::alpha_cutoff`, not drawn below.
+    Mask,
+    Blend,
+}
+
+impl From<GltfMaterialAlphaMode> for AlphaMode {
+    fn from(alpha_mode: GltfMaterialAlphaMode) -> Self {
+        match alpha_mode {
+            GltfMaterialAlphaMode::Opaque => AlphaMode::Opaque,
+            GltfMaterialAlphaMode::Mask => AlphaMode::Mask,
+            GltfMaterialAlphaMode::Blend => AlphaMode::Blend,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    /// Back faces are drawn too.
+    pub double_sided: bool,
+    /// Decoded and uploaded with the model, not sampled yet.
+    pub base_color_texture: Option<Handle<Texture>>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            name: "Default".to_owned(),
+            base_color: [1.0; 4],
+            metallic: 1.0,
+            roughness: 1.0,
+            emissive: [0.0; 3],
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+            double_sided: false,
+            base_color_texture: None,
+        }
+    }
+}
+
+impl Asset for Material {}
+
+impl Material {
+    /// The color it is drawn with, opaque materials ignore the alpha.
+    pub fn draw_color(&self) -> [f32; 4] {
+        let [r, g, b, a] = self.base_color;
+        match self.alpha_mode {
+            AlphaMode::Opaque => [r, g, b, 1.0],
+            AlphaMode::Mask | AlphaMode::Blend => [r, g, b, a],
+        }
+    }
+
+    /// `textures` are the textures of the model by glTF texture index.
+    pub fn from_gltf(
+        index: usize,
+        material: &GltfMaterial,
+        textures: &[Option<Handle<Texture>>],
+    ) -> Self {
+        let default = Material::default();
+        let pbr = material.pb_metallic_roughness.as_ref();
+        Material {
+            name: material
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Material {}", index)),
+            base_color: pbr
+                .and_then(|pbr| pbr.base_color_factor)
+                .unwrap_or(default.base_color),
+            metallic: pbr
+                .and_then(|pbr| pbr.metallic_factor)
+                .unwrap_or(default.metallic),
+            roughness: pbr
+                .and_then(|pbr| pbr.roughness_factor)
+                .unwrap_or(default.roughness),
+            emissive: material.emissive_factor.unwrap_or(default.emissive),
+            alpha_mode: material.alpha_mode.unwrap_or_default().into(),
+            alpha_cutoff: material.alpha_cutoff.unwrap_or(default.alpha_cutoff),
+            double_sided: material.double_sided.unwrap_or(default.double_sided),
+            base_color_texture: pbr
+                .and_then(|pbr| pbr.base_color_texture.as_ref())
+                .and_then(|info| textures.get(info.index)?.clone()),
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    Directional,
+    Point,
+    /// Cone angles in radians.
+    Spot {
+        inner_cone: f32,
+        outer_cone: f32,
+    },
+}
+
+/// A `KHR_lights_punctual` light component, kept for editing, the renderer does not shade with it yet.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Light {
+    pub name: String,
+    pub kind: LightKind,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Infinite when `None`.
+    pub range: Option<f32>,
+}
+
+impl Light {
+    pub fn from_gltf(index: usize, light: &GltfLight) -> Self {
+        let kind = match light.light_type {
+            GltfLightType::Directional => LightKind::Directional,
+            GltfLightType::Point => LightKind::Point,
+            GltfLightType::Spot => {
+                let spot = light.spot.as_ref();
+                LightKind::Spot {
+                    inner_cone: spot.and_then(|spot| spot.inner_cone_angle).unwrap_or(0.0),
+                    outer_cone: spot
+                        .and_then(|spot| spot.outer_cone_angle)
+                        .unwrap_or(std::f32::consts::FRAC_PI_4),
+                }
+            }
+        };
+        Light {
+            name: light
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Light {}", index)),
+            kind,
+            color: light.color.unwrap_or([1.0; 3]),
+            intensity: light.intensity.unwrap_or(1.0),
+            range: light.range,
+        }
+    }
+}
+
+/// Places an entity in the scene, the glTF loader spawns one for every node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeLink {
+    pub node: NodeHandle,
+    /// Index of the glTF node it was loaded from.
+    pub gltf_node: usize,
+}
+
+/// One drawn part of a mesh, its GPU buffers and material are shared assets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshPrimitive {
+    pub mesh: Handle<SceneRenderData>,
+    /// The default material when `None`.
+    pub material: Option<Handle<Material>>,
+    pub triangles: u32,
+    /// In the node's space, the placeholder fills it until the mesh is uploaded.
+    pub bounds: Option<Bounds>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    pub primitives: Vec<MeshPrimitive>,
+    /// Of all primitives in the node's space, `None` when not known.
+    pub bounds: Option<Bounds>,
+}
+
+/// An axis aligned box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Bounds {
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            min: std::array::from_fn(|axis| self.min[axis].min(other.min[axis])),
+            max: std::array::from_fn(|axis| self.max[axis].max(other.max[axis])),
+        }
+    }
+
+    /// The box around this one after `matrix` is applied to it.
+    pub fn transformed(&self, matrix: Matrix4) -> Bounds {
+        let corners = (0..8).map(|corner| {
+            let pick = |axis: usize| match corner >> axis & 1 {
+                0 => self.min[axis],
+                _ => self.max[axis],
+            };
+            let point = matrix * Array4::new([pick(0), pick(1), pick(2), 1.0]);
+            Bounds {
+                min: [point.0[0], point.0[1], point.0[2]],
+                max: [point.0[0], point.0[1], point.0[2]],
+            }
+        });
+        corners
+            .reduce(|bounds, corner| bounds.union(&corner))
+            .unwrap()
+    }
+
+    pub fn center(&self) -> [f32; 3] {
+        std::array::from_fn(|axis| (self.min[axis] + self.max[axis]) * 0.5)
+    }
+
+    /// Half the diagonal, the radius of a sphere around the box.
+    pub fn radius(&self) -> f32 {
+        let squared: f32 = (0..3)
+            .map(|axis| (self.max[axis] - self.min[axis]) * 0.5)
+            .map(|half| half * half)
+            .sum();
+        squared.sqrt()
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Field of view in radians, the aspect ratio follows the viewport when `None`.
+    Perspective {
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        /// Infinite when `None`.
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+/// A camera placed by its node, looking down the node's -z.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct SceneCamera {
+    pub name: String,
+    pub projection: Projection,
+}
+
+impl SceneCamera {
+    /// `None` when the camera lacks the properties of its type.
+    pub fn from_gltf(index: usize, camera: &GltfCamera) -> Option<Self> {
+        let projection = match camera.camera_type {
+            GltfCameraType::Perspective => {
+                let perspective = camera.perspective.as_ref()?;
+                Projection::Perspective {
+                    yfov: perspective.yfov,
+                    aspect_ratio: perspective.aspect_ratio,
+                    znear: perspective.znear,
+                    zfar: perspective.zfar,
+                }
+            }
+            GltfCameraType::Orthographic => {
+                let orthographic = camera.orthographic.as_ref()?;
+                Projection::Orthographic {
+                    xmag: orthographic.xmag,
+                    ymag: orthographic.ymag,
+                    znear: orthographic.znear,
+                    zfar: orthographic.zfar,
+                }
+            }
+        };
+        Some(SceneCamera {
+            name: camera
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Camera {}", index)),
+            projection,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Slot {
+    generation: u32,
+    node: Option<SceneNode>,
+}
+
+/// Nodes with parent/child links and components.
+///
+/// Nodes live in generational slots addressed by `NodeHandle`. World transforms
+/// are cached and only recomputed below nodes whose transform changed, the
+/// renderer extracts its draw list from the graph every frame.
+#[derive(Debug, Default)]
+pub struct SceneGraph {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    roots: Vec<NodeHandle>,
+    /// A unit cube drawn in place of meshes still loading, see `MeshPrimitive::bounds`.
+    pub placeholder: Option<Handle<SceneRenderData>>,
+}
+
+impl SceneGraph {
+    pub fn spawn(
+        &mut self,
+        name: String,
+        parent: Option<NodeHandle>,
+        transform: Transform,
+    ) -> NodeHandle {
+        let node = SceneNode {
+            name,
+            parent,
+            children: Vec::new(),
+            transform,
+            world: Matrix4::identity(),
+            dirty: true,
+            mesh: None,
+            light: None,
+            camera: None,
+        };
+        let handle = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.node = Some(node);
+                NodeHandle {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    node: Some(node),
+                });
+                NodeHandle {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        };
+        match parent.and_then(|parent| self.get_mut(parent)) {
+            Some(parent) => parent.children.push(handle),
+            None => {
+                self.get_mut(handle).unwrap().parent = None;
+                self.roots.push(handle);
+            }
+        }
+        handle
+    }
+
+    /// Removes the node with its subtree, their handles become invalid.
+    pub fn remove(&mut self, handle: NodeHandle) {
+        if !self.contains(handle) {
+            return;
+        }
+        self.detach(handle);
+        let mut stack = vec![handle];
+        while let Some(handle) = stack.pop() {
+            let slot = &mut self.slots[handle.index as usize];
+            if let Some(node) = slot.node.take() {
+                stack.extend(node.children);
+                slot.generation += 1;
+                self.free.push(handle.index);
+            }
+        }
+    }
+
+    /// Removes all nodes, handles to them stay invalid.
+    pub fn clear(&mut self) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if slot.node.take().is_some() {
+                slot.generation += 1;
+                self.free.push(index as u32);
+            }
+        }
+        self.roots.clear();
+    }
+
+    #[inline]
+    pub fn contains(&self, handle: NodeHandle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    pub fn get(&self, handle: NodeHandle) -> Option<&SceneNode> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.node.as_ref())
+    }
+
+    pub fn get_mut(&mut self, handle: NodeHandle) -> Option<&mut SceneNode> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.node.as_mut())
+    }
+
+    /// Live nodes, attached or not.
+    #[inline]
+    pub fn node_count(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    #[inline]
+    pub fn roots(&self) -> &[NodeHandle] {
+        &self.roots
+    }
+
+    /// Replaces the node's local transform, its subtree moves along on the next update.
+    pub fn set_transform(&mut self, handle: NodeHandle, transform: Transform) {
+        if let Some(node) = self.get_mut(handle) {
+            node.transform = transform;
+            node.dirty = true;
+        }
+    }
+
+    /// Where the node sits among its siblings, `None` once it was detached.
+    pub fn sibling_index(&self, handle: NodeHandle) -> Option<usize> {
+        let siblings = match self.get(handle)?.parent {
+            Some(parent) => &self.get(parent)?.children,
+            None => &self.roots,
+        };
+        siblings.iter().position(|sibling| *sibling == handle)
+    }
+
+    /// The node and all its ancestors are still in the tree.
+    pub fn is_attached(&self, handle: NodeHandle) -> bool {
+        let mut current = handle;
+        loop {
+            if self.sibling_index(current).is_none() {
+                return false;
+            }
+            match self.get(current).and_then(|node| node.parent) {
+                Some(parent) => current = parent,
+                None => return true,
+            }
+        }
+    }
+
+    /// Takes the node with its subtree out of the tree, it stays alive and
+    /// remembers its parent so it can be attached again.
+    pub fn detach(&mut self, handle: NodeHandle) -> Option<usize> {
+        let position = self.sibling_index(handle)?;
+        match self.get(handle)?.parent {
+            Some(parent) => self.get_mut(parent)?.children.remove(position),
+            None => self.roots.remove(position),
+        };
+        Some(position)
+    }
+
+    /// Puts a detached node back under its parent at `position`.
+    pub fn attach(&mut self, handle: NodeHandle, position: usize) {
+        let Some(node) = self.get_mut(handle) else {
+            return;
+        };
+        node.dirty = true;
+        let parent = node.parent;
+        if self.sibling_index(handle).is_some() {
+            return;
+        }
+        let siblings = match parent {
+            Some(parent) => match self.get_mut(parent) {
+                Some(parent) => &mut parent.children,
+                None => return,
+            },
+            None => &mut self.roots,
+        };
+        siblings.insert(position.min(siblings.len()), handle);
+    }
+
+    /// Moves the node with its subtree to the end of `parent`'s children, or of
+    /// the roots. `parent` must not be in the node's subtree.
+    pub fn set_parent(&mut self, handle: NodeHandle, parent: Option<NodeHandle>) {
+        if !self.contains(handle) || parent.is_some_and(|parent| !self.contains(parent)) {
+            return;
+        }
+        self.detach(handle);
+        self.get_mut(handle).unwrap().parent = parent;
+        self.attach(handle, usize::MAX);
+    }
+
+    /// The node's transform relative to the scene root, from the current local transforms.
+    pub fn world_matrix(&self, handle: NodeHandle) -> Matrix4 {
+        let Some(node) = self.get(handle) else {
+            return Matrix4::identity();
+        };
+        let mut matrix = node.transform.matrix();
+        let mut parent = node.parent;
+        while let Some(node) = parent.and_then(|parent| self.get(parent)) {
+            matrix = node.transform.matrix() * matrix;
+            parent = node.parent;
+        }
+        matrix
+    }
+
+    /// World space bounds of the meshes in the subtree of `handle`, `None` without any.
+    pub fn bounds(&self, handle: NodeHandle) -> Option<Bounds> {
+        let mut bounds: Option<Bounds> = None;
+        let mut stack = vec![handle];
+        while let Some(handle) = stack.pop() {
+            let Some(node) = self.get(handle) else {
+                continue;
+            };
+            stack.extend(node.children.iter());
+            if let Some(mesh_bounds) = node.mesh.as_ref().and_then(|mesh| mesh.bounds) {
+                let world = mesh_bounds.transformed(self.world_matrix(handle));
+                bounds = Some(bounds.map_or(world, |bounds| bounds.union(&world)));
+            }
+        }
+        bounds
+    }
+
+    /// Recomputes the cached world transforms of dirty nodes and their subtrees.
+    pub fn update_transforms(&mut self) {
+        let mut stack: Vec<_> = self
+            .roots
+            .iter()
+            .map(|root| (*root, Matrix4::identity(), false))
+            .collect();
+        while let Some((handle, parent_world, parent_changed)) = stack.pop() {
+            let Some(node) = self.get_mut(handle) else {
+                continue;
+            };
+            let changed = parent_changed || node.dirty;
+            if changed {
+                node.world = parent_world * node.transform.matrix();
+                node.dirty = false;
+            }
+            let world = node.world;
+            stack.extend(node.children.iter().map(|child| (*child, world, changed)));
+        }
+    }
+
+    /// Fills `draw_list` with the mesh primitives of all attached nodes, depth
+    /// first. Their world transforms are those of the last `update_transforms`,
+    /// which `Stage::TransformPropagation` runs.
+    pub fn extract(&self, assets: &AssetServer, draw_list: &mut DrawList) {
+        draw_list.clear();
+        let default_material = Material::default();
+        let mut stack: Vec<_> = self.roots.iter().rev().copied().collect();
+        while let Some(handle) = stack.pop() {
+            let Some(node) = self.get(handle) else {
+                continue;
+            };
+            stack.extend(node.children.iter().rev());
+            let Some(mesh) = node.mesh.as_ref() else {
+                continue;
+            };
+            for primitive in mesh.primitives.iter() {
+                let material = primitive
+                    .material
+                    .as_ref()
+                    .and_then(|material| assets.get(material))
+                    .unwrap_or(&default_material);
+                let (mesh, object, material) = match (assets.get(&primitive.mesh), primitive.bounds)
+                {
+                    (Some(_), _) => {
+                        let mut object = ObjectUniform::new(node.world, material.draw_color());
+                        object.emissive = material.emissive;
+                        object.alpha_cutoff = material.alpha_cutoff;
+                        (primitive.mesh.id(), object, MaterialState::from(material))
+                    }
+                    (None, Some(bounds)) => {
+                        let Some(placeholder) = self.placeholder.as_ref() else {
+                            continue;
+                        };
+                        let [x, y, z] = bounds.min;
+                        let size = std::array::from_fn(|axis| bounds.max[axis] - bounds.min[axis]);
+                        let cube = Transform {
+                            translation: [x, y, z],
+                            scale: size,
+                            ..Default::default()
+                        };
+                        (
+                            placeholder.id(),
+                            ObjectUniform::new(node.world * cube.matrix(), PLACEHOLDER_COLOR),
+                            MaterialState::default(),
+                        )
+                    }
+                    (None, None) => continue,
+                };
+                draw_list.push(DrawItem {
+                    node: handle,
+                    mesh,
+                    object,
+                    material,
+                });
+            }
+        }
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{Bounds, Material, Mesh, MeshPrimitive, SceneGraph, PLACEHOLDER_COLOR};
+    use crate::runtime::{
+        core::mathematics::{
+            transform::{quaternion_from_axis_angle, Transform},
+            Array4,
+        },
+        functions::{
+            render_system::draw_list::DrawList, scene_system::models::renderable::SceneRenderData,
+        },
+        resources::asset_system::{AssetKey, AssetServer},
+    };
+
+    #[test]
+    fn graph_handles_and_transforms() {
+        let mut graph = SceneGraph::default();
+        let root = graph.spawn(
+            "Root".to_owned(),
+            None,
+            Transform {
+                translation: [0.0, 1.0, 0.0],
+                rotation: quaternion_from_axis_angle([1.0, 0.0, 0.0], -90f32.to_radians()),
+                ..Default::default()
+            },
+        );
+        let child = graph.spawn(
+            "Child".to_owned(),
+            Some(root),
+            Transform {
+                translation: [0.0, 0.0, 2.0],
+                ..Default::default()
+            },
+        );
+        let mut assets = AssetServer::default();
+        let red = assets.add(Material {
+            base_color: [1.0, 0.0, 0.0, 1.0],
+            ..Default::default()
+        });
+        let child_mesh = assets.add(SceneRenderData::default());
+        let root_mesh = assets.add(SceneRenderData::default());
+        let primitive = |mesh, material| MeshPrimitive {
+            mesh,
+            material,
+            triangles: 2,
+            bounds: None,
+        };
+        graph.get_mut(child).unwrap().mesh = Some(Mesh {
+            primitives: vec![primitive(child_mesh.clone(), Some(red))],
+            bounds: Some(Bounds {
+                min: [-1.0; 3],
+                max: [1.0; 3],
+            }),
+        });
+        graph.get_mut(root).unwrap().mesh = Some(Mesh {
+            primitives: vec![primitive(root_mesh, None)],
+            ..Default::default()
+        });
+        assert_eq!(graph.roots(), &[root]);
+        assert_eq!(graph.get(root).unwrap().children(), &[child]);
+
+        // z up below the root, as the glTF root nodes of z-up exports do
+        let origin = Array4::new([0.0, 0.0, 0.0, 1.0]);
+        let expected = Array4::new([0.0, 3.0, 0.0, 1.0]);
+        assert!((graph.world_matrix(child) * origin).almost_eq(&expected));
+        // the child's box around (0, 3, 0), rotated with the root
+        let bounds = graph.bounds(root).unwrap();
+        assert!(
+            Array4::new([bounds.min[0], bounds.min[1], bounds.min[2], 1.0])
+                .almost_eq(&Array4::new([-1.0, 2.0, -1.0, 1.0]))
+        );
+        assert!((bounds.radius() - 3f32.sqrt()).abs() < 1e-5);
+        let [x, y, z] = graph.bounds(child).unwrap().center();
+        assert!(Array4::new([x, y, z, 1.0]).almost_eq(&expected));
+
+        // draws follow the tree depth first, with cached world transforms
+        let mut draw_list = DrawList::default();
+        graph.update_transforms();
+        graph.extract(&assets, &mut draw_list);
+        assert_eq!(draw_list.items.len(), 2);
+        assert_eq!(draw_list.items[0].node, root);
+        assert_eq!(
+            draw_list.items[0].object.base_color,
+            Material::default().base_color
+        );
+        assert_eq!(draw_list.items[1].mesh, child_mesh.id());
+        assert_eq!(draw_list.items[1].object.base_color, [1.0, 0.0, 0.0, 1.0]);
+        assert!((draw_list.items[1].object.model * origin).almost_eq(&expected));
+
+        // moving the root moves its subtree once transforms are propagated
+        graph.set_transform(root, Transform::default());
+        graph.extract(&assets, &mut draw_list);
+        assert!((draw_list.items[1].object.model * origin).almost_eq(&expected));
+        graph.update_transforms();
+        graph.extract(&assets, &mut draw_list);
+        let moved = draw_list.items[1].object.model * origin;
+        assert!(moved.almost_eq(&Array4::new([0.0, 0.0, 2.0, 1.0])));
+
+        // detached subtrees are kept but not drawn
+        assert_eq!(graph.detach(root), Some(0));
+        assert!(!graph.is_attached(child));
+        graph.extract(&assets, &mut draw_list);
+        assert!(draw_list.items.is_empty());
+        graph.attach(root, 0);
+        assert!(graph.is_attached(child));
+
+        // reparented nodes go last, keeping their local transform
+        graph.set_parent(child, None);
+        assert_eq!(graph.roots(), &[root, child]);
+        assert!(graph.get(root).unwrap().children().is_empty());
+        graph.update_transforms();
+        graph.extract(&assets, &mut draw_list);
+        let moved = draw_list.items[1].object.model * origin;
+        assert!(moved.almost_eq(&Array4::new([0.0, 0.0, 2.0, 1.0])));
+        graph.set_parent(child, Some(root));
+        assert_eq!(graph.roots(), &[root]);
+        assert_eq!(graph.get(child).unwrap().parent(), Some(root));
+
+        // removed nodes free their slots, stale handles stay invalid
+        graph.remove(root);
+        assert!(!graph.contains(root) && !graph.contains(child));
+        assert_eq!(graph.node_count(), 0);
+        let reused = graph.spawn("Reused".to_owned(), None, Transform::default());
+        assert!(graph.contains(reused) && !graph.contains(child) && !graph.contains(root));
+        assert_eq!(graph.roots(), &[reused]);
+        // the meshes and materials went with the nodes
+        drop(child_mesh);
+        assert_eq!(assets.free_unused(), 3);
+
+        // meshes still loading are drawn as boxes filling their bounds
+        let (loading, _) = assets.reserve::<SceneRenderData>(AssetKey::path("model.gltf"));
+        graph.get_mut(reused).unwrap().mesh = Some(Mesh {
+            primitives: vec![MeshPrimitive {
+                bounds: Some(Bounds {
+                    min: [1.0, 2.0, 3.0],
+                    max: [2.0; 3],
+                }),
+                ..primitive(loading.clone(), None)
+            }],
+            ..Default::default()
+        });
+        graph.extract(&assets, &mut draw_list);
+        assert!(draw_list.items.is_empty());
+        let placeholder = assets.add(SceneRenderData::default());
+        graph.placeholder = Some(placeholder.clone());
+        graph.extract(&assets, &mut draw_list);
+        assert_eq!(draw_list.items[0].mesh, placeholder.id());
+        assert_eq!(draw_list.items[0].object.base_color, PLACEHOLDER_COLOR);
+        let corner = draw_list.items[0].object.model * origin;
+        assert!(corner.almost_eq(&Array4::new([1.0, 2.0, 3.0, 1.0])));
+        assets.finish(&loading, Ok(SceneRenderData::default()));
+        graph.extract(&assets, &mut draw_list);
+        assert_eq!(draw_list.items[0].mesh, loading.id());
+    }
+}
@@ -1,10 +1,46 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    fmt::Debug,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::runtime::{
+    core::{
+        ecs::{
+            schedule::{Schedule, Stage},
+            World,
+        },
+        mathematics::{transform::Transform, Array4, Matrix4},
+        timestep::{FixedTimestep, DEFAULT_MAX_STEPS},
+    },
+    functions::render_system::texture::Texture,
+    resources::{
+        asset_system::{AssetId, AssetKey, AssetServer, Handle, LoadState},
+        file_watcher::WatchBackend,
+    },
+};
+
 use self::{
     camera::CameraInfo,
-    models::{gltf::GltfData, load, renderable::SceneRenderData},
+    graph::{
+        Bounds, Light, Material, Mesh, MeshPrimitive, NodeHandle, NodeLink, SceneCamera, SceneGraph,
+    },
+    models::{
+        gltf::{GltfData, GltfLight, GltfMeshPrimitiveAttr, GltfNode, KHR_LIGHTS_PUNCTUAL},
+        image::ImageData,
+        is_model, model_name,
+        renderable::{MeshData, SceneRenderData},
+        source_files, ModelLoader, Scene,
+    },
+    scene_file::{Environment, NodeOverride, SceneFile, SceneObject, Viewpoint},
 };
 
 pub mod camera;
+pub mod graph;
 pub mod models;
+pub mod panel;
+pub mod scene_file;
 
 #[repr(C)]
 #[derive(Debug)]
@@ -22,104 +58,1359 @@ pub struct VertexInfo {
 }
 
 pub struct SceneManager {
-    pub camera: CameraInfo,
+    /// Runtime objects, the camera and the frame timing are resources of it.
+    pub world: World,
+    pub schedule: Schedule,
+    // camera as of the previous fixed step, for interpolation
+    previous_camera: CameraInfo,
+    pub timestep: FixedTimestep,
+    pub graph: SceneGraph,
+    /// Node selected in the hierarchy panel or the viewport.
+    pub selection: Option<NodeHandle>,
+    pub environment: Environment,
+    /// The scene file last loaded.
+    pub scene_path: Option<PathBuf>,
+    /// Meshes, materials, textures and models of the scene, the graph's nodes hold handles to them.
+    pub assets: AssetServer,
+    // model file of each node a model was loaded below
+    models: HashMap<NodeHandle, (PathBuf, Handle<Scene>)>,
+    // the scene file's transforms of model nodes by model root, see `apply_overrides`
+    overrides: HashMap<NodeHandle, Vec<NodeOverride>>,
+    // reads the reserved models on worker threads
+    loader: ModelLoader<ModelRequest>,
+    // models not added to the scene yet, in the order they were asked for
+    pending: Vec<PendingModel>,
+    uploads: UploadQueue,
+    // reports changes of the scene file and the model files, see `reload_changed`
+    watcher: Option<Box<dyn WatchBackend>>,
+    // model of each file a loaded model was read from
+    watched: HashMap<PathBuf, PathBuf>,
+    // counts the scenes replaced, see `scene_generation`
+    generation: u64,
+}
+
+/// Time per frame spent creating GPU buffers and textures of loaded models, at least one is created every frame.
+pub const UPLOAD_BUDGET: Duration = Duration::from_millis(4);
+
+/// What becomes of the current scene when a model is opened into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    Add,
+    Replace,
+}
+
+/// Where a model goes once it is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModelTarget {
+    /// Opened with `open_model`.
+    Open(OpenMode),
+    /// Below the node of its scene file object.
+    Node(NodeHandle),
+}
+
+/// A model asked of the loader, a reload replaces the model and updates its instances.
+#[derive(Debug, Clone, Copy)]
+struct ModelRequest {
+    scene: AssetId<Scene>,
+    reload: bool,
+}
+
+struct PendingModel {
+    path: PathBuf,
+    scene: Handle<Scene>,
+    target: ModelTarget,
+}
+
+/// A mesh waiting for its GPU buffers, or an image for its texture.
+enum Upload {
+    Mesh(Handle<SceneRenderData>, MeshData),
+    Texture(Handle<Texture>, ImageData),
+}
+
+/// Meshes and images waiting to be created on the GPU.
+#[derive(Default)]
+struct UploadQueue {
+    queue: VecDeque<Upload>,
+    // since the queue was last empty
+    queued: usize,
+}
+
+impl UploadQueue {
+    fn push(&mut self, upload: Upload) {
+        self.queue.push_back(upload);
+        self.queued += 1;
+    }
+}
+
+/// How far loading the scene is, see `SceneManager::progress`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    /// Models still being read.
+    pub models: usize,
+    /// Meshes and textures uploaded of those waiting since everything was last uploaded.
+    pub uploaded: usize,
+    pub uploads: usize,
+}
+
+impl LoadProgress {
+    #[inline]
+    pub fn done(&self) -> bool {
+        self.models == 0 && self.uploaded == self.uploads
+    }
+}
+
+/// The asset key of a mesh primitive of a model, so instances of the model share its buffers.
+fn mesh_key(path: &Path, mesh: usize, primitive: usize) -> AssetKey {
+    AssetKey::labeled(path, format!("mesh{}/{}", mesh, primitive))
+}
+
+fn material_key(path: &Path, material: usize) -> AssetKey {
+    AssetKey::labeled(path, format!("material{}", material))
+}
+
+fn texture_key(path: &Path, image: usize) -> AssetKey {
+    AssetKey::labeled(path, format!("image{}", image))
+}
+
+/// Identifies a scene file object by the names from its root down, siblings
+/// sharing a name are told apart by their order.
+fn object_key(parent_key: &str, name: &str, names: &mut HashMap<String, usize>) -> String {
+    let occurrence = names.entry(name.to_owned()).or_default();
+    let key = format!("{}/{}#{}", parent_key, name, occurrence);
+    *occurrence += 1;
+    key
+}
+
+/// The local transform of a node, from its `matrix` unless that is the identity.
+fn node_transform(node: &GltfNode) -> Transform {
+    let matrix = node.matrix.map(|m| {
+        Matrix4::new([
+            [m[0], m[1], m[2], m[3]],
+            [m[4], m[5], m[6], m[7]],
+            [m[8], m[9], m[10], m[11]],
+            [m[12], m[13], m[14], m[15]],
+        ])
+    });
+    match matrix {
+        Some(matrix) if !matrix.almost_eq(&Matrix4::identity()) => Transform::from_matrix(&matrix),
+        _ => {
+            let default = Transform::default();
+            Transform {
+                translation: node.translation.unwrap_or(default.translation),
+                rotation: node.rotation.unwrap_or(default.rotation),
+                scale: node.scale.unwrap_or(default.scale),
+            }
+        }
+    }
+}
+
+/// Lights declared by the `KHR_lights_punctual` extension, nodes refer to them by index.
+fn scene_lights(gltf_data: &GltfData) -> Vec<Light> {
+    let Some(lights) = gltf_data
+        .extras
+        .extensions
+        .as_ref()
+        .and_then(|extensions| extensions.get(KHR_LIGHTS_PUNCTUAL)?.get("lights"))
+    else {
+        return Vec::new();
+    };
+    match serde_json::from_value::<Vec<GltfLight>>(lights.clone()) {
+        Ok(lights) => lights
+            .iter()
+            .enumerate()
+            .map(|(index, light)| Light::from_gltf(index, light))
+            .collect(),
+        Err(error) => {
+            log::warn!("Ignoring invalid {} lights: {}", KHR_LIGHTS_PUNCTUAL, error);
+            Vec::new()
+        }
+    }
+}
+
+/// A parsed glTF file with the components its nodes refer to by index.
+struct GltfScene<'a> {
+    path: &'a Path,
+    data: &'a GltfData,
+    bin: &'a [u8],
+    materials: Vec<Handle<Material>>,
+    lights: Vec<Light>,
+    cameras: Vec<Option<SceneCamera>>,
+    // nodes spawned from the model before by glTF node, they are updated instead of spawned
+    reuse: HashMap<usize, NodeHandle>,
 }
 
 fn traverse_node(
     index: usize,
-    gltf_data: &GltfData,
-    bin_data: &[u8],
-    device: &wgpu::Device,
-    render_queue: &mut Vec<SceneRenderData>,
+    parent: Option<NodeHandle>,
+    scene: &mut GltfScene,
+    uploads: &mut UploadQueue,
+    assets: &mut AssetServer,
+    graph: &mut SceneGraph,
+    world: &mut World,
 ) {
+    let (gltf_data, bin_data) = (scene.data, scene.bin);
     let node = &gltf_data.nodes[index];
+    let name = node
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("Node {}", index));
+    let transform = node_transform(node);
+    let handle = match scene.reuse.remove(&index) {
+        // the node and its entity stay, so handles to them do
+        Some(handle) => {
+            graph.set_parent(handle, parent);
+            graph.set_transform(handle, transform);
+            graph.get_mut(handle).unwrap().name = name;
+            handle
+        }
+        None => {
+            let handle = graph.spawn(name, parent, transform);
+            let entity = world.spawn();
+            world.insert(
+                entity,
+                NodeLink {
+                    node: handle,
+                    gltf_node: index,
+                },
+            );
+            handle
+        }
+    };
+    let scene_node = graph.get_mut(handle).unwrap();
+    scene_node.mesh = None;
+    scene_node.light = node
+        .extras
+        .extensions
+        .as_ref()
+        .and_then(|extensions| extensions.get(KHR_LIGHTS_PUNCTUAL)?.get("light")?.as_u64())
+        .and_then(|light| scene.lights.get(light as usize).cloned());
+    scene_node.camera = node
+        .camera
+        .and_then(|camera| scene.cameras.get(camera)?.clone());
     for child in node.children.iter() {
-        traverse_node(*child, gltf_data, bin_data, device, render_queue);
+        traverse_node(*child, Some(handle), scene, uploads, assets, graph, world);
     }
 
-    let buffer_views = &gltf_data.buffer_views;
     let Some(mesh_index) = node.mesh else { return; };
     let mesh = &gltf_data.meshes[mesh_index];
-    for mesh_element in mesh.primitives.iter() {
-        let mut render_data = SceneRenderData::default();
-        for primitive_type in mesh_element.attributes.keys() {
-            let primitive_index = mesh_element.attributes[primitive_type];
-            let accessor = &gltf_data.accessors[primitive_index];
-            render_data.process_primitive(primitive_type, buffer_views, bin_data, device, accessor);
+    let mut primitives = Vec::new();
+    let mut bounds: Option<Bounds> = None;
+    for (primitive_index, mesh_element) in mesh.primitives.iter().enumerate() {
+        // queued when the model was read, unless freed since
+        let (render_data, new) =
+            assets.reserve::<SceneRenderData>(mesh_key(scene.path, mesh_index, primitive_index));
+        if new {
+            let mesh_data =
+                MeshData::from_primitive(gltf_data, bin_data, mesh_index, primitive_index);
+            uploads.push(Upload::Mesh(render_data.clone(), mesh_data));
         }
-        let indices_index = mesh_element.indices.unwrap();
-        let indices_data = &gltf_data.accessors[indices_index];
-        render_data.process_indices(buffer_views, bin_data, device, indices_data);
 
-        match mesh_element.mode.unwrap() {
-            models::gltf::GltfMeshPrimitiveMode::Points => todo!(),
-            models::gltf::GltfMeshPrimitiveMode::Lines => todo!(),
-            models::gltf::GltfMeshPrimitiveMode::LineLoop => todo!(),
-            models::gltf::GltfMeshPrimitiveMode::LineStrip => todo!(),
-            models::gltf::GltfMeshPrimitiveMode::Triangles => {}
-            models::gltf::GltfMeshPrimitiveMode::TriangleStrip => todo!(),
-            models::gltf::GltfMeshPrimitiveMode::TriangleFan => todo!(),
+        // glTF requires the extent of positions
+        let positions = mesh_element
+            .attributes
+            .get(&GltfMeshPrimitiveAttr::Position)
+            .map(|position| &gltf_data.accessors[*position]);
+        let primitive_bounds = match positions.map(|positions| (&positions.min, &positions.max)) {
+            Some((Some(min), Some(max))) => Some(Bounds {
+                min: std::array::from_fn(|axis| min.get(axis).copied().unwrap_or_default()),
+                max: std::array::from_fn(|axis| max.get(axis).copied().unwrap_or_default()),
+            }),
+            _ => None,
+        };
+        if let Some(primitive_bounds) = primitive_bounds {
+            bounds =
+                Some(bounds.map_or(primitive_bounds, |bounds| bounds.union(&primitive_bounds)));
         }
 
-        render_queue.push(render_data);
+        let triangles = mesh_element
+            .indices
+            .map_or(0, |indices| gltf_data.accessors[indices].count as u32 / 3);
+        primitives.push(MeshPrimitive {
+            mesh: render_data,
+            material: mesh_element
+                .material
+                .and_then(|material| scene.materials.get(material).cloned()),
+            triangles,
+            bounds: primitive_bounds,
+        });
     }
+    graph.get_mut(handle).unwrap().mesh = Some(Mesh { primitives, bounds });
 }
 
 impl SceneManager {
     pub fn new() -> Self {
+        let mut world = World::default();
+        world.insert_resource(CameraInfo::default());
+        let mut schedule = Schedule::default();
+        schedule.add_system(
+            Stage::TransformPropagation,
+            "propagate_transforms",
+            |world| {
+                if let Some(graph) = world.get_resource_mut::<SceneGraph>() {
+                    graph.update_transforms();
+                }
+            },
+        );
         SceneManager {
-            camera: CameraInfo::default(),
+            world,
+            schedule,
+            previous_camera: CameraInfo::default(),
+            timestep: FixedTimestep::default(),
+            graph: SceneGraph::default(),
+            selection: None,
+            environment: Environment::default(),
+            scene_path: None,
+            assets: AssetServer::default(),
+            models: HashMap::new(),
+            overrides: HashMap::new(),
+            loader: ModelLoader::default(),
+            pending: Vec::new(),
+            uploads: UploadQueue::default(),
+            watcher: None,
+            watched: HashMap::new(),
+            generation: 0,
         }
     }
 
-    pub fn load_scene(
+    /// Changes whenever the scene is replaced, so state about its nodes can be dropped.
+    #[inline]
+    pub fn scene_generation(&self) -> u64 {
+        self.generation
+    }
+
+    #[inline]
+    pub fn camera(&self) -> &CameraInfo {
+        self.world.resource()
+    }
+
+    #[inline]
+    pub fn camera_mut(&mut self) -> &mut CameraInfo {
+        self.world.resource_mut()
+    }
+
+    /// Runs the systems of `stage`. The scene graph is lent to the world as a
+    /// resource meanwhile, so systems can move the nodes of their entities.
+    pub fn run_stage(&mut self, stage: Stage) {
+        self.world.insert_resource(std::mem::take(&mut self.graph));
+        self.schedule.run(stage, &mut self.world);
+        self.graph = self.world.remove_resource().unwrap_or_default();
+    }
+
+    /// Runs `update` for every fixed step due after `delta_t` seconds of frame time.
+    pub fn simulate(&mut self, delta_t: f32, mut update: impl FnMut(f32, &mut SceneManager)) {
+        let steps = self.timestep.advance(delta_t);
+        for _ in 0..steps {
+            self.previous_camera = *self.camera();
+            update(self.timestep.step(), self);
+        }
+    }
+
+    /// The camera interpolated between the last two fixed steps.
+    #[inline]
+    pub fn render_camera(&self) -> CameraInfo {
+        self.camera()
+            .interpolate(&self.previous_camera, self.timestep.alpha())
+    }
+
+    pub fn reset_camera(&mut self) {
+        *self.camera_mut() = CameraInfo::default();
+        self.previous_camera = CameraInfo::default();
+    }
+
+    /// Replaces the scene with the one described by a scene file. Its models are
+    /// read in the background and added by `finish_loading`, those loaded already
+    /// are not read again. The current scene stays when the scene file can not be read.
+    pub fn load_scene<P: AsRef<Path> + Debug>(
         &mut self,
-        device: &wgpu::Device,
-        render_queue: &mut Vec<SceneRenderData>,
-        bindgroup_list: &mut Vec<wgpu::BindGroup>,
-    ) {
-        // let scene_data = load("assets/scenes/triangle/tri.gltf");
-        let (scene_data, bin_data) = load(
-            "assets/scenes/CornellBox/scene.gltf",
-            "assets/scenes/CornellBox/scene.bin",
+        path: P,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        // a model on its own is opened as a scene of just that model
+        let is_model = is_model(path);
+        let scene_file = if is_model {
+            SceneFile::with_model(Path::new(path.file_name().unwrap_or_default()))
+        } else {
+            SceneFile::load(path)?
+        };
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        // requested before the current scene lets go of the models it shares
+        let models = self.request_models(&scene_file, base_dir);
+
+        self.clear();
+        self.spawn_objects(&scene_file.objects, base_dir, &models, HashMap::new());
+
+        self.environment = scene_file.environment;
+        self.timestep = FixedTimestep::new(self.environment.update_rate, DEFAULT_MAX_STEPS);
+        let mut camera = CameraInfo::default();
+        if let Some(viewpoint) = scene_file.viewpoint {
+            let [x, y, z] = viewpoint.position;
+            camera.position = Array4::new([x, y, z, 1.0]);
+            // the camera keeps a direction, the file the point looked at
+            let [target_x, target_y, target_z] = viewpoint.look_at;
+            let mut direction = Array4::new([target_x - x, target_y - y, target_z - z, 0.0]);
+            if direction.length() > f32::EPSILON {
+                direction.normalize();
+                camera.lookat = direction;
+            } else {
+                log::warn!("Viewpoint of {:?} looks at its own position", path);
+            }
+        }
+        *self.camera_mut() = camera;
+        self.previous_camera = camera;
+        if let (Some(watcher), Some(scene_path)) = (self.watcher.as_mut(), &self.scene_path) {
+            watcher.unwatch(scene_path);
+        }
+        // saving must not write JSON over the model
+        self.scene_path = (!is_model).then(|| path.to_path_buf());
+        if let (Some(watcher), Some(scene_path)) = (self.watcher.as_mut(), &self.scene_path) {
+            watcher.watch(scene_path);
+        }
+        log::info!(
+            "Loading scene {:?}: {} nodes, {} models",
+            path,
+            self.graph.node_count(),
+            models.len()
+        );
+        Ok(())
+    }
+
+    /// Applies the changes of a scene file to the scene in place. Objects are
+    /// matched by `object_key`, their nodes and entities stay and so do their
+    /// models unless the object's model changed. The camera stays where it is.
+    fn reload_scene(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let scene_file = SceneFile::load(path)?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        let models = self.request_models(&scene_file, base_dir);
+        let objects = self.object_nodes();
+        self.spawn_objects(&scene_file.objects, base_dir, &models, objects);
+        self.environment = scene_file.environment;
+        self.timestep = FixedTimestep::new(self.environment.update_rate, DEFAULT_MAX_STEPS);
+        log::info!(
+            "Reloaded scene {:?}: {} nodes, {} models",
+            path,
+            self.graph.node_count(),
+            models.len()
+        );
+        Ok(())
+    }
+
+    /// Reports changes of the scene file and the model files from now on, see `reload_changed`.
+    pub fn set_watcher(&mut self, mut watcher: Box<dyn WatchBackend>) {
+        for path in self.scene_path.iter().chain(self.watched.keys()) {
+            watcher.watch(path);
+        }
+        self.watcher = Some(watcher);
+    }
+
+    /// Reloads the scene file and the models changed on disk since the last
+    /// call, nothing is watched unless `set_watcher` was called. Nodes and their
+    /// entities stay as long as the files still have them, so handles to them
+    /// stay valid. Models are read in the background and reported by
+    /// `finish_loading`, a failed one stays as it was.
+    /// Returns the path of the scene file if it was reloaded, with the result.
+    pub fn reload_changed(&mut self) -> Vec<(PathBuf, Result<(), String>)> {
+        let Some(watcher) = self.watcher.as_mut() else {
+            return Vec::new();
+        };
+        let mut reloaded = Vec::new();
+        let mut models = Vec::new();
+        for path in watcher.poll_changes() {
+            if self.scene_path.as_ref() == Some(&path) {
+                let result = self.reload_scene(&path).map_err(|err| err.to_string());
+                reloaded.push((path, result));
+            } else if let Some(model) = self.watched.get(&path) {
+                // a `.gltf` and its buffer are read once
+                if !models.contains(model) {
+                    models.push(model.clone());
+                }
+            }
+        }
+        for model in models {
+            self.reload_model(&model);
+        }
+        reloaded
+    }
+
+    /// Reads the model again unless it is no longer in the scene.
+    fn reload_model(&mut self, path: &Path) {
+        let Some(scene) = self
+            .models
+            .values()
+            .find(|(model, _)| model == path)
+            .map(|(_, scene)| scene.id())
+        else {
+            return;
+        };
+        log::info!("Reloading model {:?}", path);
+        self.loader.request(
+            path.to_path_buf(),
+            ModelRequest {
+                scene,
+                reload: true,
+            },
         );
-        // let scene_data = load("assets/scenes/Curtains/NewSponza.gltf");
-        let default_scene = scene_data.default_scene.unwrap();
-        for node_index in scene_data.scenes[default_scene].nodes.iter() {
-            traverse_node(*node_index, &scene_data, &bin_data, device, render_queue);
-        }
-        let bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Bindgroup Layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: true },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+    }
+
+    /// The models of a scene file, each requested once.
+    fn request_models(
+        &mut self,
+        scene_file: &SceneFile,
+        base_dir: &Path,
+    ) -> HashMap<PathBuf, Handle<Scene>> {
+        let mut models = HashMap::new();
+        for model in scene_file.objects.iter().flat_map(SceneObject::models) {
+            if let Entry::Vacant(entry) = models.entry(base_dir.join(model)) {
+                let scene = self.request_model(entry.key());
+                entry.insert(scene);
+            }
+        }
+        models
+    }
+
+    /// The model asset of a file, requested from the loader unless it is loaded or loading already.
+    fn request_model(&mut self, path: &Path) -> Handle<Scene> {
+        let (scene, new) = self.assets.reserve::<Scene>(AssetKey::path(path));
+        if new {
+            log::info!("Loading model {:?}", path);
+            self.loader.request(
+                path.to_path_buf(),
+                ModelRequest {
+                    scene: scene.id(),
+                    reload: false,
                 },
-                count: None,
-            }],
+            );
+        }
+        scene
+    }
+
+    /// Starts reading a model file in the background, it is added to the scene
+    /// by `finish_loading` once read.
+    pub fn open_model(&mut self, path: PathBuf, mode: OpenMode) {
+        let scene = self.request_model(&path);
+        self.pending.push(PendingModel {
+            path,
+            scene,
+            target: ModelTarget::Open(mode),
         });
+    }
+
+    /// Models still being read and meshes waiting for their GPU buffers.
+    pub fn progress(&self) -> LoadProgress {
+        LoadProgress {
+            models: self.pending.len(),
+            uploaded: self.uploads.queued - self.uploads.queue.len(),
+            uploads: self.uploads.queued,
+        }
+    }
+
+    /// Adds the models read since the last call to the scene, their meshes are
+    /// drawn as placeholders until `upload_assets` created their buffers. Models
+    /// opened with `open_model` are selected and framed by the camera, reloaded
+    /// ones are updated in place.
+    /// Returns the path of each opened or reloaded model, and of each that failed, with its load error.
+    pub fn finish_loading(&mut self) -> Vec<(PathBuf, Result<(), String>)> {
+        let mut finished = Vec::new();
+        for loaded in self.loader.poll() {
+            let ModelRequest { scene, reload } = loaded.tag;
+            let result = loaded.result.map(|(model, meshes)| {
+                for mesh_data in meshes {
+                    let key = mesh_key(&loaded.path, mesh_data.mesh, mesh_data.primitive);
+                    let (mesh, new) = self.assets.reserve::<SceneRenderData>(key);
+                    // reloaded meshes are drawn with their old buffers until uploaded
+                    if new || (reload && self.assets.reload(&mesh)) {
+                        self.uploads.push(Upload::Mesh(mesh, mesh_data));
+                    }
+                }
+                for (image, image_data) in model.images.iter().enumerate() {
+                    let Some(image_data) = image_data else {
+                        continue;
+                    };
+                    let key = texture_key(&loaded.path, image);
+                    let (texture, new) = self.assets.reserve::<Texture>(key);
+                    if new || (reload && self.assets.reload(&texture)) {
+                        self.uploads
+                            .push(Upload::Texture(texture, image_data.clone()));
+                    }
+                }
+                for file in source_files(&loaded.path, &model) {
+                    if let Some(watcher) = self.watcher.as_mut() {
+                        watcher.watch(&file);
+                    }
+                    self.watched.insert(file, loaded.path.clone());
+                }
+                model
+            });
+            if !reload {
+                self.assets.finish(scene, result);
+                continue;
+            }
+            // the model as it was stays when the file can not be read
+            let reloaded = result.as_ref().map(|_| ()).map_err(Clone::clone);
+            self.assets.reload(scene);
+            self.assets.finish(scene, result);
+            if reloaded.is_ok() {
+                self.respawn_model(&loaded.path, scene);
+            }
+            finished.push((loaded.path, reloaded));
+        }
+        for pending in std::mem::take(&mut self.pending) {
+            let PendingModel {
+                path,
+                scene,
+                target,
+            } = pending;
+            match self.assets.load_state(&scene).cloned() {
+                Some(LoadState::Loading) => self.pending.push(PendingModel {
+                    path,
+                    scene,
+                    target,
+                }),
+                Some(LoadState::Failed(err)) => finished.push((path, Err(err))),
+                _ => match target {
+                    // gone when the scene was replaced meanwhile
+                    ModelTarget::Node(node) if self.graph.contains(node) => {
+                        self.spawn_model(node, &path, scene);
+                    }
+                    ModelTarget::Node(_) => {}
+                    ModelTarget::Open(mode) => {
+                        if mode == OpenMode::Replace {
+                            self.clear();
+                            self.scene_path = None;
+                        }
+                        let root = self
+                            .graph
+                            .spawn(model_name(&path), None, Transform::default());
+                        self.spawn_model(root, &path, scene);
+                        self.selection = Some(root);
+                        self.frame_node(root);
+                        finished.push((path, Ok(())));
+                    }
+                },
+            }
+        }
+        finished
+    }
+
+    /// Creates the GPU buffers of loaded meshes and the textures of decoded images
+    /// for up to `budget`, and at least one, so big scenes load over several frames
+    /// without stalling any. Returns how many were created.
+    pub fn upload_assets(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        budget: Duration,
+    ) -> usize {
+        if self.graph.placeholder.is_none() {
+            let cube = SceneRenderData::new(device, &MeshData::unit_cube());
+            self.graph.placeholder = Some(self.assets.add(cube));
+        }
+        let start = Instant::now();
+        let mut uploaded = 0;
+        while uploaded == 0 || start.elapsed() < budget {
+            match self.uploads.queue.pop_front() {
+                Some(Upload::Mesh(mesh, mesh_data)) => {
+                    let render_data = SceneRenderData::new(device, &mesh_data);
+                    self.assets.finish(&mesh, Ok(render_data));
+                }
+                Some(Upload::Texture(texture, image_data)) => {
+                    let created = Texture::with_data(
+                        device,
+                        queue,
+                        "Model texture",
+                        image_data.size,
+                        wgpu::TextureFormat::Rgba8UnormSrgb,
+                        &image_data.pixels,
+                    );
+                    self.assets.finish(&texture, Ok(created));
+                }
+                None => break,
+            }
+            uploaded += 1;
+        }
+        if uploaded > 0 && self.uploads.queue.is_empty() {
+            log::info!(
+                "Uploaded {} meshes and textures, last {} in {:?}",
+                self.uploads.queued,
+                uploaded,
+                start.elapsed()
+            );
+            self.uploads.queued = 0;
+        }
+        uploaded
+    }
+
+    /// Points the camera at the meshes below `handle` from where they fill the view.
+    pub fn frame_node(&mut self, handle: NodeHandle) {
+        let Some(bounds) = self.graph.bounds(handle) else {
+            return;
+        };
+        self.camera_mut().frame(bounds.center(), bounds.radius());
+        self.previous_camera = *self.camera();
+    }
+
+    /// Writes the scene to a scene file, model paths are made relative to it where possible.
+    pub fn save_scene<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let base_dir = path.as_ref().parent().unwrap_or(Path::new(""));
+        let links = self.node_links();
+        let camera = self.camera();
+        let mut direction = camera.lookat;
+        direction.normalize();
+        let [x, y, z, _] = camera.position.0;
+        let [dx, dy, dz, _] = direction.0;
+        let scene_file = SceneFile {
+            environment: self.environment,
+            viewpoint: Some(Viewpoint {
+                position: [x, y, z],
+                look_at: [x + dx, y + dy, z + dz],
+            }),
+            objects: self
+                .graph
+                .roots()
+                .iter()
+                .filter_map(|root| self.scene_object(*root, base_dir, &links))
+                .collect(),
+        };
+        scene_file.save(path)
+    }
+
+    /// Removes all nodes and their entities, their assets are freed by the next
+    /// `AssetServer::free_unused` unless used again meanwhile.
+    fn clear(&mut self) {
+        // entities of the previous scene would point at nodes that are gone
+        let linked = self.world.query::<&NodeLink>().entities();
+        for entity in linked {
+            self.world.despawn(entity);
+        }
+        self.graph.clear();
+        self.models.clear();
+        self.overrides.clear();
+        self.selection = None;
+        self.generation += 1;
+        // models of the scene file wait for nodes that are gone
+        self.pending
+            .retain(|pending| matches!(pending.target, ModelTarget::Open(_)));
+    }
+
+    /// Spawns scene file objects as roots. Nodes of `reuse` are updated instead
+    /// where the object has the same `object_key`, the others are removed.
+    fn spawn_objects(
+        &mut self,
+        objects: &[SceneObject],
+        base_dir: &Path,
+        models: &HashMap<PathBuf, Handle<Scene>>,
+        mut reuse: HashMap<String, NodeHandle>,
+    ) {
+        let mut names = HashMap::new();
+        for object in objects {
+            let key = object_key("", &object.name, &mut names);
+            self.spawn_object(object, None, key, base_dir, models, &mut reuse);
+        }
+        for node in reuse.into_values() {
+            self.graph.remove(node);
+        }
+        self.despawn_unlinked();
+        let graph = &self.graph;
+        self.models.retain(|node, _| graph.contains(*node));
+        self.overrides.retain(|node, _| graph.contains(*node));
+        self.selection = self.selection.filter(|node| graph.contains(*node));
+    }
+
+    fn spawn_object(
+        &mut self,
+        object: &SceneObject,
+        parent: Option<NodeHandle>,
+        key: String,
+        base_dir: &Path,
+        models: &HashMap<PathBuf, Handle<Scene>>,
+        reuse: &mut HashMap<String, NodeHandle>,
+    ) {
+        let handle = match reuse.remove(&key) {
+            Some(handle) => {
+                self.graph.set_parent(handle, parent);
+                self.graph.set_transform(handle, object.transform);
+                handle
+            }
+            None => self
+                .graph
+                .spawn(object.name.clone(), parent, object.transform),
+        };
+        let scene_node = self.graph.get_mut(handle).unwrap();
+        scene_node.light = object.light.clone();
+        scene_node.camera = object.camera.clone();
+        let model = object.model.as_ref().map(|model| base_dir.join(model));
+        let model = model.and_then(|model| models.get_key_value(&model));
+        // a node kept from before keeps its model's nodes unless the model changed
+        if self.models.get(&handle).map(|(path, _)| path) != model.map(|(path, _)| path) {
+            self.remove_model(handle);
+            if let Some((model, scene)) = model {
+                // saved with the scene while it loads
+                self.models.insert(handle, (model.clone(), scene.clone()));
+                self.pending.push(PendingModel {
+                    path: model.clone(),
+                    scene: scene.clone(),
+                    target: ModelTarget::Node(handle),
+                });
+            }
+        }
+        if object.overrides.is_empty() {
+            self.overrides.remove(&handle);
+        } else {
+            self.overrides.insert(handle, object.overrides.clone());
+            // a model kept from before is not spawned again
+            self.apply_overrides(handle);
+        }
+        let mut names = HashMap::new();
+        for child in object.children.iter() {
+            let child_key = object_key(&key, &child.name, &mut names);
+            self.spawn_object(child, Some(handle), child_key, base_dir, models, reuse);
+        }
+    }
+
+    /// The glTF node each node spawned from a model was made from.
+    fn node_links(&self) -> HashMap<NodeHandle, usize> {
+        let mut links = HashMap::new();
+        self.world.query::<&NodeLink>().for_each(|_, link| {
+            links.insert(link.node, link.gltf_node);
+        });
+        links
+    }
+
+    /// Despawns the entities of removed nodes.
+    fn despawn_unlinked(&mut self) {
+        let mut unlinked = Vec::new();
+        let graph = &self.graph;
+        self.world.query::<&NodeLink>().for_each(|entity, link| {
+            if !graph.contains(link.node) {
+                unlinked.push(entity);
+            }
+        });
+        for entity in unlinked {
+            self.world.despawn(entity);
+        }
+    }
+
+    /// The nodes of scene file objects by `object_key`, nodes of models are not objects.
+    fn object_nodes(&self) -> HashMap<String, NodeHandle> {
+        let links = self.node_links();
+        let mut objects = HashMap::new();
+        let mut stack = vec![(String::new(), self.graph.roots().to_vec())];
+        while let Some((parent_key, children)) = stack.pop() {
+            let mut names = HashMap::new();
+            for child in children {
+                if links.contains_key(&child) {
+                    continue;
+                }
+                let Some(node) = self.graph.get(child) else {
+                    continue;
+                };
+                let key = object_key(&parent_key, &node.name, &mut names);
+                stack.push((key.clone(), node.children().to_vec()));
+                objects.insert(key, child);
+            }
+        }
+        objects
+    }
+
+    /// Removes the nodes spawned from the model below `root`, and the model if it still loads.
+    fn remove_model(&mut self, root: NodeHandle) {
+        if self.models.remove(&root).is_none() {
+            return;
+        }
+        let links = self.node_links();
+        let children = self
+            .graph
+            .get(root)
+            .map_or(Vec::new(), |node| node.children().to_vec());
+        for child in children {
+            if links.contains_key(&child) {
+                self.graph.remove(child);
+            }
+        }
+        self.pending
+            .retain(|pending| pending.target != ModelTarget::Node(root));
+    }
+
+    /// Updates the materials and every instance of a reloaded model.
+    fn respawn_model(&mut self, path: &Path, scene: AssetId<Scene>) {
+        let Some(model) = self.assets.get(scene).cloned() else {
+            return;
+        };
+        let textures = self.model_textures(path, &model);
+        // edits of the materials are lost, the file changed
+        for (index, material) in model.data.materials.iter().enumerate() {
+            let handle = self.assets.get_or_add(material_key(path, index), || {
+                Material::from_gltf(index, material, &textures)
+            });
+            if let Some(current) = self.assets.get_mut(&handle) {
+                *current = Material::from_gltf(index, material, &textures);
+            }
+        }
+        let instances: Vec<_> = self
+            .models
+            .iter()
+            .filter(|(_, (_, model))| model.id() == scene)
+            .map(|(root, (_, model))| (*root, model.clone()))
+            .collect();
+        for (root, model) in instances {
+            self.spawn_model(root, path, model);
+        }
+    }
+
+    /// Spawns the default scene of a loaded model below `root`. Nodes spawned
+    /// from the model before are updated, those it no longer has are removed.
+    fn spawn_model(&mut self, root: NodeHandle, path: &Path, model: Handle<Scene>) {
+        let Some(loaded) = self.assets.get(&model).cloned() else {
+            return;
+        };
+        let textures = self.model_textures(path, &loaded);
+        let Scene { data, bin, .. } = loaded;
+        let reuse = self
+            .model_nodes(root, &self.node_links())
+            .into_iter()
+            .map(|(node, gltf_node)| (gltf_node, node))
+            .collect();
+        let gltf_data = &*data;
+        // shared by the instances of the model, editing one edits all
+        let materials = gltf_data
+            .materials
+            .iter()
+            .enumerate()
+            .map(|(index, material)| {
+                self.assets.get_or_add(material_key(path, index), || {
+                    Material::from_gltf(index, material, &textures)
+                })
+            })
+            .collect();
+        let mut scene = GltfScene {
+            path,
+            data: gltf_data,
+            bin: &bin,
+            materials,
+            lights: scene_lights(gltf_data),
+            cameras: gltf_data
+                .cameras
+                .iter()
+                .enumerate()
+                .map(|(index, camera)| SceneCamera::from_gltf(index, camera))
+                .collect(),
+            reuse,
+        };
+        let default_scene = gltf_data.default_scene.unwrap_or(0);
+        for node_index in gltf_data
+            .scenes
+            .get(default_scene)
+            .map_or(&[][..], |scene| &scene.nodes)
+        {
+            traverse_node(
+                *node_index,
+                Some(root),
+                &mut scene,
+                &mut self.uploads,
+                &mut self.assets,
+                &mut self.graph,
+                &mut self.world,
+            );
+        }
+        for node in scene.reuse.into_values() {
+            self.graph.remove(node);
+        }
+        self.despawn_unlinked();
+        self.models.insert(root, (path.to_path_buf(), model));
+        self.apply_overrides(root);
+    }
+
+    /// The textures of a model by glTF texture index. Images uploaded before are
+    /// shared, those freed since are queued again.
+    fn model_textures(&mut self, path: &Path, model: &Scene) -> Vec<Option<Handle<Texture>>> {
+        let images: Vec<_> = model
+            .images
+            .iter()
+            .enumerate()
+            .map(|(image, image_data)| {
+                let image_data = image_data.as_ref()?;
+                let (texture, new) = self.assets.reserve::<Texture>(texture_key(path, image));
+                if new {
+                    self.uploads
+                        .push(Upload::Texture(texture.clone(), image_data.clone()));
+                }
+                Some(texture)
+            })
+            .collect();
+        model
+            .data
+            .textures
+            .iter()
+            .map(|texture| images.get(texture.source?)?.clone())
+            .collect()
+    }
+
+    /// The nodes spawned from the model below `root` with their glTF node,
+    /// nodes placed below them that are not of the model are left out.
+    fn model_nodes(
+        &self,
+        root: NodeHandle,
+        links: &HashMap<NodeHandle, usize>,
+    ) -> Vec<(NodeHandle, usize)> {
+        let mut nodes = Vec::new();
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            let children = self.graph.get(node).map_or(&[][..], |node| node.children());
+            for child in children {
+                if let Some(gltf_node) = links.get(child) {
+                    nodes.push((*child, *gltf_node));
+                    stack.push(*child);
+                }
+            }
+        }
+        nodes
+    }
+
+    /// Moves the nodes of the model below `root` to where the scene file placed them.
+    fn apply_overrides(&mut self, root: NodeHandle) {
+        let Some(overrides) = self.overrides.get(&root) else {
+            return;
+        };
+        for (node, gltf_node) in self.model_nodes(root, &self.node_links()) {
+            if let Some(node_override) = overrides.iter().find(|o| o.node == gltf_node) {
+                self.graph.set_transform(node, node_override.transform);
+            }
+        }
+    }
+
+    /// Whether the node was spawned from a model, the model's nodes are not
+    /// saved, so nothing placed below them would be.
+    pub fn is_model_node(&self, handle: NodeHandle) -> bool {
+        let mut linked = false;
+        self.world.query::<&NodeLink>().for_each(|_, link| {
+            linked |= link.node == handle;
+        });
+        linked
+    }
+
+    /// The scene file object of a node, `None` for nodes that are not attached.
+    fn scene_object(
+        &self,
+        handle: NodeHandle,
+        base_dir: &Path,
+        links: &HashMap<NodeHandle, usize>,
+    ) -> Option<SceneObject> {
+        let node = self.graph.get(handle)?;
+        let model = self.models.get(&handle);
+        // the model's nodes come back with the model, only where they were moved is kept
+        let overrides = match model.and_then(|(_, scene)| self.assets.get(scene)) {
+            Some(Scene { data, .. }) => self
+                .model_nodes(handle, links)
+                .into_iter()
+                .filter_map(|(node, gltf_node)| {
+                    let transform = *self.graph.get(node)?.transform();
+                    let original = node_transform(data.nodes.get(gltf_node)?);
+                    (transform != original).then_some(NodeOverride {
+                        node: gltf_node,
+                        transform,
+                    })
+                })
+                .collect(),
+            // still loading
+            None => self.overrides.get(&handle).cloned().unwrap_or_default(),
+        };
+        let children = node
+            .children()
+            .iter()
+            .filter(|child| !links.contains_key(child))
+            .filter_map(|child| self.scene_object(*child, base_dir, links))
+            .collect();
+        Some(SceneObject {
+            name: node.name.clone(),
+            transform: *node.transform(),
+            model: model
+                .map(|(model, _)| model.strip_prefix(base_dir).unwrap_or(model).to_path_buf()),
+            light: node.light.clone(),
+            camera: node.camera.clone(),
+            overrides,
+            children,
+        })
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use std::{
+        path::{Path, PathBuf},
+        time::Duration,
+    };
+
+    use super::{
+        graph::Material,
+        models::{obj, renderable::SceneRenderData},
+        scene_file::{SceneFile, DEFAULT_SCENE_PATH},
+        OpenMode, SceneManager,
+    };
+    use crate::runtime::{
+        core::mathematics::transform::Transform, resources::file_watcher::PollWatcher,
+    };
+
+    #[test]
+    fn scene_loads_in_background() {
+        let mut scene_manager = SceneManager::new();
+        scene_manager.load_scene(DEFAULT_SCENE_PATH).unwrap();
+        // the objects are there at once, their models once read
+        assert_eq!(scene_manager.graph.node_count(), 1);
+        assert_eq!(scene_manager.progress().models, 1);
+        let start = std::time::Instant::now();
+        while scene_manager.progress().models > 0 {
+            assert!(scene_manager.finish_loading().is_empty());
+            assert!(start.elapsed().as_secs() < 10, "model not read in time");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let nodes = scene_manager.graph.node_count();
+        assert!(nodes > 1);
+        let meshes = scene_manager.assets.len::<SceneRenderData>();
+        let progress = scene_manager.progress();
+        // buffers are created on the render thread, placeholders are drawn meanwhile
+        assert_eq!((progress.uploaded, progress.uploads), (0, meshes));
+        assert!(meshes > 0 && !progress.done());
+        assert_eq!(scene_manager.assets.iter::<SceneRenderData>().count(), 0);
+
+        // loaded again, the model is not read twice
+        scene_manager.load_scene(DEFAULT_SCENE_PATH).unwrap();
+        assert!(scene_manager.finish_loading().is_empty());
+        assert_eq!(scene_manager.graph.node_count(), nodes);
+        assert_eq!(scene_manager.assets.len::<SceneRenderData>(), meshes);
+        assert_eq!(scene_manager.progress().uploads, meshes);
+
+        assert!(scene_manager.load_scene("missing.json").is_err());
+        assert_eq!(scene_manager.graph.node_count(), nodes);
+    }
+
+    #[test]
+    fn scene_reloads_in_place() {
+        // models are reported once read
+        fn wait_for_model(scene_manager: &mut SceneManager) -> Vec<(PathBuf, Result<(), String>)> {
+            let start = std::time::Instant::now();
+            loop {
+                let finished = scene_manager.finish_loading();
+                if !finished.is_empty() {
+                    return finished;
+                }
+                assert!(start.elapsed().as_secs() < 10, "model not read in time");
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("ruccolo_reload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (model, scene) = (dir.join("shape.obj"), dir.join("scene.json"));
+        std::fs::write(&model, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let objects = |second: &str| {
+            format!(
+                r#"{{"objects": [{{"name": "Shape", "model": "shape.obj"}}, {{"name": "{}"}}]}}"#,
+                second
+            )
+        };
+        std::fs::write(&scene, objects("Light")).unwrap();
+
+        let mut scene_manager = SceneManager::new();
+        scene_manager.set_watcher(Box::new(PollWatcher::new(Duration::ZERO)));
+        scene_manager.load_scene(&scene).unwrap();
+        let start = std::time::Instant::now();
+        while scene_manager.progress().models > 0 {
+            scene_manager.finish_loading();
+            assert!(start.elapsed().as_secs() < 10, "model not read in time");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let [shape, light] = [0, 1].map(|index| scene_manager.graph.roots()[index]);
+        let shape_node = scene_manager.graph.get(shape).unwrap().children()[0];
+        let triangles = |scene_manager: &SceneManager| {
+            let node = scene_manager.graph.get(shape_node).unwrap();
+            node.mesh.as_ref().unwrap().primitives[0].triangles
+        };
+        assert_eq!(triangles(&scene_manager), 1);
+        assert!(scene_manager.reload_changed().is_empty());
+
+        // the model's node stays, with the new mesh
+        std::fs::write(
+            &model,
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf 1 2 3\nf 2 4 3\n",
+        )
+        .unwrap();
+        assert!(scene_manager.reload_changed().is_empty());
+        assert_eq!(
+            wait_for_model(&mut scene_manager),
+            vec![(model.clone(), Ok(()))]
+        );
+        assert_eq!(
+            scene_manager.graph.get(shape).unwrap().children(),
+            &[shape_node]
+        );
+        assert_eq!(triangles(&scene_manager), 2);
+        assert_eq!(scene_manager.progress().uploads, 2);
+
+        // a broken file leaves the model as it was
+        std::fs::write(&model, "f 1 2 3\n").unwrap();
+        scene_manager.reload_changed();
+        assert!(wait_for_model(&mut scene_manager)[0].1.is_err());
+        assert_eq!(triangles(&scene_manager), 2);
+
+        // the model kept can still be added, and both instances follow the fixed file
+        scene_manager.open_model(model.clone(), OpenMode::Add);
+        assert_eq!(
+            wait_for_model(&mut scene_manager),
+            vec![(model.clone(), Ok(()))]
+        );
+        let added = scene_manager.graph.roots()[2];
+        let added_node = scene_manager.graph.get(added).unwrap().children()[0];
+        std::fs::write(&model, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        scene_manager.reload_changed();
+        assert_eq!(
+            wait_for_model(&mut scene_manager),
+            vec![(model.clone(), Ok(()))]
+        );
+        assert_eq!(triangles(&scene_manager), 1);
+        let node = scene_manager.graph.get(added_node).unwrap();
+        assert_eq!(node.mesh.as_ref().unwrap().primitives[0].triangles, 1);
+
+        // objects of the scene file are matched by name, their models are not read again
+        std::fs::write(&scene, objects("Camera")).unwrap();
+        assert_eq!(
+            scene_manager.reload_changed(),
+            vec![(scene.clone(), Ok(()))]
+        );
+        let roots = scene_manager.graph.roots();
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[0], shape);
+        assert!(!scene_manager.graph.contains(light));
+        assert_eq!(scene_manager.graph.get(roots[1]).unwrap().name, "Camera");
+        assert!(scene_manager.graph.contains(shape_node));
+        assert_eq!(scene_manager.progress().models, 0);
+
+        std::fs::write(&scene, "{").unwrap();
+        assert!(scene_manager.reload_changed()[0].1.is_err());
+        assert_eq!(scene_manager.graph.node_count(), 3);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn scene_saves_what_was_edited() {
+        fn load(path: &Path) -> SceneManager {
+            let mut scene_manager = SceneManager::new();
+            scene_manager.load_scene(path).unwrap();
+            let start = std::time::Instant::now();
+            while scene_manager.progress().models > 0 {
+                scene_manager.finish_loading();
+                assert!(start.elapsed().as_secs() < 10, "model not read in time");
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            scene_manager
+        }
+
+        let dir = std::env::temp_dir().join(format!("ruccolo_save_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (model, scene) = (dir.join("shape.obj"), dir.join("scene.json"));
+        std::fs::write(&model, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        std::fs::write(
+            &scene,
+            r#"{
+                "viewpoint": {"position": [0, 0, 5], "look_at": [0, 0, 2]},
+                "objects": [{"name": "Shape", "model": "shape.obj"}]
+            }"#,
+        )
+        .unwrap();
+
+        // the file has the point looked at, the camera the direction
+        let mut scene_manager = load(&scene);
+        assert_eq!(scene_manager.camera().lookat.0, [0.0, 0.0, -1.0, 0.0]);
+        let shape = scene_manager.graph.roots()[0];
+        let shape_node = scene_manager.graph.get(shape).unwrap().children()[0];
+        let moved = Transform {
+            translation: [1.0, 2.0, 3.0],
+            ..Default::default()
+        };
+        scene_manager.graph.set_transform(shape_node, moved);
+        scene_manager
+            .graph
+            .spawn("Extra".to_string(), Some(shape), Transform::default());
+        assert!(scene_manager.is_model_node(shape_node));
+        assert!(!scene_manager.is_model_node(shape));
+        scene_manager.save_scene(&scene).unwrap();
+
+        let saved = SceneFile::load(&scene).unwrap();
+        assert_eq!(saved.viewpoint.unwrap().look_at, [0.0, 0.0, 4.0]);
+        assert_eq!(saved.objects[0].overrides.len(), 1);
+        assert_eq!(saved.objects[0].children.len(), 1);
+
+        let scene_manager = load(&scene);
+        assert_eq!(scene_manager.camera().lookat.0, [0.0, 0.0, -1.0, 0.0]);
+        let shape = scene_manager.graph.roots()[0];
+        let children = scene_manager.graph.get(shape).unwrap().children();
+        assert_eq!(children.len(), 2);
+        let names: Vec<_> = children
+            .iter()
+            .map(|child| scene_manager.graph.get(*child).unwrap())
+            .map(|node| (node.name.as_str(), *node.transform()))
+            .collect();
+        assert!(names.contains(&("Extra", Transform::default())));
+        assert!(names.iter().any(|(_, transform)| *transform == moved));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn model_images_become_textures() {
+        let instance = wgpu::Instance::default();
+        let Some(adapter) = pollster::block_on(instance.request_adapter(&Default::default()))
+        else {
+            log::warn!("No adapter, not uploading textures");
+            return;
+        };
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&Default::default(), None)).unwrap();
+
+        // a triangle whose material has a base color image of two pixels
+        let dir = std::env::temp_dir().join(format!("ruccolo_images_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&[255, 0, 0, 0, 0, 255])
+            .unwrap();
+        std::fs::write(dir.join("color.png"), png).unwrap();
+        let (content, bin_data) =
+            obj::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n", "Tri").unwrap();
+        std::fs::write(dir.join("tri.bin"), bin_data).unwrap();
+        let mut model = serde_json::to_value(&content).unwrap();
+        model["buffers"][0]["uri"] = "tri.bin".into();
+        model["meshes"][0]["primitives"][0]["material"] = 0.into();
+        model["materials"] = serde_json::json!([
+            { "pbrMetallicRoughness": { "baseColorTexture": { "index": 0 } } }
+        ]);
+        model["textures"] = serde_json::json!([{ "source": 0 }]);
+        model["images"] = serde_json::json!([{ "uri": "color.png" }]);
+        let path = dir.join("tri.gltf");
+        std::fs::write(&path, serde_json::to_vec(&model).unwrap()).unwrap();
+
+        let mut scene_manager = SceneManager::new();
+        scene_manager.open_model(path.clone(), OpenMode::Add);
+        let start = std::time::Instant::now();
+        while scene_manager.progress().models > 0 {
+            scene_manager.finish_loading();
+            assert!(start.elapsed().as_secs() < 10, "model not read in time");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let (_, material) = scene_manager.assets.iter::<Material>().next().unwrap();
+        let texture = material.base_color_texture.clone().unwrap();
+        // decoded on the worker, uploaded with the meshes
+        assert!(scene_manager.assets.get(&texture).is_none());
+        assert_eq!(scene_manager.progress().uploads, 2);
+        while !scene_manager.progress().done() {
+            scene_manager.upload_assets(&device, &queue, Duration::ZERO);
+        }
+        let size = scene_manager.assets.get(&texture).unwrap().texture.size();
+        assert_eq!((size.width, size.height), (2, 1));
 
-        // let mut color_data = Vec::new();
-        // for material in materials.iter() {
-        //     color_data.push(material.ambient.unwrap());
-        // }
-        // let color_buff = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        //     label: None,
-        //     contents: bytemuck::cast_slice(&color_data),
-        //     usage: wgpu::BufferUsages::STORAGE,
-        // });
-
-        // let bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        //     label: Some("Bindgroup"),
-        //     layout: (&bg_layout),
-        //     entries: &[wgpu::BindGroupEntry {
-        //         binding: 0,
-        //         resource: wgpu::BindingResource::Buffer(color_buff.as_entire_buffer_binding()),
-        //     }],
-        // });
-        // bindgroup_list.push(bindgroup);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
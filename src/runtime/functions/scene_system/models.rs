@@ -1,37 +1,393 @@
 pub mod gltf;
+pub mod image;
+pub mod obj;
 pub mod renderable;
 
-use std::{fmt::Debug, io::Read, path::Path};
+use std::{
+    fmt::Debug,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
+};
+
+use crate::runtime::resources::asset_system::Asset;
+
+use self::{
+    gltf::{GltfAccessorComponentType, GltfAccessorType, GltfData, GltfMeshPrimitiveAttr},
+    image::ImageData,
+    renderable::MeshData,
+};
 
-// pub struct ModelLoader;
 const BUFFER_SIZE: usize = 8 * 1024;
 
-pub fn load<P: AsRef<Path> + Debug + Clone>(
-    gltf_path: P,
-    bin_path: P,
-) -> (gltf::GltfData, Vec<u8>) {
-    let gltf_file = std::fs::File::open(gltf_path.clone()).expect(&format!(
-        "Unable to open the gltf file: {:?}",
-        gltf_path.clone()
-    ));
+/// Extensions of the model files `load` reads.
+pub const MODEL_EXTENSIONS: [&str; 3] = ["gltf", "glb", "obj"];
+
+// little endian "glTF", "JSON" and "BIN\0"
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+/// A model file as read by `load`, the scene asset of the `AssetServer`.
+///
+/// Cheap to clone, so nodes can be spawned from it while adding its meshes to the server.
+#[derive(Debug, Clone)]
+pub struct Scene {
+    pub data: Arc<GltfData>,
+    /// The binary buffer the accessors read from.
+    pub bin: Arc<[u8]>,
+    /// The decoded images, `None` where an image could not be read.
+    pub images: Arc<[Option<ImageData>]>,
+}
+
+impl Asset for Scene {}
+
+/// Whether `path` names a file `load` reads, by its extension.
+pub fn is_model(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        MODEL_EXTENSIONS
+            .iter()
+            .any(|model| extension.eq_ignore_ascii_case(model))
+    })
+}
+
+/// Reads a model as glTF and its binary buffer: a `.gltf` file with the buffer
+/// in a file next to it, a `.glb` file, or an `.obj` file converted to glTF.
+/// Its images are decoded too, see `image::decode_images`.
+///
+/// Models the renderer can not draw are an error here rather than a panic later.
+pub fn load<P: AsRef<Path> + Debug>(path: P) -> Result<Scene, Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+    let (content, bin_data) = match extension.as_deref() {
+        Some("gltf") => load_gltf(path)?,
+        Some("glb") => parse_glb(&std::fs::read(path)?)?,
+        Some("obj") => obj::parse(&std::fs::read_to_string(path)?, &model_name(path))?,
+        _ => {
+            return Err(format!(
+                "Unsupported model file, expected one of {:?}",
+                MODEL_EXTENSIONS
+            )
+            .into())
+        }
+    };
+    validate(&content, &bin_data)?;
+    let images = image::decode_images(path, &content, &bin_data);
+    Ok(Scene {
+        data: Arc::new(content),
+        bin: bin_data.into(),
+        images: images.into(),
+    })
+}
+
+/// The files a model loaded from `path` was read from, the model, and the binary
+/// buffer and images of a `.gltf`.
+pub fn source_files(path: &Path, scene: &Scene) -> Vec<PathBuf> {
+    let buffer = scene.data.buffers.first();
+    let bin = buffer.and_then(|buffer| buffer.uri.as_deref());
+    let images = scene
+        .data
+        .images
+        .iter()
+        .filter_map(|image| image.uri.as_deref());
+    let files = bin
+        .into_iter()
+        .chain(images)
+        .filter(|uri| !uri.starts_with("data:"))
+        .map(|uri| path.parent().unwrap_or(Path::new("")).join(uri));
+    std::iter::once(path.to_path_buf()).chain(files).collect()
+}
+
+/// The file name without extension, what a model is called in the scene.
+pub fn model_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn load_gltf(gltf_path: &Path) -> Result<(GltfData, Vec<u8>), Box<dyn std::error::Error>> {
+    let gltf_file = std::fs::File::open(gltf_path)?;
     let reader = std::io::BufReader::new(gltf_file);
-    let content: gltf::GltfData = serde_json::from_reader(reader)
-        .expect(&format!("Unable to parser json file: {:?}", gltf_path));
-
-    let bin_length = content.buffers[0].byte_length;
-    let bin_file = std::fs::File::open(bin_path.clone()).expect(&format!(
-        "Unable to open the bin file: {:?}",
-        bin_path.clone()
-    ));
+    let content: GltfData = serde_json::from_reader(reader)?;
+
+    let buffer = content
+        .buffers
+        .first()
+        .ok_or("No buffer in the glTF file")?;
+    let uri = buffer.uri.as_deref().ok_or("No uri for the glTF buffer")?;
+    if uri.starts_with("data:") {
+        return Err("Embedded glTF buffers are not supported".into());
+    }
+    let bin_path = gltf_path.parent().unwrap_or(Path::new("")).join(uri);
+    let bin_file = std::fs::File::open(bin_path)?;
     let mut bin = std::io::BufReader::with_capacity(BUFFER_SIZE, bin_file);
-    let bin_data = read_binary(&mut bin, bin_length);
-    // println!("{:#?}", content.accessors.unwrap());
-    return (content, bin_data);
+    let bin_data = read_binary(&mut bin, buffer.byte_length)?;
+    Ok((content, bin_data))
 }
 
-fn read_binary(reader: &mut std::io::BufReader<std::fs::File>, length: usize) -> Vec<u8> {
+fn read_binary(
+    reader: &mut std::io::BufReader<std::fs::File>,
+    length: usize,
+) -> std::io::Result<Vec<u8>> {
     let mut res: Vec<u8> = Vec::with_capacity(length);
-    reader.read_to_end(&mut res).unwrap();
+    reader.read_to_end(&mut res)?;
     log::info!("Read bytes: {}/{}", res.len(), length);
-    return res;
+    Ok(res)
+}
+
+/// Splits a binary glTF file into its JSON and binary chunks.
+fn parse_glb(data: &[u8]) -> Result<(GltfData, Vec<u8>), Box<dyn std::error::Error>> {
+    let word = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    if word(0) != Some(GLB_MAGIC) {
+        return Err("Not a binary glTF file".into());
+    }
+    let mut json = None;
+    let mut bin_data = Vec::new();
+    // chunks follow the 12 byte header, each with its length and type first
+    let mut offset = 12;
+    while let (Some(length), Some(chunk_type)) = (word(offset), word(offset + 4)) {
+        let start = offset + 8;
+        let chunk = data
+            .get(start..start + length as usize)
+            .ok_or("Truncated binary glTF chunk")?;
+        match chunk_type {
+            GLB_CHUNK_JSON => json = Some(chunk),
+            GLB_CHUNK_BIN => bin_data = chunk.to_vec(),
+            _ => {}
+        }
+        offset = start + length as usize;
+    }
+    let content: GltfData =
+        serde_json::from_slice(json.ok_or("No JSON chunk in the binary glTF file")?)?;
+    if content
+        .buffers
+        .first()
+        .is_some_and(|buffer| buffer.uri.is_some())
+    {
+        return Err("Binary glTF files with external buffers are not supported".into());
+    }
+    Ok((content, bin_data))
+}
+
+/// Checks the meshes only use what the renderer draws, and that every index
+/// and byte range they use is inside the data.
+fn validate(content: &GltfData, bin_data: &[u8]) -> Result<(), String> {
+    let node_count = content.nodes.len();
+    for scene in content.scenes.iter() {
+        if let Some(node) = scene.nodes.iter().find(|node| **node >= node_count) {
+            return Err(format!("Scene node {} does not exist", node));
+        }
+    }
+    for (index, node) in content.nodes.iter().enumerate() {
+        if node.children.iter().any(|child| *child >= node_count) {
+            return Err(format!("Node {} has a child that does not exist", index));
+        }
+        if node.mesh.is_some_and(|mesh| mesh >= content.meshes.len()) {
+            return Err(format!("Node {} has a mesh that does not exist", index));
+        }
+    }
+    // the nodes must form trees, spawning them follows the children
+    let mut parents = vec![None; node_count];
+    for (index, node) in content.nodes.iter().enumerate() {
+        for child in node.children.iter() {
+            if let Some(parent) = parents[*child].replace(index) {
+                return Err(format!(
+                    "Node {} is a child of both node {} and node {}",
+                    child, parent, index
+                ));
+            }
+        }
+    }
+    for scene in content.scenes.iter() {
+        if let Some(node) = scene.nodes.iter().find(|node| parents[**node].is_some()) {
+            return Err(format!("Scene node {} is the child of another node", node));
+        }
+    }
+    let mut reached = vec![false; node_count];
+    let mut stack: Vec<usize> = (0..node_count)
+        .filter(|node| parents[*node].is_none())
+        .collect();
+    while let Some(node) = stack.pop() {
+        reached[node] = true;
+        stack.extend(content.nodes[node].children.iter());
+    }
+    if let Some(node) = reached.iter().position(|reached| !reached) {
+        return Err(format!("Node {} is below a cycle of nodes", node));
+    }
+
+    // byte range of an accessor, the renderer reads tightly packed data only
+    let accessor_bytes = |index: usize| -> Result<&GltfAccessorComponentType, String> {
+        let accessor = content
+            .accessors
+            .get(index)
+            .ok_or_else(|| format!("Accessor {} does not exist", index))?;
+        let view = accessor
+            .buffer_view
+            .and_then(|view| content.buffer_views.get(view))
+            .ok_or_else(|| format!("Accessor {} has no buffer view", index))?;
+        let stride = accessor.accessor_type.to_length() * accessor.component_type.to_typesize();
+        if view.buffer != 0 {
+            return Err("Models with more than one buffer are not supported".to_owned());
+        }
+        if view
+            .byte_stride
+            .is_some_and(|byte_stride| byte_stride != stride)
+        {
+            return Err("Interleaved vertex data is not supported".to_owned());
+        }
+        let view_start = view.byte_offset.unwrap_or(0);
+        let end = accessor.byte_offset.unwrap_or(0) + accessor.count * stride;
+        if end > view.byte_length || view_start + view.byte_length > bin_data.len() {
+            return Err(format!(
+                "Accessor {} reads past the end of the buffer",
+                index
+            ));
+        }
+        Ok(&accessor.component_type)
+    };
+
+    for mesh in content.meshes.iter() {
+        for primitive in mesh.primitives.iter() {
+            if renderable::topology(primitive.mode.unwrap_or_default()).is_none() {
+                return Err(format!(
+                    "{:?} primitives are not supported",
+                    primitive.mode.unwrap_or_default()
+                ));
+            }
+            let position = primitive
+                .attributes
+                .get(&GltfMeshPrimitiveAttr::Position)
+                .ok_or("Primitives without positions are not supported")?;
+            let component_type = accessor_bytes(*position)?;
+            if content.accessors[*position].accessor_type != GltfAccessorType::Vec3
+                || !matches!(component_type, GltfAccessorComponentType::Float)
+            {
+                return Err("Positions have to be three floats".to_owned());
+            }
+            for accessor in primitive.attributes.values() {
+                accessor_bytes(*accessor)?;
+            }
+            let indices = primitive
+                .indices
+                .ok_or("Primitives without indices are not supported")?;
+            match accessor_bytes(indices)? {
+                GltfAccessorComponentType::UnsignedShort
+                | GltfAccessorComponentType::UnsignedInt
+                    if content.accessors[indices].accessor_type == GltfAccessorType::Scalar => {}
+                _ => return Err("Indices have to be 16 or 32 bit integers".to_owned()),
+            }
+            if primitive
+                .material
+                .is_some_and(|material| material >= content.materials.len())
+            {
+                return Err("A primitive has a material that does not exist".to_owned());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A model read on a worker thread, see `ModelLoader`.
+pub struct LoadedModel<T> {
+    pub path: PathBuf,
+    /// What the model was requested with.
+    pub tag: T,
+    /// The model with the data of all its mesh primitives.
+    pub result: Result<(Scene, Vec<MeshData>), String>,
+}
+
+/// Reads and checks model files, decodes their images and prepares their mesh data
+/// on worker threads, so the frame goes on meanwhile. GPU buffers and textures are
+/// created on the main thread once polled.
+pub struct ModelLoader<T> {
+    sender: mpsc::Sender<LoadedModel<T>>,
+    receiver: mpsc::Receiver<LoadedModel<T>>,
+}
+
+impl<T> Default for ModelLoader<T> {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        ModelLoader { sender, receiver }
+    }
+}
+
+impl<T: Send + 'static> ModelLoader<T> {
+    pub fn request(&mut self, path: PathBuf, tag: T) {
+        let sender = self.sender.clone();
+        std::thread::spawn(move || {
+            let result = load(&path)
+                .map(|scene| {
+                    let meshes = MeshData::from_gltf(&scene.data, &scene.bin);
+                    (scene, meshes)
+                })
+                .map_err(|err| err.to_string());
+            // the receiver is gone when the engine shuts down meanwhile
+            let _ = sender.send(LoadedModel { path, tag, result });
+        });
+    }
+
+    /// The models finished since the last poll, in the order they finished.
+    pub fn poll(&mut self) -> Vec<LoadedModel<T>> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{obj, parse_glb, validate, GLB_CHUNK_BIN, GLB_CHUNK_JSON, GLB_MAGIC};
+
+    #[test]
+    fn glb_chunks() {
+        let (content, bin_data) =
+            obj::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n", "Tri").unwrap();
+        let mut json = serde_json::to_vec(&content).unwrap();
+        // chunks are padded to four bytes, JSON with spaces
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+        let mut glb = Vec::new();
+        let total = 12 + 8 + json.len() + 8 + bin_data.len();
+        for word in [
+            GLB_MAGIC,
+            2,
+            total as u32,
+            json.len() as u32,
+            GLB_CHUNK_JSON,
+        ] {
+            glb.extend_from_slice(&word.to_le_bytes());
+        }
+        glb.extend_from_slice(&json);
+        for word in [bin_data.len() as u32, GLB_CHUNK_BIN] {
+            glb.extend_from_slice(&word.to_le_bytes());
+        }
+        glb.extend_from_slice(&bin_data);
+
+        let (parsed, parsed_bin) = parse_glb(&glb).unwrap();
+        assert_eq!(parsed_bin, bin_data);
+        assert_eq!(parsed.meshes.len(), 1);
+        assert!(validate(&parsed, &parsed_bin).is_ok());
+
+        assert!(parse_glb(b"not a model").is_err());
+        assert!(parse_glb(&glb[..glb.len() - 4]).is_err());
+        // a buffer too short for the accessors is refused before anything reads it
+        assert!(validate(&parsed, &parsed_bin[..8]).is_err());
+
+        // nodes that are their own ancestors would be spawned forever
+        let mut nodes = serde_json::to_value(&parsed).unwrap();
+        nodes["nodes"] =
+            serde_json::json!([{ "mesh": 0 }, { "children": [2] }, { "children": [1] }]);
+        let cyclic = serde_json::from_value(nodes.clone()).unwrap();
+        assert!(validate(&cyclic, &parsed_bin)
+            .unwrap_err()
+            .contains("cycle"));
+        nodes["nodes"] =
+            serde_json::json!([{ "mesh": 0, "children": [1, 2] }, {}, { "children": [1] }]);
+        let shared = serde_json::from_value(nodes).unwrap();
+        assert!(validate(&shared, &parsed_bin).unwrap_err().contains("both"));
+    }
 }
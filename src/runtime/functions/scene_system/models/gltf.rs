@@ -29,7 +29,9 @@ pub struct GltfData {
     /// An array of bufferViews.
     #[serde(rename = "bufferViews", skip_serializing_if = "Vec::is_empty", default)]
     pub buffer_views: Vec<GltfBufferView>,
-    // TODO: Cameras
+    /// An array of cameras.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub cameras: Vec<GltfCamera>,
     /// An array of images.
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub images: Vec<GltfImage>,
@@ -59,6 +61,9 @@ pub struct GltfData {
     /// An array of textures.
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub textures: Vec<GltfTexture>,
+    /// Application-specific data.
+    #[serde(flatten)]
+    pub extras: GltfExtras,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
@@ -681,6 +686,117 @@ pub struct GltfOcclusionTextureInfo {
     pub extras: GltfExtras,
 }
 
+/// `KHR_lights_punctual` extension name, in the root `extensions` and in nodes.
+pub const KHR_LIGHTS_PUNCTUAL: &str = "KHR_lights_punctual";
+
+/// A camera's projection. A node may reference a camera to apply a transform to place the camera in the scene.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct GltfCamera {
+    /// The user-defined name of this object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Specifies if the camera uses a perspective or orthographic projection.
+    #[serde(rename = "type")]
+    pub camera_type: GltfCameraType,
+    /// A perspective camera containing properties to create a perspective projection matrix.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub perspective: Option<GltfCameraPerspective>,
+    /// An orthographic camera containing properties to create an orthographic projection matrix.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orthographic: Option<GltfCameraOrthographic>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum GltfCameraType {
+    #[serde(rename = "perspective")]
+    Perspective,
+    #[serde(rename = "orthographic")]
+    Orthographic,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct GltfCameraPerspective {
+    /// The floating-point aspect ratio of the field of view.
+    #[serde(rename = "aspectRatio", skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<f32>,
+    /// The floating-point vertical field of view in radians.
+    pub yfov: f32,
+    /// The floating-point distance to the far clipping plane, infinite when undefined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zfar: Option<f32>,
+    /// The floating-point distance to the near clipping plane.
+    pub znear: f32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct GltfCameraOrthographic {
+    /// The floating-point horizontal magnification of the view.
+    pub xmag: f32,
+    /// The floating-point vertical magnification of the view.
+    pub ymag: f32,
+    /// The floating-point distance to the far clipping plane.
+    pub zfar: f32,
+    /// The floating-point distance to the near clipping plane.
+    pub znear: f32,
+}
+
+/// A punctual light of the `KHR_lights_punctual` extension.
+/// https://github.com/KhronosGroup/glTF/tree/main/extensions/2.0/Khronos/KHR_lights_punctual
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct GltfLight {
+    /// The user-defined name of this object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// RGB value for light's color in linear space.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default = "default_vector3_one"
+    )]
+    pub color: Option<[f32; 3]>,
+    /// Brightness of light. The units that this is defined in depend on the type of light.
+    /// Point and spot lights use luminous intensity in candela (lm/sr)
+    /// while directional lights use illuminance in lux (lm/m2).
+    #[serde(skip_serializing_if = "Option::is_none", default = "default_float_one")]
+    pub intensity: Option<f32>,
+    /// Declares the type of the light.
+    #[serde(rename = "type")]
+    pub light_type: GltfLightType,
+    /// A distance cutoff at which the light's intensity may be considered to have reached zero.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<f32>,
+    /// Cone angles of a spot light.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spot: Option<GltfLightSpot>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum GltfLightType {
+    #[serde(rename = "directional")]
+    Directional,
+    #[serde(rename = "point")]
+    Point,
+    #[serde(rename = "spot")]
+    Spot,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct GltfLightSpot {
+    /// Angle in radians from centre of spotlight where falloff begins.
+    #[serde(
+        rename = "innerConeAngle",
+        skip_serializing_if = "Option::is_none",
+        default = "default_float_zero"
+    )]
+    pub inner_cone_angle: Option<f32>,
+    /// Angle in radians from centre of spotlight where falloff ends.
+    #[serde(
+        rename = "outerConeAngle",
+        skip_serializing_if = "Option::is_none",
+        default = "default_float_quarter_pi"
+    )]
+    pub outer_cone_angle: Option<f32>,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct GltfExtras {
     /// JSON object with extension-specific objects.
@@ -699,6 +815,14 @@ fn default_float_one() -> Option<f32> {
     Some(1.0)
 }
 
+fn default_float_zero() -> Option<f32> {
+    Some(0.0)
+}
+
+fn default_float_quarter_pi() -> Option<f32> {
+    Some(std::f32::consts::FRAC_PI_4)
+}
+
 fn default_float_half() -> Option<f32> {
     Some(0.5)
 }
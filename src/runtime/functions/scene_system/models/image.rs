@@ -0,0 +1,177 @@
+use std::{path::Path, sync::Arc};
+
+use super::gltf::{GltfData, GltfImage};
+
+// wgpu's default limit of 2D textures
+const MAX_IMAGE_SIZE: u32 = 8192;
+
+/// A decoded image, tightly packed rows of 8 bit RGBA in sRGB.
+///
+/// Cheap to clone, so uploads can be queued again from the model it was read with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageData {
+    pub size: [u32; 2],
+    pub pixels: Arc<[u8]>,
+}
+
+/// Decodes the images of a model, from files next to `path` or from buffer views.
+/// Images that can not be read are `None`, the materials using them are drawn
+/// with their factors only.
+pub fn decode_images(path: &Path, content: &GltfData, bin_data: &[u8]) -> Vec<Option<ImageData>> {
+    content
+        .images
+        .iter()
+        .enumerate()
+        .map(|(index, image)| {
+            read_image(path, content, bin_data, image)
+                .and_then(|bytes| decode(&bytes))
+                .map_err(|err| log::warn!("Image {} of {:?}: {}", index, path, err))
+                .ok()
+        })
+        .collect()
+}
+
+fn read_image(
+    path: &Path,
+    content: &GltfData,
+    bin_data: &[u8],
+    image: &GltfImage,
+) -> Result<Vec<u8>, String> {
+    if let Some(uri) = image.uri.as_deref() {
+        if uri.starts_with("data:") {
+            return Err("Embedded glTF images are not supported".to_owned());
+        }
+        let image_path = path.parent().unwrap_or(Path::new("")).join(uri);
+        return std::fs::read(&image_path).map_err(|err| format!("{:?}: {}", image_path, err));
+    }
+    let view = image
+        .buffer_view
+        .and_then(|view| content.buffer_views.get(view))
+        .ok_or("No uri or buffer view for the image")?;
+    let start = view.byte_offset.unwrap_or(0);
+    bin_data
+        .get(start..start + view.byte_length)
+        .filter(|_| view.buffer == 0)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| "The image reads past the end of the buffer".to_owned())
+}
+
+/// Decodes a PNG or JPEG file, told apart by their signatures rather than the
+/// mime type, which files often get wrong.
+fn decode(bytes: &[u8]) -> Result<ImageData, String> {
+    let (size, pixels) = if bytes.starts_with(b"\x89PNG") {
+        decode_png(bytes)?
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        decode_jpeg(bytes)?
+    } else {
+        return Err("Only PNG and JPEG images are supported".to_owned());
+    };
+    if size.iter().any(|side| *side == 0 || *side > MAX_IMAGE_SIZE) {
+        return Err(format!(
+            "Images have to be 1 to {} pixels wide and high, not {}x{}",
+            MAX_IMAGE_SIZE, size[0], size[1]
+        ));
+    }
+    Ok(ImageData {
+        size,
+        pixels: pixels.into(),
+    })
+}
+
+fn decode_png(bytes: &[u8]) -> Result<([u32; 2], Vec<u8>), String> {
+    let mut decoder = png::Decoder::new(bytes);
+    // palettes and transparent colors expanded, 16 bit channels cut to 8
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut data)
+        .map_err(|err| err.to_string())?;
+    data.truncate(info.buffer_size());
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return Err("Unexpanded PNG palette".to_owned()),
+    };
+    Ok(([info.width, info.height], to_rgba(&data, channels)))
+}
+
+fn decode_jpeg(bytes: &[u8]) -> Result<([u32; 2], Vec<u8>), String> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let data = decoder.decode().map_err(|err| err.to_string())?;
+    let info = decoder.info().ok_or("No JPEG header")?;
+    let channels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => 1,
+        jpeg_decoder::PixelFormat::RGB24 => 3,
+        format => return Err(format!("{:?} JPEG images are not supported", format)),
+    };
+    let size = [info.width as u32, info.height as u32];
+    Ok((size, to_rgba(&data, channels)))
+}
+
+/// Expands gray, gray and alpha, or RGB pixels to RGBA.
+fn to_rgba(data: &[u8], channels: usize) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(data.len() / channels * 4);
+    for pixel in data.chunks_exact(channels) {
+        match *pixel {
+            [gray] => rgba.extend_from_slice(&[gray, gray, gray, 255]),
+            [gray, alpha] => rgba.extend_from_slice(&[gray, gray, gray, alpha]),
+            [r, g, b] => rgba.extend_from_slice(&[r, g, b, 255]),
+            _ => rgba.extend_from_slice(pixel),
+        }
+    }
+    rgba
+}
+
+#[allow(unused_imports)]
+mod test {
+    use std::path::Path;
+
+    use super::{decode_images, ImageData};
+    use crate::runtime::functions::scene_system::models::{gltf::GltfData, obj};
+
+    #[test]
+    fn images_decode_to_rgba() {
+        // a gray and alpha PNG of two pixels in the binary buffer, after the mesh
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, 2, 1);
+        encoder.set_color(png::ColorType::GrayscaleAlpha);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&[10, 255, 20, 128])
+            .unwrap();
+        let (content, mut bin_data) =
+            obj::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n", "Tri").unwrap();
+        let mut model = serde_json::to_value(&content).unwrap();
+        model["bufferViews"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({
+                "buffer": 0,
+                "byteOffset": bin_data.len(),
+                "byteLength": png.len(),
+            }));
+        let view = model["bufferViews"].as_array().unwrap().len() - 1;
+        bin_data.extend_from_slice(&png);
+        model["images"] = serde_json::json!([
+            { "bufferView": view, "mimeType": "image/png" },
+            { "uri": "missing.png" },
+            { "bufferView": 0, "mimeType": "image/png" },
+        ]);
+        let content: GltfData = serde_json::from_value(model).unwrap();
+
+        let images = decode_images(Path::new("tri.gltf"), &content, &bin_data);
+        assert_eq!(
+            images[0],
+            Some(ImageData {
+                size: [2, 1],
+                pixels: vec![10, 10, 10, 255, 20, 20, 20, 128].into(),
+            })
+        );
+        // the others are left out, the model still loads
+        assert_eq!(images[1..], [None, None]);
+    }
+}
@@ -0,0 +1,122 @@
+use super::gltf::GltfData;
+
+/// Converts a Wavefront OBJ mesh to a glTF model of one node, so it loads like any other.
+///
+/// Only the positions and faces are read, polygons are split into triangle fans.
+/// Normals, texture coordinates, groups and materials are ignored.
+pub fn parse(source: &str, name: &str) -> Result<(GltfData, Vec<u8>), Box<dyn std::error::Error>> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    for (line_index, line) in source.lines().enumerate() {
+        let error = |message: &str| format!("Line {}: {}", line_index + 1, message);
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("v") => {
+                let mut position = [0.0; 3];
+                for value in position.iter_mut() {
+                    *value = words
+                        .next()
+                        .and_then(|word| word.parse().ok())
+                        .ok_or_else(|| error("Invalid vertex"))?;
+                }
+                positions.push(position);
+            }
+            Some("f") => {
+                // `v`, `v/vt`, `v//vn` or `v/vt/vn`, negative indices count from the last vertex
+                let face = words
+                    .map(|word| {
+                        let index: i64 = word.split('/').next()?.parse().ok()?;
+                        let index = if index < 0 {
+                            positions.len() as i64 + index
+                        } else {
+                            index - 1
+                        };
+                        (0..positions.len() as i64)
+                            .contains(&index)
+                            .then_some(index as u32)
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| error("Invalid face"))?;
+                if face.len() < 3 {
+                    return Err(error("Face with less than three vertices").into());
+                }
+                for corner in 1..face.len() - 1 {
+                    indices.extend_from_slice(&[face[0], face[corner], face[corner + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+    if indices.is_empty() {
+        return Err("No faces in the OBJ file".into());
+    }
+
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for position in positions.iter() {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(position[axis]);
+            max[axis] = max[axis].max(position[axis]);
+        }
+    }
+    let mut bin_data: Vec<u8> = bytemuck::cast_slice(&positions).to_vec();
+    let positions_length = bin_data.len();
+    bin_data.extend_from_slice(bytemuck::cast_slice(&indices));
+
+    let content = serde_json::from_value(serde_json::json!({
+        "asset": { "version": "2.0", "generator": "Ruccolo OBJ import" },
+        "buffers": [{ "byteLength": bin_data.len() }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": positions_length },
+            {
+                "buffer": 0,
+                "byteOffset": positions_length,
+                "byteLength": bin_data.len() - positions_length
+            }
+        ],
+        "accessors": [
+            {
+                "bufferView": 0, "componentType": 5126, "count": positions.len(),
+                "type": "VEC3", "min": min, "max": max
+            },
+            { "bufferView": 1, "componentType": 5125, "count": indices.len(), "type": "SCALAR" }
+        ],
+        "meshes": [{ "name": name, "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "mode": 4 }] }],
+        "nodes": [{ "name": name, "mesh": 0 }],
+        "scenes": [{ "nodes": [0] }],
+        "scene": 0
+    }))?;
+    Ok((content, bin_data))
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::parse;
+
+    #[test]
+    fn obj_to_gltf() {
+        let source = "\
+# a unit quad and a triangle on top
+o Quad
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vn 0 0 1
+f 1//1 2//1 3//1 4//1
+v 0.5 2 0
+f -3/1 -2/1 -1/1
+";
+        let (content, bin_data) = parse(source, "Shapes").unwrap();
+        assert_eq!(content.nodes[0].name.as_deref(), Some("Shapes"));
+        let positions = &content.accessors[0];
+        assert_eq!(positions.count, 5);
+        assert_eq!(positions.max.as_deref(), Some(&[1.0, 2.0, 0.0][..]));
+        let indices: &[u32] = bytemuck::cast_slice(&bin_data[5 * 12..]);
+        assert_eq!(indices, [0, 1, 2, 0, 2, 3, 2, 3, 4]);
+
+        assert!(parse("v 0 0\n", "Broken").is_err());
+        assert!(parse("v 0 0 0\nf 1 2 3\n", "Broken").is_err());
+        assert!(parse("v 0 0 0\n", "Empty").is_err());
+    }
+}
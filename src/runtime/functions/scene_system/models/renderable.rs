@@ -1,67 +1,153 @@
 use wgpu::util::DeviceExt;
 
-use crate::runtime::functions::scene_system::models::gltf::GltfAccessorType;
+use crate::runtime::{
+    functions::scene_system::models::gltf::{
+        GltfAccessorComponentType, GltfAccessorType, GltfMeshPrimitiveMode,
+    },
+    resources::asset_system::Asset,
+};
 
-use super::gltf::{GltfAccessor, GltfBufferView, GltfMeshPrimitiveAttr};
+use super::gltf::{GltfAccessor, GltfBufferView, GltfData, GltfMeshPrimitiveAttr};
 
+/// The vertex attributes of meshes, positions only so far.
+pub const POSITION_ATTRIBUTES: [wgpu::VertexAttribute; 1] =
+    wgpu::vertex_attr_array![0 => Float32x3];
+
+/// GPU buffers of one mesh primitive, the mesh asset of the `AssetServer`.
 #[derive(Debug, Default)]
-pub struct SceneRenderData<'a> {
-    pub vertexbuffer: Option<(wgpu::Buffer, wgpu::VertexBufferLayout<'a>)>,
+pub struct SceneRenderData {
+    pub vertexbuffer: Option<(wgpu::Buffer, wgpu::VertexBufferLayout<'static>)>,
     pub indexbuffer: Option<(wgpu::Buffer, wgpu::IndexFormat, u32)>, // len of index
+    pub topology: wgpu::PrimitiveTopology,
 }
 
-impl SceneRenderData<'_> {
-    pub fn process_primitive(
-        &mut self,
-        prim: &GltfMeshPrimitiveAttr,
-        bufferviews: &[GltfBufferView],
-        bin_data: &[u8],
-        device: &wgpu::Device,
-        accessor: &GltfAccessor,
-    ) {
-        match *prim {
-            GltfMeshPrimitiveAttr::Position => {
-                let (contents, stride, _) = process_buffer(bufferviews, bin_data, accessor);
-
-                let vertexbuffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: None,
-                    contents,
-                    usage: wgpu::BufferUsages::VERTEX,
-                });
-                let vertexbuffer_desc = wgpu::VertexBufferLayout {
-                    array_stride: stride,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &wgpu::vertex_attr_array![0 => Float32x3],
-                };
-
-                self.vertexbuffer = Some((vertexbuffer, vertexbuffer_desc));
+impl Asset for SceneRenderData {}
+
+impl SceneRenderData {
+    /// Uploads the buffers, the only part of loading a mesh done on the render thread.
+    pub fn new(device: &wgpu::Device, mesh_data: &MeshData) -> Self {
+        let vertexbuffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: &mesh_data.vertices,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let vertexbuffer_desc = wgpu::VertexBufferLayout {
+            array_stride: mesh_data.vertex_stride,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &POSITION_ATTRIBUTES,
+        };
+        let indexbuffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: &mesh_data.indices,
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        SceneRenderData {
+            vertexbuffer: Some((vertexbuffer, vertexbuffer_desc)),
+            indexbuffer: Some((indexbuffer, mesh_data.index_format, mesh_data.index_count)),
+            topology: mesh_data.topology,
+        }
+    }
+}
+
+/// The vertex and index data of a mesh primitive, read from the model on a
+/// loader thread and uploaded by `SceneRenderData::new` later.
+#[derive(Debug, Clone)]
+pub struct MeshData {
+    /// Indices of the glTF mesh and its primitive.
+    pub mesh: usize,
+    pub primitive: usize,
+    pub vertices: Vec<u8>,
+    pub vertex_stride: u64,
+    pub indices: Vec<u8>,
+    pub index_format: wgpu::IndexFormat,
+    pub index_count: u32,
+    pub topology: wgpu::PrimitiveTopology,
+}
+
+/// How the vertices of a glTF primitive are drawn, `None` for the modes wgpu
+/// has no topology for, line loops and triangle fans.
+pub fn topology(mode: GltfMeshPrimitiveMode) -> Option<wgpu::PrimitiveTopology> {
+    match mode {
+        GltfMeshPrimitiveMode::Points => Some(wgpu::PrimitiveTopology::PointList),
+        GltfMeshPrimitiveMode::Lines => Some(wgpu::PrimitiveTopology::LineList),
+        GltfMeshPrimitiveMode::LineStrip => Some(wgpu::PrimitiveTopology::LineStrip),
+        GltfMeshPrimitiveMode::Triangles => Some(wgpu::PrimitiveTopology::TriangleList),
+        GltfMeshPrimitiveMode::TriangleStrip => Some(wgpu::PrimitiveTopology::TriangleStrip),
+        GltfMeshPrimitiveMode::LineLoop | GltfMeshPrimitiveMode::TriangleFan => None,
+    }
+}
+
+impl MeshData {
+    /// The data of every primitive of a checked model, see `models::load`.
+    pub fn from_gltf(gltf_data: &GltfData, bin_data: &[u8]) -> Vec<MeshData> {
+        let mut meshes = Vec::new();
+        for (mesh, gltf_mesh) in gltf_data.meshes.iter().enumerate() {
+            for primitive in 0..gltf_mesh.primitives.len() {
+                meshes.push(MeshData::from_primitive(
+                    gltf_data, bin_data, mesh, primitive,
+                ));
             }
-            GltfMeshPrimitiveAttr::Normal => {}
-            GltfMeshPrimitiveAttr::Tangent => todo!(),
-            GltfMeshPrimitiveAttr::Weight => todo!(),
-            GltfMeshPrimitiveAttr::Color => todo!(),
-            GltfMeshPrimitiveAttr::MatrixPalette => todo!(),
-            GltfMeshPrimitiveAttr::Joint => todo!(),
-            GltfMeshPrimitiveAttr::TexCoord => todo!(),
         }
+        meshes
     }
 
-    pub fn process_indices(
-        &mut self,
-        bufferviews: &[GltfBufferView],
+    pub fn from_primitive(
+        gltf_data: &GltfData,
         bin_data: &[u8],
-        device: &wgpu::Device,
-        accessor: &GltfAccessor,
-    ) {
+        mesh: usize,
+        primitive: usize,
+    ) -> MeshData {
+        let buffer_views = &gltf_data.buffer_views;
+        let mesh_element = &gltf_data.meshes[mesh].primitives[primitive];
+        // other attributes are not drawn yet, models having them still load
+        let positions =
+            &gltf_data.accessors[mesh_element.attributes[&GltfMeshPrimitiveAttr::Position]];
+        let (vertices, vertex_stride, _) = process_buffer(buffer_views, bin_data, positions);
+
+        let accessor = &gltf_data.accessors[mesh_element.indices.unwrap()];
         assert_eq!(accessor.accessor_type, GltfAccessorType::Scalar);
-        let (contents, _, count) = process_buffer(bufferviews, bin_data, accessor);
+        let (indices, _, index_count) = process_buffer(buffer_views, bin_data, accessor);
+        let index_format = match accessor.component_type {
+            GltfAccessorComponentType::UnsignedShort => wgpu::IndexFormat::Uint16,
+            _ => wgpu::IndexFormat::Uint32,
+        };
+        MeshData {
+            mesh,
+            primitive,
+            vertices: vertices.to_vec(),
+            vertex_stride,
+            indices: indices.to_vec(),
+            index_format,
+            index_count,
+            // checked by `models::load`
+            topology: topology(mesh_element.mode.unwrap_or_default())
+                .unwrap_or(wgpu::PrimitiveTopology::TriangleList),
+        }
+    }
 
-        let indexbuffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: None,
-            contents,
-            usage: wgpu::BufferUsages::INDEX,
-        });
-        self.indexbuffer = Some((indexbuffer, wgpu::IndexFormat::Uint32, count));
+    /// A cube from 0 to 1 on every axis, drawn in place of meshes still loading.
+    pub fn unit_cube() -> MeshData {
+        let vertices: [[f32; 3]; 8] =
+            std::array::from_fn(|corner| std::array::from_fn(|axis| ((corner >> axis) & 1) as f32));
+        // two counter-clockwise triangles per face, seen from outside
+        let indices: [u16; 36] = [
+            0, 2, 1, 1, 2, 3, // -z
+            4, 5, 6, 5, 7, 6, // +z
+            0, 1, 4, 1, 5, 4, // -y
+            2, 6, 3, 3, 6, 7, // +y
+            0, 4, 2, 2, 4, 6, // -x
+            1, 3, 5, 3, 7, 5, // +x
+        ];
+        MeshData {
+            mesh: 0,
+            primitive: 0,
+            vertices: bytemuck::cast_slice(&vertices).to_vec(),
+            vertex_stride: std::mem::size_of::<[f32; 3]>() as _,
+            indices: bytemuck::cast_slice(&indices).to_vec(),
+            index_format: wgpu::IndexFormat::Uint16,
+            index_count: indices.len() as _,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+        }
     }
 }
 
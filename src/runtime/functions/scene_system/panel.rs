@@ -0,0 +1,372 @@
+use std::collections::HashSet;
+
+use crate::runtime::{
+    core::mathematics::transform::{Quaternion, Transform},
+    functions::{
+        editor_system::{command::EditCommand, gizmo::MIN_SCALE},
+        gui_system::{ui::Ui, Panel, PanelContext},
+    },
+};
+
+use super::{
+    graph::{LightKind, Material, NodeHandle, Projection, SceneGraph},
+    models::{renderable::SceneRenderData, Scene},
+};
+
+/// Camera state and the simulation rate.
+pub struct ScenePanel;
+
+impl Panel for ScenePanel {
+    fn title(&self) -> &str {
+        "Scene"
+    }
+
+    fn ui(&mut self, ui: &mut Ui, context: &mut PanelContext) {
+        let scene_manager = &mut *context.scene_manager;
+        let [x, y, z, _] = scene_manager.camera().position.0;
+        ui.value("Position", format!("{:.2}, {:.2}, {:.2}", x, y, z));
+        let [x, y, z, _] = scene_manager.camera().lookat.0;
+        ui.value("Look at", format!("{:.2}, {:.2}, {:.2}", x, y, z));
+        ui.value("Entities", scene_manager.world.entity_count());
+        let assets = &scene_manager.assets;
+        ui.value(
+            "Assets",
+            format!(
+                "{} meshes, {} materials, {} models",
+                assets.len::<SceneRenderData>(),
+                assets.len::<Material>(),
+                assets.len::<Scene>()
+            ),
+        );
+        let progress = scene_manager.progress();
+        if !progress.done() {
+            ui.value(
+                "Loading",
+                format!(
+                    "{} models, {}/{} meshes",
+                    progress.models, progress.uploaded, progress.uploads
+                ),
+            );
+        }
+        ui.value(
+            "Update rate",
+            format!("{:.0} Hz", 1.0 / scene_manager.timestep.step()),
+        );
+        if ui.button("Reset camera") {
+            scene_manager.reset_camera();
+        }
+        ui.separator();
+        let Some(path) = scene_manager.scene_path.clone() else {
+            ui.label("No scene file");
+            return;
+        };
+        ui.value("File", path.display());
+        if ui.button("Save scene") {
+            match scene_manager.save_scene(&path) {
+                Ok(()) => log::info!("Saved scene {:?}", path),
+                Err(err) => log::error!("Unable to save scene {:?}: {}", path, err),
+            }
+        }
+    }
+}
+
+/// The node tree of the loaded scene, clicking a node selects it for the inspector.
+#[derive(Default)]
+pub struct HierarchyPanel {
+    collapsed: HashSet<NodeHandle>,
+}
+
+impl HierarchyPanel {
+    fn node_row(
+        &mut self,
+        ui: &mut Ui,
+        graph: &SceneGraph,
+        node: NodeHandle,
+        depth: usize,
+        selection: &mut Option<NodeHandle>,
+    ) {
+        let Some(scene_node) = graph.get(node) else {
+            return;
+        };
+        let has_children = !scene_node.children().is_empty();
+        let mut expanded = !self.collapsed.contains(&node);
+        let label = format!("{}##{:?}", scene_node.name, node);
+        if ui.tree_item(
+            &label,
+            depth,
+            has_children.then_some(&mut expanded),
+            *selection == Some(node),
+        ) {
+            *selection = Some(node);
+        }
+        if !expanded {
+            self.collapsed.insert(node);
+            return;
+        }
+        self.collapsed.remove(&node);
+        for child in scene_node.children() {
+            self.node_row(ui, graph, *child, depth + 1, selection);
+        }
+    }
+}
+
+impl Panel for HierarchyPanel {
+    fn title(&self) -> &str {
+        "Hierarchy"
+    }
+
+    fn ui(&mut self, ui: &mut Ui, context: &mut PanelContext) {
+        let editor_manager = &mut *context.editor_manager;
+        let scene_manager = &mut *context.scene_manager;
+        let history = &editor_manager.history;
+        let (undo_steps, redo_steps) = (history.undo_steps(), history.redo_steps());
+        if ui.button(&format!("Undo ({})##undo", undo_steps)) {
+            editor_manager.undo(scene_manager);
+        }
+        if ui.button(&format!("Redo ({})##redo", redo_steps)) {
+            editor_manager.redo(scene_manager);
+        }
+
+        let history = &mut editor_manager.history;
+        let selection = scene_manager
+            .selection
+            .filter(|node| scene_manager.graph.contains(*node));
+        // nothing below the nodes of a model is saved
+        let model_node = matches!(selection, Some(node) if scene_manager.is_model_node(node));
+        let graph = &mut scene_manager.graph;
+        let label = match selection {
+            Some(_) => "Add child",
+            None => "Add node",
+        };
+        if model_node {
+            ui.label("Model nodes take no children");
+        } else if ui.button(label) {
+            let name = format!("Node {}", graph.node_count());
+            let node = graph.spawn(name, selection, Transform::default());
+            if let Some(position) = graph.sibling_index(node) {
+                history.record(EditCommand::AddNode { node, position }, graph);
+            }
+            scene_manager.selection = Some(node);
+        }
+        if let Some(node) = selection {
+            if ui.button("Delete") {
+                if let Some(position) = graph.sibling_index(node) {
+                    history.execute(
+                        EditCommand::RemoveNode { node, position },
+                        graph,
+                        &mut scene_manager.assets,
+                    );
+                }
+                scene_manager.selection = None;
+            }
+        }
+        ui.separator();
+
+        let graph = &scene_manager.graph;
+        if graph.roots().is_empty() {
+            ui.label("No scene loaded");
+            return;
+        }
+        let mut selection = scene_manager.selection;
+        for root in graph.roots() {
+            self.node_row(ui, graph, *root, 0, &mut selection);
+        }
+        scene_manager.selection = selection;
+    }
+}
+
+/// Transform and components of the selected node. Edits of what is drawn
+/// apply live and go through the undo history, the rest is shown read-only.
+#[derive(Default)]
+pub struct InspectorPanel {
+    // euler angles as last shown, recomputing them from the quaternion while
+    // dragging would jump between equivalent angles
+    rotation: Option<(NodeHandle, Quaternion, [f32; 3])>,
+}
+
+impl Panel for InspectorPanel {
+    fn title(&self) -> &str {
+        "Inspector"
+    }
+
+    fn ui(&mut self, ui: &mut Ui, context: &mut PanelContext) {
+        let scene_manager = &mut *context.scene_manager;
+        let history = &mut context.editor_manager.history;
+        let graph = &mut scene_manager.graph;
+        let assets = &mut scene_manager.assets;
+        let Some((node, scene_node)) = scene_manager
+            .selection
+            .and_then(|node| Some((node, graph.get(node)?)))
+        else {
+            ui.label("Nothing selected");
+            return;
+        };
+
+        ui.value("Node", &scene_node.name);
+        let before = *scene_node.transform();
+        let mesh = scene_node.mesh.clone();
+        let light = scene_node.light.clone();
+        let camera = scene_node.camera.clone();
+        let mut transform = before;
+        let mut changed = ui.drag_values(
+            "Position",
+            &mut transform.translation,
+            0.01,
+            f32::MIN..=f32::MAX,
+        );
+        let mut euler = match self.rotation {
+            Some((cached_node, rotation, euler))
+                if cached_node == node && rotation == transform.rotation =>
+            {
+                euler
+            }
+            _ => transform.euler_degrees(),
+        };
+        if ui.drag_values("Rotation", &mut euler, 0.5, -180.0..=180.0) {
+            transform.set_euler_degrees(euler);
+            changed = true;
+        }
+        self.rotation = Some((node, transform.rotation, euler));
+        changed |= ui.drag_values("Scale", &mut transform.scale, 0.01, MIN_SCALE..=f32::MAX);
+        if changed {
+            history.execute(
+                EditCommand::Transform {
+                    node,
+                    before,
+                    after: transform,
+                },
+                graph,
+                assets,
+            );
+        }
+
+        if let Some(mesh) = mesh.filter(|mesh| !mesh.primitives.is_empty()) {
+            ui.separator();
+            let mut materials = Vec::new();
+            for (index, primitive) in mesh.primitives.iter().enumerate() {
+                let material = primitive
+                    .material
+                    .as_ref()
+                    .and_then(|material| assets.get(material));
+                ui.value(
+                    &format!("Primitive {}", index),
+                    // instances of a model share their buffers
+                    format!(
+                        "{} tris, {}, {} uses",
+                        primitive.triangles,
+                        material.map_or("default", |material| material.name.as_str()),
+                        assets.ref_count(&primitive.mesh)
+                    ),
+                );
+                if let (Some(id), Some(_)) = (primitive.material.as_ref(), material) {
+                    if !materials.contains(&id.id()) {
+                        materials.push(id.id());
+                    }
+                }
+            }
+            for (index, id) in materials.into_iter().enumerate() {
+                let before = assets.get(id).unwrap().clone();
+                let mut material = before.clone();
+                ui.separator();
+                ui.value("Material", &material.name);
+                let mut changed = ui.drag_values(
+                    &format!("Color##{}", index),
+                    &mut material.base_color,
+                    0.005,
+                    0.0..=1.0,
+                );
+                changed |= ui.drag_values(
+                    &format!("Emissive##{}", index),
+                    &mut material.emissive,
+                    0.005,
+                    0.0..=1.0,
+                );
+                // not shaded with yet, shown but not editable
+                ui.value(
+                    "Metallic / roughness",
+                    format!("{:.2} / {:.2}", material.metallic, material.roughness),
+                );
+                if let Some(texture) = material.base_color_texture.as_ref() {
+                    let size = assets.get(texture).map(|texture| texture.texture.size());
+                    ui.value(
+                        "Base color texture",
+                        size.map_or("loading".to_owned(), |size| {
+                            format!("{}x{}", size.width, size.height)
+                        }),
+                    );
+                }
+                if changed {
+                    history.execute(
+                        EditCommand::Material {
+                            material: id,
+                            before,
+                            after: material,
+                        },
+                        graph,
+                        assets,
+                    );
+                }
+            }
+        }
+
+        // the renderer does not shade with lights yet, they are shown but not editable
+        if let Some(light) = light {
+            ui.separator();
+            let kind = match light.kind {
+                LightKind::Directional => "directional",
+                LightKind::Point => "point",
+                LightKind::Spot { .. } => "spot",
+            };
+            ui.value("Light", format!("{} ({})", light.name, kind));
+            let [r, g, b] = light.color;
+            ui.value("Color", format!("{:.2} {:.2} {:.2}", r, g, b));
+            ui.value("Intensity", light.intensity);
+            if let Some(range) = light.range {
+                ui.value("Range", range);
+            }
+            if let LightKind::Spot {
+                inner_cone,
+                outer_cone,
+            } = light.kind
+            {
+                ui.value(
+                    "Cone",
+                    format!(
+                        "{:.1}° - {:.1}°",
+                        inner_cone.to_degrees(),
+                        outer_cone.to_degrees()
+                    ),
+                );
+            }
+        }
+
+        if let Some(camera) = camera {
+            ui.separator();
+            match camera.projection {
+                Projection::Perspective {
+                    yfov, znear, zfar, ..
+                } => {
+                    ui.value("Camera", format!("{} (perspective)", camera.name));
+                    ui.value("Field of view", format!("{:.1}°", yfov.to_degrees()));
+                    ui.value(
+                        "Clip",
+                        match zfar {
+                            Some(zfar) => format!("{} - {}", znear, zfar),
+                            None => format!("{} - inf", znear),
+                        },
+                    );
+                }
+                Projection::Orthographic {
+                    xmag,
+                    ymag,
+                    znear,
+                    zfar,
+                } => {
+                    ui.value("Camera", format!("{} (orthographic)", camera.name));
+                    ui.value("Extent", format!("{} x {}", xmag, ymag));
+                    ui.value("Clip", format!("{} - {}", znear, zfar));
+                }
+            }
+        }
+    }
+}
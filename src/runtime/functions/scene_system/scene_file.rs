@@ -0,0 +1,199 @@
+use std::{fmt::Debug, path::Path, path::PathBuf};
+
+use crate::runtime::core::{mathematics::transform::Transform, timestep::DEFAULT_UPDATE_RATE};
+
+use super::{
+    graph::{Light, SceneCamera},
+    models::model_name,
+};
+
+pub const DEFAULT_SCENE_PATH: &str = "assets/scenes/cornell_box.json";
+
+/// Settings of a scene that are not attached to a node.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Environment {
+    /// Linear RGBA the frame is cleared to.
+    pub clear_color: [f32; 4],
+    /// Fixed simulation steps per second.
+    pub update_rate: f32,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment {
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            update_rate: DEFAULT_UPDATE_RATE,
+        }
+    }
+}
+
+/// Where the viewer camera starts.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Viewpoint {
+    pub position: [f32; 3],
+    /// The point the camera looks at.
+    pub look_at: [f32; 3],
+}
+
+/// A node of an object's model placed elsewhere than in the model file.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct NodeOverride {
+    /// Index of the node in the model file.
+    pub node: usize,
+    pub transform: Transform,
+}
+
+/// A node of a scene file, an instance of a model and/or a light or camera.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SceneObject {
+    pub name: String,
+    pub transform: Transform,
+    /// Model file relative to the scene file, its nodes are loaded below this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub light: Option<Light>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera: Option<SceneCamera>,
+    /// Nodes of the model that were moved, applied whenever the model is loaded.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<NodeOverride>,
+    /// Nodes placed in the scene below this one, nodes of the model are not stored.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<SceneObject>,
+}
+
+impl SceneObject {
+    /// Model paths of this object and its children, in order.
+    pub fn models(&self) -> Vec<&Path> {
+        let mut models = Vec::new();
+        let mut stack = vec![self];
+        while let Some(object) = stack.pop() {
+            models.extend(object.model.as_deref());
+            stack.extend(object.children.iter().rev());
+        }
+        models
+    }
+}
+
+/// The native scene description, JSON that composes glTF models into a scene.
+///
+/// Models are referenced by path and instanced with their own transform, so
+/// one file can place the same model several times next to others.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SceneFile {
+    pub environment: Environment,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub viewpoint: Option<Viewpoint>,
+    pub objects: Vec<SceneObject>,
+}
+
+impl SceneFile {
+    pub fn load<P: AsRef<Path> + Debug>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = std::fs::File::open(path.as_ref())?;
+        let reader = std::io::BufReader::new(file);
+        let scene_file: SceneFile = serde_json::from_reader(reader)?;
+        let update_rate = scene_file.environment.update_rate;
+        if !(update_rate > 0.0 && update_rate.is_finite()) {
+            return Err(format!("Invalid update rate {}", update_rate).into());
+        }
+        Ok(scene_file)
+    }
+
+    /// A scene of one model, `model` relative to where the scene is loaded from.
+    pub fn with_model(model: &Path) -> Self {
+        SceneFile {
+            objects: vec![SceneObject {
+                name: model_name(model),
+                model: Some(model.to_path_buf()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{NodeOverride, SceneFile, SceneObject, Viewpoint, DEFAULT_SCENE_PATH};
+    use crate::runtime::{
+        core::mathematics::transform::Transform,
+        functions::scene_system::graph::{Light, LightKind},
+    };
+
+    #[test]
+    fn scene_file_round_trip() {
+        // left out fields take their defaults
+        let scene: SceneFile = serde_json::from_str(
+            r#"{
+                "objects": [
+                    { "name": "Box", "model": "box.gltf", "transform": { "translation": [1, 2, 3] } },
+                    {
+                        "name": "Lamps",
+                        "children": [
+                            { "model": "lamp.gltf" },
+                            { "name": "Sun", "light": {
+                                "name": "Sun", "kind": "Directional", "color": [1, 1, 1],
+                                "intensity": 3, "range": null
+                            } }
+                        ]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(scene.environment, Default::default());
+        assert_eq!(
+            scene.objects[0].transform,
+            Transform {
+                translation: [1.0, 2.0, 3.0],
+                ..Default::default()
+            }
+        );
+        let models: Vec<_> = scene.objects.iter().flat_map(SceneObject::models).collect();
+        assert_eq!(models, [Path::new("box.gltf"), Path::new("lamp.gltf")]);
+        assert_eq!(
+            scene.objects[1].children[1].light.as_ref().unwrap().kind,
+            LightKind::Directional
+        );
+
+        let mut edited = scene.clone();
+        edited.viewpoint = Some(Viewpoint {
+            position: [0.0, 1.0, 5.0],
+            look_at: [0.0; 3],
+        });
+        edited.objects[1].children[1].light.as_mut().unwrap().kind = LightKind::Spot {
+            inner_cone: 0.1,
+            outer_cone: 0.5,
+        };
+        edited.objects[0].overrides.push(NodeOverride {
+            node: 2,
+            transform: Transform {
+                scale: [2.0; 3],
+                ..Default::default()
+            },
+        });
+        let json = serde_json::to_string(&edited).unwrap();
+        assert_eq!(serde_json::from_str::<SceneFile>(&json).unwrap(), edited);
+
+        let default_scene = SceneFile::load(DEFAULT_SCENE_PATH).unwrap();
+        assert!(!default_scene.objects.is_empty());
+
+        // the simulation can not step at a rate of zero
+        let path = std::env::temp_dir().join(format!("ruccolo_rate_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"environment": {"update_rate": 0}}"#).unwrap();
+        assert!(SceneFile::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
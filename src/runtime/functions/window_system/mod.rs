@@ -4,10 +4,16 @@ pub struct WindowManager {
 
 #[profiling::all_functions]
 impl WindowManager {
-    pub fn new(event_loop: &winit::event_loop::EventLoop<()>) -> Self {
+    /// A hidden window still renders, used to take screenshots without showing up.
+    pub fn new(
+        event_loop: &winit::event_loop::EventLoop<()>,
+        size: [u32; 2],
+        visible: bool,
+    ) -> Self {
         let window = winit::window::WindowBuilder::new()
             .with_title("Ruccolo Engine")
-            .with_inner_size(winit::dpi::PhysicalSize::new(1280, 720))
+            .with_inner_size(winit::dpi::PhysicalSize::new(size[0], size[1]))
+            .with_visible(visible)
             .with_enabled_buttons(
                 winit::window::WindowButtons::CLOSE | winit::window::WindowButtons::MINIMIZE,
             )
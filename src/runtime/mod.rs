@@ -1,40 +1,147 @@
 use crate::runtime::functions::{
-    event_system::handle_event, render_system::fps_manager, scene_system::SceneManager,
+    editor_system::{panel::GizmoPanel, EditorManager},
+    event_system::{
+        action::{ActionMap, DEFAULT_BINDINGS_PATH},
+        gamepad, handle_event,
+        input_manager::InputManager,
+        replay::{InputMode, HEADLESS_ENV},
+        update_camera,
+    },
+    gui_system::GuiManager,
+    render_system::{fps_manager, panel::RenderPanel},
+    scene_system::{
+        panel::{HierarchyPanel, InspectorPanel, ScenePanel},
+        SceneManager,
+    },
 };
+use winit::event::Event;
 
-use self::functions::{render_system::RenderManager, window_system::WindowManager};
+use self::{
+    core::config::EngineConfig,
+    functions::{render_system::RenderManager, window_system::WindowManager},
+    resources::file_watcher,
+};
 
-mod core;
+pub mod core;
 mod functions;
 mod platforms;
 mod resources;
 mod tools;
 
+/// Frames rendered after the scene finished loading before the screenshot of
+/// `EngineConfig::screenshot` is taken, so the first frame timings have settled.
+const SCREENSHOT_FRAME: u32 = 3;
+
 #[inline]
-pub fn run() {
+pub fn run(config: EngineConfig) {
     log::info!("Engine runtime started.");
+    let input_mode = InputMode::from_env();
+    if std::env::var_os(HEADLESS_ENV).is_some() {
+        if let InputMode::Replay(_) = input_mode {
+            run_headless(input_mode);
+            return;
+        }
+        log::warn!("Headless mode needs an input replay, starting with a window.");
+    }
+
     let event_loop = winit::event_loop::EventLoop::new();
-    let window_manager = WindowManager::new(&event_loop);
+    let window_manager =
+        WindowManager::new(&event_loop, config.window_size, config.screenshot.is_none());
     let mut scene_manager = SceneManager::new();
+    let camera = *scene_manager.camera();
     let mut render_manager = pollster::block_on(RenderManager::new(
         &window_manager.window,
-        &scene_manager.camera,
+        &camera,
+        &config,
+        &mut scene_manager.assets,
     ));
-    scene_manager.load_scene(
-        &render_manager.gpu_context.device,
-        &mut render_manager.render_queue,
-        &mut render_manager.bindgroup,
-    );
+    // a screenshot is taken of the scene as it was started
+    if config.hot_reload && config.screenshot.is_none() {
+        scene_manager.set_watcher(file_watcher::default_backend());
+    }
+    if let Err(err) = scene_manager.load_scene(&config.scene) {
+        log::error!("Unable to load scene {:?}: {}", config.scene, err);
+    }
     let mut fps_manager = fps_manager::FpsManager::new();
+    fps_manager.set_target_fps(
+        std::env::var(fps_manager::TARGET_FPS_ENV)
+            .ok()
+            .and_then(|fps| fps.parse().ok()),
+    );
+    scene_manager.world.insert_resource(fps_manager);
+    let mut input_manager = InputManager::new(
+        ActionMap::load_or_default(DEFAULT_BINDINGS_PATH),
+        gamepad::default_backend(),
+        input_mode,
+    );
+    let mut gui_manager = GuiManager::new();
+    gui_manager.register_panel(Box::new(RenderPanel));
+    gui_manager.register_panel(Box::new(ScenePanel));
+    gui_manager.register_panel(Box::<HierarchyPanel>::default());
+    gui_manager.register_panel(Box::<InspectorPanel>::default());
+    gui_manager.register_panel(Box::new(GizmoPanel));
+    let mut editor_manager = EditorManager::new();
 
+    let mut screenshot = config.screenshot;
+    let mut frames = 0;
     event_loop.run(move |event, _, control_flow| {
+        // the frame is rendered on `MainEventsCleared`
+        let frame_end = matches!(event, Event::MainEventsCleared);
+        let mut exit = false;
+        // the scene is taken once all of it is loaded
+        if frame_end && screenshot.is_some() && scene_manager.progress().done() {
+            frames += 1;
+            if frames == SCREENSHOT_FRAME {
+                render_manager.request_screenshot_to(screenshot.take().unwrap());
+                exit = true;
+            }
+        }
         handle_event(
             event,
             control_flow,
-            &mut fps_manager,
             &window_manager,
             &mut scene_manager,
-            &render_manager,
-        )
+            &mut render_manager,
+            &mut input_manager,
+            &mut gui_manager,
+            &mut editor_manager,
+        );
+        if exit {
+            // scripts taking screenshots see when there is none
+            match render_manager.take_screenshot_result() {
+                Some(Ok(_)) => control_flow.set_exit(),
+                Some(Err(_)) | None => {
+                    log::error!("Exiting without a screenshot");
+                    control_flow.set_exit_with_code(1);
+                }
+            }
+        }
     });
 }
+
+/// Runs an input replay without window and GPU, only the simulation is stepped.
+fn run_headless(input_mode: InputMode) {
+    log::info!("Engine runtime started headless.");
+    let mut scene_manager = SceneManager::new();
+    // the same bindings as the recording run, or the actions replay differently
+    let mut input_manager = InputManager::new(
+        ActionMap::load_or_default(DEFAULT_BINDINGS_PATH),
+        None,
+        input_mode,
+    );
+    let mut frames = 0;
+    let start = std::time::Instant::now();
+    while let Some(delta_t) = input_manager.begin_frame(0.0) {
+        scene_manager.simulate(delta_t, |step, scene_manager| {
+            update_camera(step, &input_manager, scene_manager)
+        });
+        input_manager.end_frame(delta_t);
+        frames += 1;
+    }
+    log::info!(
+        "Replayed {} frames in {:?}, final camera position: {:?}",
+        frames,
+        start.elapsed(),
+        scene_manager.camera().position.0
+    );
+}
@@ -1 +1,533 @@
-pub struct AssetManager {}
+use std::{
+    any::{Any, TypeId},
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Weak},
+};
+
+/// A type the `AssetServer` stores.
+pub trait Asset: 'static {}
+
+/// Where an asset comes from, assets with equal keys are created once and shared.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AssetKey {
+    /// A file, the label names a part of it like `mesh0/1` and is empty for the whole file.
+    Path(PathBuf, String),
+    /// Hash of the data the asset is created from.
+    Hash(u64),
+}
+
+impl AssetKey {
+    pub fn path<P: AsRef<Path>>(path: P) -> Self {
+        AssetKey::Path(path.as_ref().to_path_buf(), String::new())
+    }
+
+    pub fn labeled<P: AsRef<Path>>(path: P, label: String) -> Self {
+        AssetKey::Path(path.as_ref().to_path_buf(), label)
+    }
+
+    pub fn hash_of<T: Hash + ?Sized>(data: &T) -> Self {
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        AssetKey::Hash(hasher.finish())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    /// With what went wrong.
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct RawId {
+    index: u32,
+    generation: u32,
+}
+
+/// Names an asset without keeping it alive, see `Handle::id`.
+pub struct AssetId<T> {
+    raw: RawId,
+    marker: PhantomData<fn() -> T>,
+}
+
+// derives would require `T` to implement them too
+impl<T> Clone for AssetId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for AssetId<T> {}
+
+impl<T> PartialEq for AssetId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<T> Eq for AssetId<T> {}
+
+impl<T> Hash for AssetId<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw.hash(state);
+    }
+}
+
+impl<T> std::fmt::Debug for AssetId<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AssetId({}v{})", self.raw.index, self.raw.generation)
+    }
+}
+
+// tells the server when the last handle of an asset is gone
+struct HandleInner {
+    type_id: TypeId,
+    raw: RawId,
+    dropped: mpsc::Sender<(TypeId, RawId)>,
+}
+
+impl Drop for HandleInner {
+    fn drop(&mut self) {
+        // the server is gone when the engine shuts down
+        let _ = self.dropped.send((self.type_id, self.raw));
+    }
+}
+
+/// Keeps an asset alive, the asset is freed by `AssetServer::free_unused`
+/// once the last handle to it is dropped.
+pub struct Handle<T> {
+    inner: Arc<HandleInner>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    #[inline]
+    pub fn id(&self) -> AssetId<T> {
+        AssetId {
+            raw: self.inner.raw,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle {
+            inner: self.inner.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner.raw == other.inner.raw
+    }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handle({:?})", self.id())
+    }
+}
+
+impl<T> From<&Handle<T>> for AssetId<T> {
+    fn from(handle: &Handle<T>) -> Self {
+        handle.id()
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    state: LoadState,
+    asset: Option<T>,
+    key: Option<AssetKey>,
+    // dead once all handles are dropped, the slot is freed with the next `free_unused`
+    handle: Weak<HandleInner>,
+}
+
+/// The assets of one type.
+struct Assets<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    keys: HashMap<AssetKey, u32>,
+}
+
+impl<T> Default for Assets<T> {
+    fn default() -> Self {
+        Assets {
+            slots: Vec::new(),
+            free: Vec::new(),
+            keys: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Assets<T> {
+    fn slot(&self, raw: RawId) -> Option<&Slot<T>> {
+        self.slots
+            .get(raw.index as usize)
+            .filter(|slot| slot.generation == raw.generation)
+    }
+
+    fn slot_mut(&mut self, raw: RawId) -> Option<&mut Slot<T>> {
+        self.slots
+            .get_mut(raw.index as usize)
+            .filter(|slot| slot.generation == raw.generation)
+    }
+}
+
+/// `Assets` of any type, so dropped handles can free them.
+trait AssetStorage {
+    fn free(&mut self, raw: RawId) -> bool;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Asset> AssetStorage for Assets<T> {
+    fn free(&mut self, raw: RawId) -> bool {
+        let Some(slot) = self.slot_mut(raw) else {
+            return false;
+        };
+        // handed out again by its key after the last handle was dropped
+        if slot.handle.strong_count() > 0 {
+            return false;
+        }
+        slot.generation += 1;
+        slot.asset = None;
+        if let Some(key) = slot.key.take() {
+            self.keys.remove(&key);
+        }
+        self.free.push(raw.index);
+        true
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Owns the meshes, textures, materials and scenes of the engine.
+///
+/// Assets are shared through reference counted `Handle`s, created once per
+/// `AssetKey` and freed when no handle is left. Assets loaded elsewhere, like
+/// on a worker thread, are reserved first and stay `LoadState::Loading` until finished.
+pub struct AssetServer {
+    assets: HashMap<TypeId, Box<dyn AssetStorage>>,
+    dropped_sender: mpsc::Sender<(TypeId, RawId)>,
+    dropped: mpsc::Receiver<(TypeId, RawId)>,
+}
+
+impl Default for AssetServer {
+    fn default() -> Self {
+        let (dropped_sender, dropped) = mpsc::channel();
+        AssetServer {
+            assets: HashMap::new(),
+            dropped_sender,
+            dropped,
+        }
+    }
+}
+
+impl AssetServer {
+    /// Stores an asset that is not shared by key.
+    pub fn add<T: Asset>(&mut self, asset: T) -> Handle<T> {
+        self.insert(None, LoadState::Loaded, Some(asset))
+    }
+
+    /// The asset of `key`, `create` makes it unless there already is one.
+    pub fn get_or_add<T: Asset>(&mut self, key: AssetKey, create: impl FnOnce() -> T) -> Handle<T> {
+        self.get_or_try_add(key, || Ok(create()))
+    }
+
+    /// Like `get_or_add`, a failed `create` is kept as `LoadState::Failed` while handles to it live.
+    pub fn get_or_try_add<T: Asset>(
+        &mut self,
+        key: AssetKey,
+        create: impl FnOnce() -> Result<T, String>,
+    ) -> Handle<T> {
+        if let Some(handle) = self.find(&key) {
+            return handle;
+        }
+        match create() {
+            Ok(asset) => self.insert(Some(key), LoadState::Loaded, Some(asset)),
+            Err(err) => self.insert(Some(key), LoadState::Failed(err), None),
+        }
+    }
+
+    /// The asset of `key`, a new one is `LoadState::Loading` until `finish`ed.
+    /// Returns whether it is new, only then it has to be loaded.
+    pub fn reserve<T: Asset>(&mut self, key: AssetKey) -> (Handle<T>, bool) {
+        match self.find(&key) {
+            Some(handle) => (handle, false),
+            None => (self.insert(Some(key), LoadState::Loading, None), true),
+        }
+    }
+
+    /// Completes a reserved asset, nothing happens when it was freed or completed meanwhile.
+    /// A failed reload leaves the asset as it was, `LoadState::Loaded`.
+    pub fn finish<T: Asset>(&mut self, id: impl Into<AssetId<T>>, result: Result<T, String>) {
+        let Some(slot) = self.storage_mut::<T>().slot_mut(id.into().raw) else {
+            return;
+        };
+        if slot.state != LoadState::Loading {
+            return;
+        }
+        match result {
+            Ok(asset) => {
+                slot.asset = Some(asset);
+                slot.state = LoadState::Loaded;
+            }
+            Err(_) if slot.asset.is_some() => slot.state = LoadState::Loaded,
+            Err(err) => slot.state = LoadState::Failed(err),
+        }
+    }
+
+    /// Makes the asset `LoadState::Loading` again so the next `finish` replaces it,
+    /// like when its file changed. It stays available meanwhile and is kept when
+    /// loading fails. Returns whether the asset still exists.
+    pub fn reload<T: Asset>(&mut self, id: impl Into<AssetId<T>>) -> bool {
+        let Some(slot) = self.storage_mut::<T>().slot_mut(id.into().raw) else {
+            return false;
+        };
+        slot.state = LoadState::Loading;
+        true
+    }
+
+    pub fn get<T: Asset>(&self, id: impl Into<AssetId<T>>) -> Option<&T> {
+        self.storage::<T>()?.slot(id.into().raw)?.asset.as_ref()
+    }
+
+    pub fn get_mut<T: Asset>(&mut self, id: impl Into<AssetId<T>>) -> Option<&mut T> {
+        self.storage_mut::<T>()
+            .slot_mut(id.into().raw)?
+            .asset
+            .as_mut()
+    }
+
+    /// `None` once the asset is freed.
+    pub fn load_state<T: Asset>(&self, id: impl Into<AssetId<T>>) -> Option<&LoadState> {
+        Some(&self.storage::<T>()?.slot(id.into().raw)?.state)
+    }
+
+    /// Number of live handles to the asset.
+    pub fn ref_count<T: Asset>(&self, id: impl Into<AssetId<T>>) -> usize {
+        self.storage::<T>()
+            .and_then(|assets| assets.slot(id.into().raw))
+            .map_or(0, |slot| slot.handle.strong_count())
+    }
+
+    /// The loaded assets of a type.
+    pub fn iter<T: Asset>(&self) -> impl Iterator<Item = (AssetId<T>, &T)> {
+        let slots = self.storage::<T>().map_or(&[][..], |assets| &assets.slots);
+        slots.iter().enumerate().filter_map(|(index, slot)| {
+            let id = AssetId {
+                raw: RawId {
+                    index: index as u32,
+                    generation: slot.generation,
+                },
+                marker: PhantomData,
+            };
+            Some((id, slot.asset.as_ref()?))
+        })
+    }
+
+    /// Number of assets of a type, loading and failed ones included.
+    pub fn len<T: Asset>(&self) -> usize {
+        self.storage::<T>()
+            .map_or(0, |assets| assets.slots.len() - assets.free.len())
+    }
+
+    /// Frees the assets whose last handle was dropped since the last call, returns how many.
+    pub fn free_unused(&mut self) -> usize {
+        let mut freed = 0;
+        for (type_id, raw) in self.dropped.try_iter() {
+            if let Some(assets) = self.assets.get_mut(&type_id) {
+                freed += assets.free(raw) as usize;
+            }
+        }
+        freed
+    }
+
+    fn find<T: Asset>(&mut self, key: &AssetKey) -> Option<Handle<T>> {
+        let sender = self.dropped_sender.clone();
+        let assets = self.storage_mut::<T>();
+        let index = *assets.keys.get(key)?;
+        let slot = &mut assets.slots[index as usize];
+        // a failure is tried again once nothing refers to it
+        if slot.handle.strong_count() == 0 && matches!(slot.state, LoadState::Failed(_)) {
+            slot.key = None;
+            assets.keys.remove(key);
+            return None;
+        }
+        let inner = slot.handle.upgrade().unwrap_or_else(|| {
+            // all handles were dropped but the asset is not freed yet, it is revived
+            let inner = Arc::new(HandleInner {
+                type_id: TypeId::of::<T>(),
+                raw: RawId {
+                    index,
+                    generation: slot.generation,
+                },
+                dropped: sender,
+            });
+            slot.handle = Arc::downgrade(&inner);
+            inner
+        });
+        Some(Handle {
+            inner,
+            marker: PhantomData,
+        })
+    }
+
+    fn insert<T: Asset>(
+        &mut self,
+        key: Option<AssetKey>,
+        state: LoadState,
+        asset: Option<T>,
+    ) -> Handle<T> {
+        let sender = self.dropped_sender.clone();
+        let assets = self.storage_mut::<T>();
+        let index = match assets.free.pop() {
+            Some(index) => index,
+            None => {
+                assets.slots.push(Slot {
+                    generation: 0,
+                    state: LoadState::Loading,
+                    asset: None,
+                    key: None,
+                    handle: Weak::new(),
+                });
+                assets.slots.len() as u32 - 1
+            }
+        };
+        if let Some(key) = key.as_ref() {
+            assets.keys.insert(key.clone(), index);
+        }
+        let slot = &mut assets.slots[index as usize];
+        let inner = Arc::new(HandleInner {
+            type_id: TypeId::of::<T>(),
+            raw: RawId {
+                index,
+                generation: slot.generation,
+            },
+            dropped: sender,
+        });
+        slot.state = state;
+        slot.asset = asset;
+        slot.key = key;
+        slot.handle = Arc::downgrade(&inner);
+        Handle {
+            inner,
+            marker: PhantomData,
+        }
+    }
+
+    fn storage<T: Asset>(&self) -> Option<&Assets<T>> {
+        self.assets.get(&TypeId::of::<T>())?.as_any().downcast_ref()
+    }
+
+    fn storage_mut<T: Asset>(&mut self) -> &mut Assets<T> {
+        self.assets
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::<Assets<T>>::default())
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+}
+
+#[allow(unused_imports)]
+mod test {
+    use super::{Asset, AssetKey, AssetServer, LoadState};
+
+    #[test]
+    fn handles_and_sharing() {
+        #[derive(Debug, PartialEq)]
+        struct Text(String);
+        impl Asset for Text {}
+
+        let mut assets = AssetServer::default();
+        let key = AssetKey::labeled("model.gltf", "mesh0/0".to_owned());
+        let first = assets.get_or_add(key.clone(), || Text("first".to_owned()));
+        // the same key gives the asset already there
+        let second = assets.get_or_add(key.clone(), || Text("second".to_owned()));
+        assert_eq!(first, second);
+        assert_eq!(assets.get(&second), Some(&Text("first".to_owned())));
+        assert_eq!(assets.ref_count(&first), 2);
+        let hashed = assets.get_or_add(AssetKey::hash_of("data"), || Text("hashed".to_owned()));
+        assert_ne!(hashed, first);
+        assert_eq!(assets.len::<Text>(), 2);
+
+        // freed once the last handle is gone, not before
+        let id = first.id();
+        drop(first);
+        assert_eq!(assets.free_unused(), 0);
+        assert!(assets.get(id).is_some());
+        drop(second);
+        assert_eq!(assets.free_unused(), 1);
+        assert_eq!(assets.get(id), None);
+        assert_eq!(assets.load_state(id), None);
+        let third = assets.get_or_add(key.clone(), || Text("third".to_owned()));
+        assert_eq!(assets.get(&third), Some(&Text("third".to_owned())));
+        // a slot reused for another asset is not reached through old ids
+        assert_ne!(third.id(), id);
+
+        // asked for again between the drop and the free, the asset is kept
+        let id = third.id();
+        drop(third);
+        let revived = assets.get_or_add(key, || Text("fourth".to_owned()));
+        assert_eq!(revived.id(), id);
+        assert_eq!(assets.free_unused(), 0);
+        assert_eq!(assets.get(&revived), Some(&Text("third".to_owned())));
+
+        // reserved assets are loading until finished
+        let (loading, new) = assets.reserve::<Text>(AssetKey::path("scene.gltf"));
+        assert!(new);
+        assert_eq!(assets.load_state(&loading), Some(&LoadState::Loading));
+        let (same, new) = assets.reserve::<Text>(AssetKey::path("scene.gltf"));
+        assert!(!new && same == loading);
+        assets.finish(&loading, Err("missing file".to_owned()));
+        assert_eq!(
+            assets.load_state(&loading),
+            Some(&LoadState::Failed("missing file".to_owned()))
+        );
+        assert_eq!(assets.get(&loading), None);
+        let failed =
+            assets.get_or_try_add::<Text>(AssetKey::path("broken.gltf"), || Err("no".to_owned()));
+        assert!(matches!(
+            assets.load_state(&failed),
+            Some(LoadState::Failed(_))
+        ));
+        drop(failed);
+        let retried = assets.get_or_try_add(AssetKey::path("broken.gltf"), || {
+            Ok(Text("fixed".to_owned()))
+        });
+        assert_eq!(assets.load_state(&retried), Some(&LoadState::Loaded));
+        assert_eq!(assets.free_unused(), 1);
+        assert!(assets.get(&retried).is_some());
+
+        // a reload keeps the asset until it is replaced, or for good when it fails
+        assert!(assets.reload(&retried));
+        assert_eq!(assets.load_state(&retried), Some(&LoadState::Loading));
+        assert_eq!(assets.get(&retried), Some(&Text("fixed".to_owned())));
+        assets.finish(&retried, Err("half written".to_owned()));
+        assert_eq!(assets.get(&retried), Some(&Text("fixed".to_owned())));
+        assert_eq!(assets.load_state(&retried), Some(&LoadState::Loaded));
+        assets.reload(&retried);
+        assets.finish(&retried, Ok(Text("edited".to_owned())));
+        assert_eq!(assets.get(&retried), Some(&Text("edited".to_owned())));
+        assert_eq!(assets.load_state(&retried), Some(&LoadState::Loaded));
+        drop(revived);
+        assets.free_unused();
+        assert!(!assets.reload(id));
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often `PollWatcher::default` looks at the watched files.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Source of changes of watched files, polled once per frame.
+pub trait WatchBackend {
+    /// Starts reporting changes of the file, also when it is created or removed.
+    fn watch(&mut self, path: &Path);
+    fn unwatch(&mut self, path: &Path);
+    /// The watched files changed since the last poll, each once.
+    fn poll_changes(&mut self) -> Vec<PathBuf>;
+}
+
+/// What a file looked like when last polled, `None` when it did not exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<FileStamp> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(FileStamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Backend comparing the modification time and size of every watched file,
+/// at most once per interval. Works everywhere at the cost of a `stat` per file.
+///
+/// A file written in several steps may be seen half written, it is reported
+/// again once the last write is seen.
+#[derive(Debug)]
+pub struct PollWatcher {
+    interval: Duration,
+    last_poll: Option<Instant>,
+    files: HashMap<PathBuf, Option<FileStamp>>,
+}
+
+impl Default for PollWatcher {
+    fn default() -> Self {
+        PollWatcher::new(DEFAULT_POLL_INTERVAL)
+    }
+}
+
+impl PollWatcher {
+    pub fn new(interval: Duration) -> Self {
+        PollWatcher {
+            interval,
+            last_poll: None,
+            files: HashMap::new(),
+        }
+    }
+}
+
+impl WatchBackend for PollWatcher {
+    fn watch(&mut self, path: &Path) {
+        self.files
+            .entry(path.to_path_buf())
+            .or_insert_with(|| FileStamp::of(path));
+    }
+
+    fn unwatch(&mut self, path: &Path) {
+        self.files.remove(path);
+    }
+
+    fn poll_changes(&mut self) -> Vec<PathBuf> {
+        if self
+            .last_poll
+            .is_some_and(|last_poll| last_poll.elapsed() < self.interval)
+        {
+            return Vec::new();
+        }
+        self.last_poll = Some(Instant::now());
+        let mut changed = Vec::new();
+        for (path, stamp) in self.files.iter_mut() {
+            let current = FileStamp::of(path);
+            if current != *stamp {
+                *stamp = current;
+                changed.push(path.clone());
+            }
+        }
+        // the map's order is random
+        changed.sort();
+        changed
+    }
+}
+
+/// The backend watching files on disk.
+pub fn default_backend() -> Box<dyn WatchBackend> {
+    Box::<PollWatcher>::default()
+}
+
+#[allow(unused_imports)]
+mod test {
+    use std::time::Duration;
+
+    use super::{PollWatcher, WatchBackend};
+
+    #[test]
+    fn poll_watcher_reports_changes() {
+        let dir = std::env::temp_dir().join(format!("ruccolo_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (model, scene) = (dir.join("model.gltf"), dir.join("scene.json"));
+        std::fs::write(&model, "{}").unwrap();
+        let mut watcher = PollWatcher::new(Duration::ZERO);
+        watcher.watch(&model);
+        watcher.watch(&scene);
+        assert!(watcher.poll_changes().is_empty());
+
+        // sizes differ, file systems with coarse modification times still tell
+        std::fs::write(&model, "{\"nodes\": []}").unwrap();
+        assert_eq!(watcher.poll_changes(), vec![model.clone()]);
+        assert!(watcher.poll_changes().is_empty());
+
+        // created and removed files are changes too
+        std::fs::write(&scene, "{}").unwrap();
+        std::fs::remove_file(&model).unwrap();
+        assert_eq!(watcher.poll_changes(), vec![model.clone(), scene.clone()]);
+
+        watcher.unwatch(&scene);
+        std::fs::write(&scene, "{\"objects\": []}").unwrap();
+        assert!(watcher.poll_changes().is_empty());
+
+        // polled at most once per interval
+        let mut watcher = PollWatcher::new(Duration::from_secs(3600));
+        watcher.watch(&scene);
+        assert!(watcher.poll_changes().is_empty());
+        std::fs::write(&scene, "{}").unwrap();
+        assert!(watcher.poll_changes().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1 +1,2 @@
 pub mod asset_system;
+pub mod file_watcher;